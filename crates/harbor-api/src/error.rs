@@ -1,6 +1,6 @@
 //! API error types
 
-use axum::http::StatusCode;
+use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use serde_json::json;
 use thiserror::Error;
@@ -19,6 +19,14 @@ pub enum ApiError {
     #[error("Forbidden")]
     Forbidden,
 
+    /// Registry request without a sufficient token; carries the
+    /// `WWW-Authenticate` challenge pointing clients at the token endpoint
+    #[error("Authentication required")]
+    RegistryUnauthorized { challenge: String },
+
+    #[error("Denied: {0}")]
+    Denied(String),
+
     #[error("Method not allowed")]
     MethodNotAllowed,
 
@@ -49,6 +57,12 @@ impl IntoResponse for ApiError {
                 "Unauthorized".to_string(),
             ),
             ApiError::Forbidden => (StatusCode::FORBIDDEN, "FORBIDDEN", "Forbidden".to_string()),
+            ApiError::RegistryUnauthorized { .. } => (
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHORIZED",
+                "authentication required".to_string(),
+            ),
+            ApiError::Denied(msg) => (StatusCode::FORBIDDEN, "DENIED", msg.clone()),
             ApiError::MethodNotAllowed => (
                 StatusCode::METHOD_NOT_ALLOWED,
                 "METHOD_NOT_ALLOWED",
@@ -69,6 +83,19 @@ impl IntoResponse for ApiError {
                 harbor_core::CoreError::InvalidDigest(msg) => {
                     (StatusCode::BAD_REQUEST, "DIGEST_INVALID", msg.clone())
                 }
+                harbor_core::CoreError::RangeNotSatisfiable { .. } => (
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    "RANGE_INVALID",
+                    e.to_string(),
+                ),
+                harbor_core::CoreError::Unsupported(msg) => {
+                    (StatusCode::METHOD_NOT_ALLOWED, "UNSUPPORTED", msg.clone())
+                }
+                harbor_core::CoreError::UploadRangeInvalid { .. } => (
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    "BLOB_UPLOAD_INVALID",
+                    e.to_string(),
+                ),
                 _ => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     "INTERNAL_ERROR",
@@ -113,6 +140,20 @@ impl IntoResponse for ApiError {
             }]
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let ApiError::RegistryUnauthorized { challenge } = &self
+            && let Ok(value) = HeaderValue::from_str(challenge)
+        {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, value);
+        }
+        if let ApiError::Core(harbor_core::CoreError::RangeNotSatisfiable { size: Some(size) }) =
+            &self
+            && let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", size))
+        {
+            response.headers_mut().insert(header::CONTENT_RANGE, value);
+        }
+        response
     }
 }
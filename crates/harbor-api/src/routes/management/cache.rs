@@ -6,6 +6,7 @@ use axum::{
     http::StatusCode,
     routing::{delete, get, post},
 };
+use harbor_core::{ConsistencyChecker, FsckOptions, FsckReport};
 use harbor_db::{repository::CacheEntryQuery, utils::format_bytes};
 use tracing::{debug, info};
 
@@ -15,7 +16,7 @@ use crate::state::AppState;
 use super::auth::{RequireAdmin, RequireAuth};
 use super::types::{
     CacheEntriesListResponse, CacheEntriesQuery, CacheEntryResponse, CacheStatsResponse,
-    CachedRepositoriesResponse,
+    CachedRepositoriesResponse, FsckQuery,
 };
 
 // ==================== Cache Routes ====================
@@ -133,7 +134,7 @@ async fn delete_cache_entry(
 ) -> Result<StatusCode, ApiError> {
     debug!("Deleting cache entry: {}", digest);
 
-    let deleted = state.cache.delete(&digest).await?;
+    let deleted = state.cache.purge(&digest).await?;
 
     if deleted {
         info!("Deleted cache entry: {}", digest);
@@ -171,6 +172,26 @@ async fn cleanup_cache(
     })))
 }
 
+/// POST /api/v1/cache/fsck (Admin only)
+async fn fsck_cache(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Query(query): Query<FsckQuery>,
+) -> Result<Json<FsckReport>, ApiError> {
+    info!("Running cache consistency check (repair: {})", query.repair);
+
+    let checker = ConsistencyChecker::new(state.cache.clone(), state.registry.clone());
+    let report = checker
+        .run(FsckOptions {
+            repair: query.repair,
+            verify_digests: query.verify,
+            bytes_per_sec: query.rate_limit,
+        })
+        .await?;
+
+    Ok(Json(report))
+}
+
 /// Create cache management routes
 pub fn routes() -> Router<AppState> {
     Router::new()
@@ -181,4 +202,5 @@ pub fn routes() -> Router<AppState> {
         .route("/api/v1/cache/entries/{digest}", delete(delete_cache_entry))
         .route("/api/v1/cache", delete(clear_cache))
         .route("/api/v1/cache/cleanup", post(cleanup_cache))
+        .route("/api/v1/cache/fsck", post(fsck_cache))
 }
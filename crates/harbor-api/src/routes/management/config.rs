@@ -146,7 +146,7 @@ fn validate_config_semantics(content: &toml::Value) -> Result<(), String> {
         && let Some(backend) = storage.get("backend")
         && let Some(backend_str) = backend.as_str()
     {
-        let valid_backends = ["local", "s3"];
+        let valid_backends = ["local", "s3", "gcs", "azure", "tiered"];
         if !valid_backends.contains(&backend_str) {
             return Err(format!(
                 "storage.backend must be one of {:?}, got '{}'",
@@ -180,7 +180,7 @@ fn build_config_schema() -> ConfigSchemaResponse {
         ConfigGroup {
             id: "storage".to_string(),
             label: "Storage".to_string(),
-            description: "Storage backend configuration (local or S3)".to_string(),
+            description: "Storage backend configuration (local, S3, GCS or Azure)".to_string(),
         },
         ConfigGroup {
             id: "database".to_string(),
@@ -338,6 +338,18 @@ fn build_config_schema() -> ConfigSchemaResponse {
                     value: "s3".to_string(),
                     label: "S3 Compatible".to_string(),
                 },
+                ConfigOption {
+                    value: "gcs".to_string(),
+                    label: "Google Cloud Storage".to_string(),
+                },
+                ConfigOption {
+                    value: "azure".to_string(),
+                    label: "Azure Blob Storage".to_string(),
+                },
+                ConfigOption {
+                    value: "tiered".to_string(),
+                    label: "Tiered (Local + S3)".to_string(),
+                },
             ]),
             group: "storage".to_string(),
         },
@@ -422,6 +434,136 @@ fn build_config_schema() -> ConfigSchemaResponse {
             options: None,
             group: "storage".to_string(),
         },
+        ConfigSchemaField {
+            key: "storage.gcs.bucket".to_string(),
+            label: "GCS Bucket".to_string(),
+            description: "GCS bucket name".to_string(),
+            field_type: "string".to_string(),
+            default_value: Some("harbor-cache".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.gcs.service_account_path".to_string(),
+            label: "GCS Service Account".to_string(),
+            description: "Path to a service account JSON file".to_string(),
+            field_type: "string".to_string(),
+            default_value: None,
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.gcs.prefix".to_string(),
+            label: "GCS Prefix".to_string(),
+            description: "Optional prefix for all objects".to_string(),
+            field_type: "string".to_string(),
+            default_value: None,
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.gcs.allow_http".to_string(),
+            label: "GCS Allow HTTP".to_string(),
+            description: "Allow HTTP (not HTTPS) for GCS connections (fake-gcs-server)".to_string(),
+            field_type: "boolean".to_string(),
+            default_value: Some("false".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.account".to_string(),
+            label: "Azure Account".to_string(),
+            description: "Azure storage account name".to_string(),
+            field_type: "string".to_string(),
+            default_value: None,
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.container".to_string(),
+            label: "Azure Container".to_string(),
+            description: "Azure blob container name".to_string(),
+            field_type: "string".to_string(),
+            default_value: Some("harbor-cache".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.access_key".to_string(),
+            label: "Azure Access Key".to_string(),
+            description: "Azure storage account access key".to_string(),
+            field_type: "password".to_string(),
+            default_value: None,
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.endpoint".to_string(),
+            label: "Azure Endpoint".to_string(),
+            description: "Custom blob service endpoint".to_string(),
+            field_type: "string".to_string(),
+            default_value: None,
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.use_emulator".to_string(),
+            label: "Use Azurite".to_string(),
+            description: "Use the Azurite emulator".to_string(),
+            field_type: "boolean".to_string(),
+            default_value: Some("false".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.prefix".to_string(),
+            label: "Azure Prefix".to_string(),
+            description: "Optional prefix for all objects".to_string(),
+            field_type: "string".to_string(),
+            default_value: None,
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.azure.allow_http".to_string(),
+            label: "Azure Allow HTTP".to_string(),
+            description: "Allow HTTP (not HTTPS) for Azure connections".to_string(),
+            field_type: "boolean".to_string(),
+            default_value: Some("false".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.tiered.path".to_string(),
+            label: "Hot Tier Path".to_string(),
+            description: "Local directory of the hot tier (tiered backend)".to_string(),
+            field_type: "string".to_string(),
+            default_value: Some("./data/hot".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
+        ConfigSchemaField {
+            key: "storage.tiered.max_size".to_string(),
+            label: "Hot Tier Max Size".to_string(),
+            description: "Maximum size of the hot tier in bytes (tiered backend)".to_string(),
+            field_type: "number".to_string(),
+            default_value: Some("10737418240".to_string()),
+            required: false,
+            options: None,
+            group: "storage".to_string(),
+        },
         // Database
         ConfigSchemaField {
             key: "database.path".to_string(),
@@ -9,6 +9,7 @@ mod auth;
 mod cache;
 mod config;
 mod logs;
+mod permissions;
 mod types;
 mod upstreams;
 mod users;
@@ -21,5 +22,6 @@ pub fn routes() -> Router<AppState> {
         .merge(cache::routes())
         .merge(config::routes())
         .merge(logs::routes())
+        .merge(permissions::routes())
         .merge(upstreams::routes())
 }
@@ -0,0 +1,348 @@
+//! Repository permission management routes
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post, put},
+};
+use harbor_auth::scope::{DELETE, PULL, PUSH, role_can_use_grant};
+use harbor_core::validate_pattern;
+use harbor_db::{NewRepositoryPermission, RepositoryPermission, UpdateRepositoryPermission, User};
+use tracing::{debug, info};
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+use super::auth::RequireAdmin;
+use super::types::{
+    CreatePermissionRequest, PermissionResponse, UpdatePermissionRequest, UserAccessBody,
+    UserGroupsBody,
+};
+
+/// Maximum length for a group name
+const MAX_GROUP_NAME_LENGTH: usize = 64;
+
+// ==================== Validation ====================
+
+/// Validate the actions of a grant
+fn validate_actions(actions: &[String]) -> Result<(), ApiError> {
+    if actions.is_empty() {
+        return Err(ApiError::BadRequest(
+            "At least one action is required".to_string(),
+        ));
+    }
+
+    for action in actions {
+        if ![PULL, PUSH, DELETE, "*"].contains(&action.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid action '{}': expected pull, push, delete or *",
+                action
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reject actions a user's role can never use, so that no grant is stored
+/// without effect. Group grants may serve members of any role.
+fn validate_user_actions(user: &User, actions: &[String]) -> Result<(), ApiError> {
+    match actions
+        .iter()
+        .find(|action| !role_can_use_grant(&user.role, action))
+    {
+        Some(action) => Err(ApiError::BadRequest(format!(
+            "Cannot grant '{}' to {}: the {} role does not allow it",
+            action,
+            user.username,
+            user.role.as_str()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Validate a group name (alphanumeric, dash, underscore and dot)
+fn validate_group_name(name: &str) -> Result<(), ApiError> {
+    if name.is_empty() || name.len() > MAX_GROUP_NAME_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "Group name must be 1-{} characters",
+            MAX_GROUP_NAME_LENGTH
+        )));
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Group name contains invalid characters: {}",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+fn to_response(permission: RepositoryPermission) -> PermissionResponse {
+    PermissionResponse {
+        id: permission.id,
+        user_id: permission.user_id,
+        group: permission.group_name,
+        pattern: permission.pattern,
+        actions: permission.actions,
+        created_at: permission.created_at.to_rfc3339(),
+    }
+}
+
+// ==================== Permission Routes ====================
+
+/// GET /api/v1/permissions (Admin only)
+async fn list_permissions(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<PermissionResponse>>, ApiError> {
+    let permissions = state.db.list_repository_permissions().await?;
+    Ok(Json(permissions.into_iter().map(to_response).collect()))
+}
+
+/// POST /api/v1/permissions (Admin only)
+async fn create_permission(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Json(request): Json<CreatePermissionRequest>,
+) -> Result<(StatusCode, Json<PermissionResponse>), ApiError> {
+    debug!("Creating permission for pattern: {}", request.pattern);
+
+    validate_pattern(&request.pattern).map_err(ApiError::BadRequest)?;
+    validate_actions(&request.actions)?;
+    match (&request.user_id, &request.group) {
+        (Some(user_id), None) => {
+            let user = state
+                .db
+                .get_user_by_id(*user_id)
+                .await?
+                .ok_or_else(|| ApiError::NotFound(format!("User: {}", user_id)))?;
+            validate_user_actions(&user, &request.actions)?;
+        }
+        (None, Some(group)) => validate_group_name(group)?,
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Exactly one of user_id and group is required".to_string(),
+            ));
+        }
+    }
+
+    let permission = state
+        .db
+        .insert_repository_permission(NewRepositoryPermission {
+            user_id: request.user_id,
+            group_name: request.group,
+            pattern: request.pattern,
+            actions: request.actions,
+        })
+        .await?;
+
+    info!(
+        "Created permission {} for pattern {}",
+        permission.id, permission.pattern
+    );
+
+    Ok((StatusCode::CREATED, Json(to_response(permission))))
+}
+
+/// GET /api/v1/permissions/:id (Admin only)
+async fn get_permission(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<PermissionResponse>, ApiError> {
+    let permission = state
+        .db
+        .get_repository_permission(id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Permission: {}", id)))?;
+
+    Ok(Json(to_response(permission)))
+}
+
+/// PUT /api/v1/permissions/:id (Admin only)
+async fn update_permission(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(request): Json<UpdatePermissionRequest>,
+) -> Result<Json<PermissionResponse>, ApiError> {
+    debug!("Updating permission: {}", id);
+
+    if let Some(pattern) = &request.pattern {
+        validate_pattern(pattern).map_err(ApiError::BadRequest)?;
+    }
+    if let Some(actions) = &request.actions {
+        validate_actions(actions)?;
+        let permission = state
+            .db
+            .get_repository_permission(id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Permission: {}", id)))?;
+        if let Some(user_id) = permission.user_id
+            && let Some(user) = state.db.get_user_by_id(user_id).await?
+        {
+            validate_user_actions(&user, actions)?;
+        }
+    }
+
+    let permission = state
+        .db
+        .update_repository_permission(
+            id,
+            UpdateRepositoryPermission {
+                pattern: request.pattern,
+                actions: request.actions,
+            },
+        )
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Permission: {}", id)))?;
+
+    info!("Updated permission: {}", id);
+
+    Ok(Json(to_response(permission)))
+}
+
+/// DELETE /api/v1/permissions/:id (Admin only)
+async fn delete_permission(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    debug!("Deleting permission: {}", id);
+
+    if state.db.delete_repository_permission(id).await? {
+        info!("Deleted permission: {}", id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound(format!("Permission: {}", id)))
+    }
+}
+
+// ==================== User Group Routes ====================
+
+/// GET /api/v1/users/:id/groups (Admin only)
+async fn get_user_groups(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<UserGroupsBody>, ApiError> {
+    state
+        .db
+        .get_user_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("User: {}", id)))?;
+
+    let groups = state.db.get_user_groups(id).await?;
+    Ok(Json(UserGroupsBody { groups }))
+}
+
+/// PUT /api/v1/users/:id/groups (Admin only)
+async fn set_user_groups(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(request): Json<UserGroupsBody>,
+) -> Result<Json<UserGroupsBody>, ApiError> {
+    state
+        .db
+        .get_user_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("User: {}", id)))?;
+
+    for group in &request.groups {
+        validate_group_name(group)?;
+    }
+
+    state.db.set_user_groups(id, &request.groups).await?;
+    info!("Updated groups for user {}: {:?}", id, request.groups);
+
+    let groups = state.db.get_user_groups(id).await?;
+    Ok(Json(UserGroupsBody { groups }))
+}
+
+// ==================== User Access Routes ====================
+
+/// GET /api/v1/users/:id/access (Admin only)
+async fn get_user_access(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<UserAccessBody>, ApiError> {
+    state
+        .db
+        .get_user_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("User: {}", id)))?;
+
+    let restricted = state.db.is_user_restricted(id).await?;
+    Ok(Json(UserAccessBody { restricted }))
+}
+
+/// PUT /api/v1/users/:id/access (Admin only)
+async fn set_user_access(
+    _admin: RequireAdmin,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(request): Json<UserAccessBody>,
+) -> Result<Json<UserAccessBody>, ApiError> {
+    state
+        .db
+        .get_user_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("User: {}", id)))?;
+
+    state.db.set_user_restricted(id, request.restricted).await?;
+    info!(
+        "Updated repository access of user {}: restricted={}",
+        id, request.restricted
+    );
+
+    Ok(Json(request))
+}
+
+/// Create permission management routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/v1/permissions", get(list_permissions))
+        .route("/api/v1/permissions", post(create_permission))
+        .route("/api/v1/permissions/{id}", get(get_permission))
+        .route("/api/v1/permissions/{id}", put(update_permission))
+        .route("/api/v1/permissions/{id}", delete(delete_permission))
+        .route("/api/v1/users/{id}/groups", get(get_user_groups))
+        .route("/api/v1/users/{id}/groups", put(set_user_groups))
+        .route("/api/v1/users/{id}/access", get(get_user_access))
+        .route("/api/v1/users/{id}/access", put(set_user_access))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harbor_db::UserRole;
+
+    #[test]
+    fn test_user_grants_must_fit_the_role() {
+        let mut user = User {
+            id: 1,
+            username: "ci".to_string(),
+            password_hash: String::new(),
+            role: UserRole::ReadOnly,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let actions = |actions: &[&str]| actions.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(validate_user_actions(&user, &actions(&[PULL, "*"])).is_ok());
+        assert!(validate_user_actions(&user, &actions(&[PULL, DELETE])).is_err());
+        assert!(validate_user_actions(&user, &actions(&[PUSH])).is_err());
+
+        user.role = UserRole::ReadWrite;
+        assert!(validate_user_actions(&user, &actions(&[PULL, PUSH, DELETE])).is_ok());
+    }
+}
@@ -1,6 +1,16 @@
 //! Request/Response DTOs for management API
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Deserialize an optional field that can also be cleared: an absent field
+/// is `None`, an explicit `null` is `Some(None)`
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
 
 // ==================== Auth Types ====================
 
@@ -45,6 +55,48 @@ pub struct UserResponse {
     pub updated_at: String,
 }
 
+// ==================== Permission Types ====================
+
+/// Create repository permission request (exactly one of `user_id` and `group`)
+#[derive(Deserialize)]
+pub struct CreatePermissionRequest {
+    pub user_id: Option<i64>,
+    pub group: Option<String>,
+    pub pattern: String,
+    pub actions: Vec<String>,
+}
+
+/// Update repository permission request
+#[derive(Deserialize)]
+pub struct UpdatePermissionRequest {
+    pub pattern: Option<String>,
+    pub actions: Option<Vec<String>>,
+}
+
+/// Repository permission response
+#[derive(Serialize)]
+pub struct PermissionResponse {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub group: Option<String>,
+    pub pattern: String,
+    pub actions: Vec<String>,
+    pub created_at: String,
+}
+
+/// User group membership request/response
+#[derive(Serialize, Deserialize)]
+pub struct UserGroupsBody {
+    pub groups: Vec<String>,
+}
+
+/// Repository access mode of a user request/response
+#[derive(Serialize, Deserialize)]
+pub struct UserAccessBody {
+    /// Whether the user is limited to their repository grants
+    pub restricted: bool,
+}
+
 // ==================== Cache Types ====================
 
 /// Cache statistics response
@@ -118,6 +170,26 @@ pub struct CachedRepositoriesResponse {
     pub repositories: Vec<String>,
 }
 
+/// Consistency check query parameters
+#[derive(Deserialize)]
+pub struct FsckQuery {
+    /// Repair the discrepancies found
+    #[serde(default)]
+    pub repair: bool,
+    /// Hash stored content against its digest (reads everything)
+    #[serde(default)]
+    pub verify: bool,
+    /// Maximum average read rate while hashing (0 = unlimited)
+    #[serde(default = "default_fsck_rate_limit")]
+    pub rate_limit: u64,
+}
+
+/// Hashing on a live instance competes with pulls, so it is paced like the
+/// scrubber by default
+fn default_fsck_rate_limit() -> u64 {
+    10 * 1024 * 1024
+}
+
 // ==================== Config Types ====================
 
 /// Config entry response
@@ -267,8 +339,14 @@ pub struct UpstreamResponse {
     pub priority: i32,
     pub enabled: bool,
     pub cache_isolation: String,
+    /// Size limit in bytes of an isolated cache (None for shared upstreams)
+    pub cache_max_size: Option<u64>,
     pub is_default: bool,
     pub has_credentials: bool,
+    /// Tag freshness TTL in seconds (None = always revalidate)
+    pub tag_ttl_secs: Option<u64>,
+    /// Upstreams to fail over to, in order
+    pub mirrors: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -292,11 +370,20 @@ pub struct CreateUpstreamRequest {
     pub enabled: bool,
     #[serde(default = "default_cache_isolation")]
     pub cache_isolation: String,
+    /// Size limit in bytes of an isolated cache
+    #[serde(default)]
+    pub cache_max_size: Option<u64>,
     #[serde(default)]
     pub is_default: bool,
     /// Route patterns for this upstream
     #[serde(default)]
     pub routes: Vec<CreateRouteRequest>,
+    /// Tag freshness TTL in seconds
+    #[serde(default)]
+    pub tag_ttl_secs: Option<u64>,
+    /// Upstreams to fail over to, in order
+    #[serde(default)]
+    pub mirrors: Vec<String>,
 }
 
 fn default_priority() -> i32 {
@@ -317,6 +404,9 @@ pub struct CreateRouteRequest {
     pub pattern: String,
     #[serde(default = "default_priority")]
     pub priority: i32,
+    /// Tag freshness TTL in seconds for repositories matching this route
+    #[serde(default)]
+    pub tag_ttl_secs: Option<u64>,
 }
 
 /// Project configuration for update request
@@ -356,12 +446,22 @@ pub struct UpdateUpstreamRequest {
     pub enabled: Option<bool>,
     #[serde(default)]
     pub cache_isolation: Option<String>,
+    /// Size limit in bytes of an isolated cache (`null` clears it)
+    #[serde(default, deserialize_with = "nullable")]
+    pub cache_max_size: Option<Option<u64>>,
     #[serde(default)]
     pub is_default: Option<bool>,
     /// Projects configuration (multi-project mode)
     /// If provided, replaces all existing projects
     #[serde(default)]
     pub projects: Option<Vec<UpdateUpstreamProjectRequest>>,
+    /// Tag freshness TTL in seconds (`null` clears it)
+    #[serde(default, deserialize_with = "nullable")]
+    pub tag_ttl_secs: Option<Option<u64>>,
+    /// Upstreams to fail over to, in order
+    /// If provided, replaces the existing mirror list
+    #[serde(default)]
+    pub mirrors: Option<Vec<String>>,
 }
 
 /// Upstream health response
@@ -373,6 +473,10 @@ pub struct UpstreamHealthResponse {
     pub last_check: String,
     pub last_error: Option<String>,
     pub consecutive_failures: u32,
+    /// Circuit breaker state: "closed", "open" or "half_open"
+    pub circuit_state: String,
+    /// When the circuit last opened (while open or half-open)
+    pub opened_at: Option<String>,
 }
 
 /// Upstream route response
@@ -382,6 +486,7 @@ pub struct UpstreamRouteResponse {
     pub upstream_id: i64,
     pub pattern: String,
     pub priority: i32,
+    pub tag_ttl_secs: Option<u64>,
     pub created_at: String,
 }
 
@@ -10,10 +10,11 @@ use axum::{
     routing::{delete, get, post, put},
 };
 use harbor_core::{
-    MAX_PROJECTS_PER_UPSTREAM, UpstreamConfig, UpstreamProjectConfig, UpstreamRouteConfig,
-    validate_pattern, validate_project_name,
+    MAX_PROJECTS_PER_UPSTREAM, UpstreamConfig, UpstreamHealth, UpstreamProjectConfig,
+    UpstreamRetryConfig, UpstreamRouteConfig, validate_pattern, validate_project_name,
 };
-use harbor_proxy::{HarborClient, HarborClientConfig};
+use harbor_db::utils::format_bytes;
+use harbor_proxy::{HarborClient, HarborClientConfig, RetryPolicy};
 use std::net::{IpAddr, ToSocketAddrs};
 use std::sync::atomic::{AtomicU64, Ordering};
 use tracing::{debug, info, warn};
@@ -317,6 +318,27 @@ fn validate_route_pattern(pattern: &str) -> Result<(), ApiError> {
     Ok(())
 }
 
+/// Validate the mirror list of an upstream
+fn validate_mirrors(name: &str, mirrors: &[String]) -> Result<(), ApiError> {
+    let mut seen_names = std::collections::HashSet::new();
+    for mirror in mirrors {
+        validate_upstream_name(mirror)?;
+        if mirror == name {
+            return Err(ApiError::BadRequest(
+                "Upstream cannot be its own mirror".to_string(),
+            ));
+        }
+        if !seen_names.insert(mirror) {
+            return Err(ApiError::BadRequest(format!(
+                "Duplicate mirror: '{}'",
+                mirror
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 /// Validate projects array for update request
 fn validate_projects(
     projects: &[super::types::UpdateUpstreamProjectRequest],
@@ -399,13 +421,29 @@ fn upstream_config_to_response(config: &UpstreamConfig, idx: usize) -> UpstreamR
         priority: config.priority,
         enabled: config.enabled,
         cache_isolation: config.cache_isolation.clone(),
+        cache_max_size: config.cache_max_size,
         is_default: config.is_default,
         has_credentials: config.username.is_some(),
+        tag_ttl_secs: config.tag_ttl_secs,
+        mirrors: config.mirrors.clone(),
         created_at: chrono::Utc::now().to_rfc3339(), // Not tracked in config
         updated_at: chrono::Utc::now().to_rfc3339(), // Not tracked in config
     }
 }
 
+fn health_to_response(health: UpstreamHealth) -> UpstreamHealthResponse {
+    UpstreamHealthResponse {
+        upstream_id: 0, // Not used with config-based storage
+        name: health.name,
+        healthy: health.healthy,
+        last_check: health.last_check.to_rfc3339(),
+        last_error: health.last_error,
+        consecutive_failures: health.consecutive_failures,
+        circuit_state: health.circuit_state.as_str().to_string(),
+        opened_at: health.opened_at.map(|at| at.to_rfc3339()),
+    }
+}
+
 fn route_config_to_response(
     route: &UpstreamRouteConfig,
     _upstream_name: &str,
@@ -416,6 +454,7 @@ fn route_config_to_response(
         upstream_id: 0, // Not used with config-based storage
         pattern: route.pattern.clone(),
         priority: route.priority,
+        tag_ttl_secs: route.tag_ttl_secs,
         created_at: chrono::Utc::now().to_rfc3339(),
     }
 }
@@ -459,6 +498,7 @@ async fn create_upstream(
     for route in &request.routes {
         validate_route_pattern(&route.pattern)?;
     }
+    validate_mirrors(&request.name, &request.mirrors)?;
 
     // Check for duplicate name
     if state
@@ -479,6 +519,7 @@ async fn create_upstream(
         .map(|r| UpstreamRouteConfig {
             pattern: r.pattern.clone(),
             priority: r.priority,
+            tag_ttl_secs: r.tag_ttl_secs,
         })
         .collect();
 
@@ -494,9 +535,14 @@ async fn create_upstream(
         priority: request.priority,
         enabled: request.enabled,
         cache_isolation: request.cache_isolation,
+        cache_max_size: request.cache_max_size,
         is_default: request.is_default,
         routes,
+        tag_ttl_secs: request.tag_ttl_secs,
+        mirrors: request.mirrors,
+        retry: UpstreamRetryConfig::default(), // Retry policy managed via config file
     };
+    upstream_config.validate().map_err(ApiError::BadRequest)?;
 
     // Add to config and save
     state
@@ -564,6 +610,9 @@ async fn update_upstream(
     if let Some(ref projects) = request.projects {
         validate_projects(projects)?;
     }
+    if let Some(ref mirrors) = request.mirrors {
+        validate_mirrors(&name, mirrors)?;
+    }
 
     // Get existing upstream
     let existing = state
@@ -602,9 +651,14 @@ async fn update_upstream(
         priority: request.priority.unwrap_or(existing.priority),
         enabled: request.enabled.unwrap_or(existing.enabled),
         cache_isolation: request.cache_isolation.unwrap_or(existing.cache_isolation),
+        cache_max_size: request.cache_max_size.unwrap_or(existing.cache_max_size),
         is_default: request.is_default.unwrap_or(existing.is_default),
         routes: existing.routes, // Routes managed separately
+        tag_ttl_secs: request.tag_ttl_secs.unwrap_or(existing.tag_ttl_secs),
+        mirrors: request.mirrors.unwrap_or(existing.mirrors),
+        retry: existing.retry,
     };
+    updated.validate().map_err(ApiError::BadRequest)?;
 
     // Update config and save
     state
@@ -699,6 +753,7 @@ async fn add_upstream_route(
     let route = UpstreamRouteConfig {
         pattern: request.pattern.clone(),
         priority: request.priority,
+        tag_ttl_secs: request.tag_ttl_secs,
     };
     upstream.routes.push(route.clone());
 
@@ -792,14 +847,7 @@ async fn get_upstream_health(
             }
         })?;
 
-    Ok(Json(UpstreamHealthResponse {
-        upstream_id: 0, // Not used with config-based storage
-        name: health.name,
-        healthy: health.healthy,
-        last_check: health.last_check.to_rfc3339(),
-        last_error: health.last_error,
-        consecutive_failures: health.consecutive_failures,
-    }))
+    Ok(Json(health_to_response(health)))
 }
 
 /// GET /api/v1/upstreams/health (Admin only) - Get health for all upstreams
@@ -812,17 +860,8 @@ async fn get_all_upstreams_health(
     // Use UpstreamManager's check_all_health which uses cached clients
     let health_results = state.upstream_manager.check_all_health().await;
 
-    let responses: Vec<UpstreamHealthResponse> = health_results
-        .into_iter()
-        .map(|health| UpstreamHealthResponse {
-            upstream_id: 0,
-            name: health.name,
-            healthy: health.healthy,
-            last_check: health.last_check.to_rfc3339(),
-            last_error: health.last_error,
-            consecutive_failures: health.consecutive_failures,
-        })
-        .collect();
+    let responses: Vec<UpstreamHealthResponse> =
+        health_results.into_iter().map(health_to_response).collect();
 
     Ok(Json(responses))
 }
@@ -844,6 +883,7 @@ async fn test_upstream_connection(
         username: request.username,
         password: request.password,
         skip_tls_verify: request.skip_tls_verify,
+        retry: RetryPolicy::default(),
     };
 
     match HarborClient::new(config) {
@@ -880,14 +920,19 @@ async fn get_upstream_stats(
         .get_upstream_by_name(&name)
         .ok_or_else(|| ApiError::NotFound(format!("Upstream: {}", name)))?;
 
-    // For now, return empty stats since we don't have upstream-specific stats in the new model
-    // TODO: Implement upstream-specific cache stats if needed
+    // Upstreams added at runtime are registered in the database when they
+    // first serve content; hits and misses are only counted cache-wide
+    let stats = match state.db.get_upstream_by_name(&name).await? {
+        Some(upstream) => state.db.get_cache_stats_by_upstream(upstream.id).await?,
+        None => Default::default(),
+    };
+
     Ok(Json(super::types::CacheStatsResponse {
-        total_size: 0,
-        total_size_human: "0 B".to_string(),
-        entry_count: 0,
-        manifest_count: 0,
-        blob_count: 0,
+        total_size: stats.total_size,
+        total_size_human: format_bytes(stats.total_size),
+        entry_count: stats.entry_count,
+        manifest_count: stats.manifest_count,
+        blob_count: stats.blob_count,
         hit_count: 0,
         miss_count: 0,
         hit_rate: 0.0,
@@ -4,6 +4,7 @@ mod health;
 mod management;
 pub mod metrics;
 mod registry;
+mod token;
 
 use axum::{
     Router,
@@ -48,6 +49,8 @@ pub fn create_router(state: AppState, metrics_handle: Option<Arc<MetricsHandle>>
         .merge(health::routes())
         // OCI Distribution API (v2)
         .merge(registry::routes())
+        // Registry token endpoint (docker login)
+        .merge(token::routes())
         // Management API
         .merge(management::routes())
         .with_state(state)
@@ -5,12 +5,17 @@ use axum::{
     extract::{Path, Query, State},
     http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
-    routing::{get, head, patch, post, put},
+    routing::{delete, get, head, patch, post, put},
 };
 use bytes::Bytes;
 use serde::Deserialize;
 use tracing::{debug, warn};
 
+use harbor_auth::scope::{DELETE, PULL, PUSH};
+use harbor_core::manifest::OCI_INDEX_MEDIA_TYPE;
+use harbor_core::{ByteRange, ChunkRange, CoreError, upload_range};
+
+use super::token::{authorize, authorize_catalog};
 use crate::error::ApiError;
 use crate::state::AppState;
 
@@ -152,6 +157,27 @@ pub struct UploadCompleteQuery {
     digest: Option<String>,
 }
 
+/// Query parameters for tag listing pagination
+#[derive(Deserialize)]
+pub struct TagListQuery {
+    n: Option<usize>,
+    last: Option<String>,
+}
+
+/// Query parameters for the referrers API
+#[derive(Deserialize)]
+pub struct ReferrersQuery {
+    #[serde(rename = "artifactType")]
+    artifact_type: Option<String>,
+}
+
+/// Query parameters for catalog pagination
+#[derive(Deserialize)]
+pub struct CatalogQuery {
+    n: Option<usize>,
+    last: Option<String>,
+}
+
 /// Query parameters for blob mount
 #[derive(Deserialize)]
 pub struct MountQuery {
@@ -159,16 +185,111 @@ pub struct MountQuery {
     from: Option<String>,
 }
 
+// ==================== Upload Sessions ====================
+
+/// Parse the `Content-Range` of an upload chunk, if the client sent one
+fn chunk_range(headers: &HeaderMap) -> Result<Option<ChunkRange>, ApiError> {
+    let Some(value) = headers.get(header::CONTENT_RANGE) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(ChunkRange::parse)
+        .map(Some)
+        .ok_or_else(|| ApiError::BadRequest("Invalid Content-Range header".to_string()))
+}
+
+/// Set the headers that tell a client where and from which offset to
+/// continue an upload session
+fn set_upload_headers(headers: &mut HeaderMap, name: &str, session_id: &str, received: u64) {
+    let location = format!("/v2/{}/blobs/uploads/{}", name, session_id);
+    headers.insert(header::LOCATION, HeaderValue::from_str(&location).unwrap());
+    headers.insert(
+        "Docker-Upload-UUID",
+        HeaderValue::from_str(session_id).unwrap(),
+    );
+    headers.insert(
+        header::RANGE,
+        HeaderValue::from_str(&upload_range(received)).unwrap(),
+    );
+}
+
+/// Append a chunk to an upload session, answering out-of-order chunks
+/// with 416 and the offset the client should resume from
+async fn append_chunk(
+    state: &AppState,
+    name: &str,
+    session_id: &str,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Result<i64, Response>, ApiError> {
+    let range = chunk_range(headers)?;
+    match state.registry.append_upload(session_id, body, range).await {
+        Ok(size) => Ok(Ok(size)),
+        Err(CoreError::UploadRangeInvalid { offset }) => {
+            let mut response =
+                ApiError::from(CoreError::UploadRangeInvalid { offset }).into_response();
+            set_upload_headers(response.headers_mut(), name, session_id, offset);
+            Ok(Err(response))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
 // ==================== Version Check ====================
 
 /// GET /v2/ - Version check
-async fn version_check() -> Response {
-    (
+///
+/// With auth enabled this answers unauthenticated clients with the bearer
+/// challenge that starts the token flow.
+async fn version_check(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    authorize(&state, &headers, None, PULL).await?;
+
+    Ok((
         StatusCode::OK,
         [(header::CONTENT_TYPE, "application/json")],
         "{}",
     )
-        .into_response()
+        .into_response())
+}
+
+// ==================== Catalog ====================
+
+/// GET /v2/_catalog - List repositories the caller may pull
+async fn catalog(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<CatalogQuery>,
+) -> Result<Response, ApiError> {
+    if let Some(ref last) = query.last {
+        validate_repository_name(last)?;
+    }
+
+    let filter = authorize_catalog(&state, &headers).await?;
+
+    debug!("GET catalog (n={:?}, last={:?})", query.n, query.last);
+    let list = state
+        .registry
+        .list_repositories(query.n, query.last.as_deref(), |name| filter.allows(name))
+        .await?;
+
+    let body = serde_json::json!({ "repositories": list.repositories });
+    let mut response = (StatusCode::OK, axum::Json(body)).into_response();
+    if let Some(last) = list.next_last {
+        let mut link = "/v2/_catalog?".to_string();
+        if let Some(n) = query.n {
+            link.push_str(&format!("n={}&", n));
+        }
+        link.push_str(&format!("last={}", last));
+        if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"next\"", link)) {
+            response.headers_mut().insert(header::LINK, value);
+        }
+    }
+    Ok(response)
 }
 
 // ==================== Routes ====================
@@ -178,12 +299,15 @@ pub fn routes() -> Router<AppState> {
     Router::new()
         // Version check
         .route("/v2/", get(version_check))
+        // Catalog (static route takes precedence over the wildcard)
+        .route("/v2/_catalog", get(catalog))
         // Manifests (using wildcard to capture multi-segment repo names like library/alpine)
         .route("/v2/{*path}", get(handle_get_or_head_request))
         .route("/v2/{*path}", head(handle_get_or_head_request))
         .route("/v2/{*path}", put(handle_put_request))
         .route("/v2/{*path}", post(handle_post_request))
         .route("/v2/{*path}", patch(handle_patch_request))
+        .route("/v2/{*path}", delete(handle_delete_request))
 }
 
 /// Parse a path to extract repository name and operation details
@@ -193,6 +317,23 @@ fn parse_registry_path(path: &str) -> Option<RegistryRequest> {
     // - library/alpine/blobs/sha256:...
     // - library/alpine/blobs/uploads/
     // - library/alpine/blobs/uploads/{session_id}
+    // - library/alpine/tags/list
+    // - library/alpine/referrers/sha256:...
+
+    if let Some(name) = path.strip_suffix("/tags/list") {
+        return Some(RegistryRequest::TagList {
+            name: name.to_string(),
+        });
+    }
+
+    if let Some(idx) = path.rfind("/referrers/") {
+        let name = &path[..idx];
+        let digest = &path[idx + 11..]; // len("/referrers/")
+        return Some(RegistryRequest::Referrers {
+            name: name.to_string(),
+            digest: digest.to_string(),
+        });
+    }
 
     // Find the last meaningful segment type
     if let Some(idx) = path.rfind("/manifests/") {
@@ -236,6 +377,8 @@ enum RegistryRequest {
     Blob { name: String, digest: String },
     StartUpload { name: String },
     Upload { name: String, session_id: String },
+    TagList { name: String },
+    Referrers { name: String, digest: String },
 }
 
 /// Handle GET and HEAD requests
@@ -243,6 +386,9 @@ async fn handle_get_or_head_request(
     State(state): State<AppState>,
     Path(path): Path<String>,
     method: axum::http::Method,
+    Query(tag_query): Query<TagListQuery>,
+    Query(referrers_query): Query<ReferrersQuery>,
+    request_headers: HeaderMap,
 ) -> Result<Response, ApiError> {
     let req = parse_registry_path(&path).ok_or_else(|| ApiError::NotFound(path.clone()))?;
 
@@ -251,6 +397,7 @@ async fn handle_get_or_head_request(
             // Validate inputs at API boundary before logging or processing
             validate_repository_name(&name)?;
             validate_reference(&reference)?;
+            authorize(&state, &request_headers, Some(&name), PULL).await?;
 
             if method == axum::http::Method::HEAD {
                 debug!("HEAD manifest: {}:{}", name, reference);
@@ -295,6 +442,7 @@ async fn handle_get_or_head_request(
             // Validate digest format to prevent path traversal and ensure correctness
             harbor_storage::backend::validate_digest(&digest)
                 .map_err(|e| ApiError::BadRequest(format!("Invalid digest: {}", e)))?;
+            authorize(&state, &request_headers, Some(&name), PULL).await?;
 
             if method == axum::http::Method::HEAD {
                 debug!("HEAD blob: {}", digest);
@@ -308,6 +456,7 @@ async fn handle_get_or_head_request(
                             HeaderValue::from_static("application/octet-stream"),
                         );
                         headers.insert(header::CONTENT_LENGTH, HeaderValue::from(s as u64));
+                        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
                         headers.insert(
                             "Docker-Content-Digest",
                             HeaderValue::from_str(&digest).unwrap(),
@@ -330,7 +479,7 @@ async fn handle_get_or_head_request(
                             .await
                         {
                             Ok(Some(presigned_url)) => {
-                                debug!("Redirecting blob {} to presigned S3 URL", digest);
+                                debug!("Redirecting blob {} to presigned URL", digest);
 
                                 // Validate that the presigned URL can be used as a header value
                                 // S3 presigned URLs may contain special characters that need validation
@@ -406,17 +555,46 @@ async fn handle_get_or_head_request(
                     }
                 }
 
+                // Single byte range requests (malformed or multi-range headers are ignored)
+                let range = request_headers
+                    .get(header::RANGE)
+                    .and_then(|h| h.to_str().ok())
+                    .and_then(ByteRange::parse);
+
                 // Standard streaming response (fallback or when redirects disabled)
-                let (stream, size) = state.registry.get_blob(&name, &digest).await?;
+                let (stream, size, content_range) = match range {
+                    Some(range) => {
+                        debug!("GET blob range {}: {}", range.header_value(), digest);
+                        state
+                            .registry
+                            .get_blob_range(&name, &digest, &range)
+                            .await?
+                    }
+                    None => {
+                        let (stream, size) = state.registry.get_blob(&name, &digest).await?;
+                        (stream, size, None)
+                    }
+                };
 
                 // Stream the blob data to the client (bounded memory usage)
                 let body = axum::body::Body::from_stream(stream);
-                let mut response = (StatusCode::OK, body).into_response();
+                let status = if content_range.is_some() {
+                    StatusCode::PARTIAL_CONTENT
+                } else {
+                    StatusCode::OK
+                };
+                let mut response = (status, body).into_response();
                 let headers = response.headers_mut();
                 headers.insert(
                     header::CONTENT_TYPE,
                     HeaderValue::from_static("application/octet-stream"),
                 );
+                headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+                if let Some(content_range) = content_range
+                    && let Ok(value) = HeaderValue::from_str(&content_range.header_value())
+                {
+                    headers.insert(header::CONTENT_RANGE, value);
+                }
                 // Only set Content-Length when we have a known size.
                 // When upstream omits Content-Length (size=0), omitting it here
                 // lets axum use chunked transfer encoding automatically.
@@ -434,6 +612,7 @@ async fn handle_get_or_head_request(
             // Validate repository name at API boundary
             // Session ID validation is handled by the core layer
             validate_repository_name(&name)?;
+            authorize(&state, &request_headers, Some(&name), PUSH).await?;
 
             debug!("GET upload status: {}", session_id);
             let session = state
@@ -441,16 +620,74 @@ async fn handle_get_or_head_request(
                 .get_upload_session(&session_id)
                 .await?
                 .ok_or_else(|| ApiError::NotFound(format!("Upload session: {}", session_id)))?;
-            let location = format!("/v2/{}/blobs/uploads/{}", name, session_id);
-            let range = format!("0-{}", session.bytes_received);
             let mut response = StatusCode::NO_CONTENT.into_response();
+            set_upload_headers(
+                response.headers_mut(),
+                &name,
+                &session_id,
+                session.bytes_received as u64,
+            );
+            Ok(response)
+        }
+        RegistryRequest::TagList { name } => {
+            validate_repository_name(&name)?;
+            authorize(&state, &request_headers, Some(&name), PULL).await?;
+
+            debug!("GET tags: {}", name);
+            let tag_list = state
+                .registry
+                .list_tags(&name, tag_query.n, tag_query.last.as_deref())
+                .await?;
+
+            let body = serde_json::json!({
+                "name": name,
+                "tags": tag_list.tags,
+            });
+            let mut response = (StatusCode::OK, axum::Json(body)).into_response();
+            if let Some(last) = tag_list.next_last {
+                // Point the next page at this registry rather than the upstream
+                let mut link = format!("/v2/{}/tags/list?", name);
+                if let Some(n) = tag_query.n {
+                    link.push_str(&format!("n={}&", n));
+                }
+                link.push_str(&format!("last={}", last));
+                if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"next\"", link)) {
+                    response.headers_mut().insert(header::LINK, value);
+                }
+            }
+            Ok(response)
+        }
+        RegistryRequest::Referrers { name, digest } => {
+            validate_repository_name(&name)?;
+            harbor_storage::backend::validate_digest(&digest)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid digest: {}", e)))?;
+            authorize(&state, &request_headers, Some(&name), PULL).await?;
+
+            let artifact_type = referrers_query.artifact_type;
+            debug!("GET referrers: {}@{} ({:?})", name, digest, artifact_type);
+            let data = state
+                .registry
+                .get_referrers(&name, &digest, artifact_type.as_deref())
+                .await?;
+
+            let size = data.len() as u64;
+            let mut response = if method == axum::http::Method::HEAD {
+                StatusCode::OK.into_response()
+            } else {
+                (StatusCode::OK, data).into_response()
+            };
             let headers = response.headers_mut();
-            headers.insert(header::LOCATION, HeaderValue::from_str(&location).unwrap());
             headers.insert(
-                "Docker-Upload-UUID",
-                HeaderValue::from_str(&session_id).unwrap(),
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(OCI_INDEX_MEDIA_TYPE),
             );
-            headers.insert(header::RANGE, HeaderValue::from_str(&range).unwrap());
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+            if artifact_type.is_some() {
+                headers.insert(
+                    "OCI-Filters-Applied",
+                    HeaderValue::from_static("artifactType"),
+                );
+            }
             Ok(response)
         }
         RegistryRequest::StartUpload { .. } => Err(ApiError::MethodNotAllowed),
@@ -472,6 +709,7 @@ async fn handle_put_request(
             // Validate inputs at API boundary before logging or processing
             validate_repository_name(&name)?;
             validate_reference(&reference)?;
+            authorize(&state, &headers, Some(&name), PUSH).await?;
 
             debug!("PUT manifest: {}:{}", name, reference);
             let content_type = headers
@@ -496,13 +734,17 @@ async fn handle_put_request(
             // Validate repository name at API boundary
             // Digest and session ID validation is handled by the core layer
             validate_repository_name(&name)?;
+            authorize(&state, &headers, Some(&name), PUSH).await?;
 
             let digest = query
                 .digest
                 .ok_or_else(|| ApiError::BadRequest("Missing digest parameter".to_string()))?;
             debug!("PUT upload: {} -> {}", session_id, digest);
-            if !body.is_empty() {
-                state.registry.append_upload(&session_id, body).await?;
+            if !body.is_empty()
+                && let Err(response) =
+                    append_chunk(&state, &name, &session_id, &headers, body).await?
+            {
+                return Ok(response);
             }
             state
                 .registry
@@ -527,6 +769,7 @@ async fn handle_post_request(
     State(state): State<AppState>,
     Path(path): Path<String>,
     Query(query): Query<MountQuery>,
+    headers: HeaderMap,
 ) -> Result<Response, ApiError> {
     let req = parse_registry_path(&path).ok_or_else(|| ApiError::NotFound(path.clone()))?;
 
@@ -534,6 +777,7 @@ async fn handle_post_request(
         RegistryRequest::StartUpload { name } => {
             // Validate repository name at API boundary before logging or processing
             validate_repository_name(&name)?;
+            authorize(&state, &headers, Some(&name), PUSH).await?;
 
             // Check if this is a mount request
             if let (Some(mount_digest), Some(from)) = (query.mount, query.from) {
@@ -543,6 +787,7 @@ async fn handle_post_request(
                     .map_err(|e| ApiError::BadRequest(format!("Invalid mount digest: {}", e)))?;
                 // Validate source repository name
                 validate_repository_name(&from)?;
+                authorize(&state, &headers, Some(&from), PULL).await?;
 
                 debug!("Mount request: {} from {}", mount_digest, from);
                 if state
@@ -564,15 +809,8 @@ async fn handle_post_request(
             // Start a new upload session
             debug!("Starting upload for: {}", name);
             let session_id = state.registry.start_upload(&name).await?;
-            let location = format!("/v2/{}/blobs/uploads/{}", name, session_id);
             let mut response = StatusCode::ACCEPTED.into_response();
-            let headers = response.headers_mut();
-            headers.insert(header::LOCATION, HeaderValue::from_str(&location).unwrap());
-            headers.insert(
-                "Docker-Upload-UUID",
-                HeaderValue::from_str(&session_id).unwrap(),
-            );
-            headers.insert(header::RANGE, HeaderValue::from_static("0-0"));
+            set_upload_headers(response.headers_mut(), &name, &session_id, 0);
             Ok(response)
         }
         _ => Err(ApiError::MethodNotAllowed),
@@ -583,6 +821,7 @@ async fn handle_post_request(
 async fn handle_patch_request(
     State(state): State<AppState>,
     Path(path): Path<String>,
+    request_headers: HeaderMap,
     body: Bytes,
 ) -> Result<Response, ApiError> {
     let req = parse_registry_path(&path).ok_or_else(|| ApiError::NotFound(path.clone()))?;
@@ -592,21 +831,232 @@ async fn handle_patch_request(
             // Validate repository name at API boundary
             // Session ID validation is handled by the core layer
             validate_repository_name(&name)?;
+            authorize(&state, &request_headers, Some(&name), PUSH).await?;
 
             debug!("PATCH upload: {} ({} bytes)", session_id, body.len());
-            let new_size = state.registry.append_upload(&session_id, body).await?;
-            let location = format!("/v2/{}/blobs/uploads/{}", name, session_id);
-            let range = format!("0-{}", new_size - 1);
+            let new_size =
+                match append_chunk(&state, &name, &session_id, &request_headers, body).await? {
+                    Ok(size) => size,
+                    Err(response) => return Ok(response),
+                };
             let mut response = StatusCode::ACCEPTED.into_response();
-            let headers = response.headers_mut();
-            headers.insert(header::LOCATION, HeaderValue::from_str(&location).unwrap());
-            headers.insert(
-                "Docker-Upload-UUID",
-                HeaderValue::from_str(&session_id).unwrap(),
-            );
-            headers.insert(header::RANGE, HeaderValue::from_str(&range).unwrap());
+            set_upload_headers(response.headers_mut(), &name, &session_id, new_size as u64);
             Ok(response)
         }
         _ => Err(ApiError::MethodNotAllowed),
     }
 }
+
+/// Handle DELETE requests
+async fn handle_delete_request(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let req = parse_registry_path(&path).ok_or_else(|| ApiError::NotFound(path.clone()))?;
+
+    match req {
+        RegistryRequest::Manifest { name, reference } => {
+            // Validate inputs at API boundary before logging or processing
+            validate_repository_name(&name)?;
+            validate_reference(&reference)?;
+            authorize(&state, &headers, Some(&name), DELETE).await?;
+
+            debug!("DELETE manifest: {}:{}", name, reference);
+            state.registry.delete_manifest(&name, &reference).await?;
+            Ok(StatusCode::ACCEPTED.into_response())
+        }
+        RegistryRequest::Blob { name, digest } => {
+            // Validate inputs at API boundary before logging or processing
+            validate_repository_name(&name)?;
+            harbor_storage::backend::validate_digest(&digest)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid digest: {}", e)))?;
+            authorize(&state, &headers, Some(&name), DELETE).await?;
+
+            debug!("DELETE blob: {}@{}", name, digest);
+            state.registry.delete_blob(&name, &digest).await?;
+            Ok(StatusCode::ACCEPTED.into_response())
+        }
+        RegistryRequest::Upload { name, session_id } => {
+            // Validate repository name at API boundary
+            // Session ID validation is handled by the core layer
+            validate_repository_name(&name)?;
+            authorize(&state, &headers, Some(&name), PUSH).await?;
+
+            debug!("DELETE upload: {}", session_id);
+            state
+                .registry
+                .get_upload_session(&session_id)
+                .await?
+                .ok_or_else(|| ApiError::NotFound(format!("Upload session: {}", session_id)))?;
+            state.registry.cancel_upload(&session_id).await?;
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        _ => Err(ApiError::MethodNotAllowed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harbor_auth::{Access, JwtManager};
+    use harbor_core::config::InMemoryConfigProvider;
+    use harbor_core::{CacheConfig, CacheManager, RegistryService, UpstreamManager};
+    use harbor_db::{Database, EntryType, NewRepositoryPermission, NewUser, UserRole};
+    use harbor_proxy::{HarborClient, HarborClientConfig, RetryPolicy};
+    use harbor_storage::{LocalStorage, StorageBackend};
+    use std::sync::Arc;
+
+    use crate::state::{BlobServingConfig, RegistryAuthConfig};
+
+    /// App state with auth enabled, a local cache and an unreachable upstream
+    async fn state(dir: &tempfile::TempDir) -> AppState {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+        let cache = Arc::new(CacheManager::new(
+            db.clone(),
+            storage.clone(),
+            CacheConfig::default(),
+        ));
+        let client = HarborClient::new(HarborClientConfig {
+            url: "http://127.0.0.1:1".to_string(),
+            registry: "library".to_string(),
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            retry: RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            },
+        })
+        .unwrap();
+        let registry = Arc::new(RegistryService::new(
+            cache.clone(),
+            Arc::new(client),
+            db.clone(),
+            storage.clone(),
+        ));
+        let provider = Arc::new(InMemoryConfigProvider::new(vec![]));
+        let upstream_manager = Arc::new(UpstreamManager::new(provider.clone()).unwrap());
+
+        AppState::new(
+            db,
+            cache,
+            registry,
+            storage,
+            Arc::new(JwtManager::new("test-secret", 1)),
+            true,
+            upstream_manager,
+            provider,
+            BlobServingConfig::default(),
+            RegistryAuthConfig::default(),
+        )
+    }
+
+    /// Request the catalog with a token of the user, returning the
+    /// repositories and the `Link` header
+    async fn catalog_page(
+        state: &AppState,
+        token: &str,
+        n: usize,
+        last: Option<&str>,
+    ) -> (Vec<String>, Option<String>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+        let query = CatalogQuery {
+            n: Some(n),
+            last: last.map(str::to_string),
+        };
+
+        let response = catalog(State(state.clone()), headers, Query(query))
+            .await
+            .unwrap();
+        let link = response
+            .headers()
+            .get(header::LINK)
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let repositories = body["repositories"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|name| name.as_str().unwrap().to_string())
+            .collect();
+        (repositories, link)
+    }
+
+    #[tokio::test]
+    async fn test_catalog_of_restricted_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir).await;
+        for repository in ["library/a", "team-a/a", "team-a/b", "zeta/x"] {
+            let data = Bytes::from(repository.to_string());
+            let digest = harbor_storage::backend::compute_sha256(&data);
+            state
+                .cache
+                .put(
+                    EntryType::Manifest,
+                    Some(repository.to_string()),
+                    None,
+                    &digest,
+                    "application/vnd.oci.image.manifest.v1+json",
+                    data,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        let user = state
+            .db
+            .insert_user(NewUser {
+                username: "dev".to_string(),
+                password_hash: String::new(),
+                role: UserRole::ReadWrite,
+            })
+            .await
+            .unwrap();
+        state
+            .db
+            .insert_repository_permission(NewRepositoryPermission {
+                user_id: Some(user.id),
+                group_name: None,
+                pattern: "team-a/**".to_string(),
+                actions: vec![PULL.to_string()],
+            })
+            .await
+            .unwrap();
+        let catalog_scope = Access {
+            resource_type: "registry".to_string(),
+            name: "catalog".to_string(),
+            actions: vec!["*".to_string()],
+        };
+        let token = state
+            .jwt
+            .generate_registry_token(user.id, "dev", "read-write", vec![catalog_scope], 300)
+            .unwrap();
+
+        // Hidden repositories neither shorten the page nor leak via the cursor
+        let (repositories, link) = catalog_page(&state, &token, 1, None).await;
+        assert_eq!(repositories, ["team-a/a"]);
+        assert_eq!(
+            link.as_deref(),
+            Some("</v2/_catalog?n=1&last=team-a/a>; rel=\"next\"")
+        );
+
+        let (repositories, link) = catalog_page(&state, &token, 1, Some("team-a/a")).await;
+        assert_eq!(repositories, ["team-a/b"]);
+        assert_eq!(link, None);
+
+        let (repositories, link) = catalog_page(&state, &token, 2, None).await;
+        assert_eq!(repositories, ["team-a/a", "team-a/b"]);
+        assert_eq!(link, None);
+    }
+}
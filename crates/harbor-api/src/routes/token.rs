@@ -0,0 +1,347 @@
+//! Docker registry token authentication
+//!
+//! Registry clients are challenged with `WWW-Authenticate: Bearer realm=...`
+//! on `/v2/` and fetch a scoped token from `/service/token` using the
+//! credentials stored in the database (this is what `docker login` does).
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, header::AUTHORIZATION},
+    routing::get,
+};
+use chrono::Utc;
+use harbor_auth::scope::{self, PULL, PUSH, REGISTRY, REPOSITORY};
+use harbor_auth::{Access, AuthUser, parse_basic_auth, verify_password};
+use harbor_core::RouteMatcher;
+use harbor_db::{RepositoryPermission, UserRole};
+use serde::Serialize;
+use tracing::debug;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Token endpoint path
+const TOKEN_PATH: &str = "/service/token";
+
+/// Response body of the token endpoint
+#[derive(Debug, Serialize)]
+struct TokenResponse {
+    token: String,
+    /// Same as `token`, for OAuth2-style clients
+    access_token: String,
+    expires_in: u64,
+    issued_at: String,
+}
+
+// ==================== Access Checks ====================
+
+/// Resolve what a user may do with the requested access.
+///
+/// Admins and unrestricted users (`rules` is None) get role-based access;
+/// restricted users are limited to the grants matching the repository,
+/// and get nothing without one.
+fn resolve_access(
+    role: &UserRole,
+    rules: Option<&[RepositoryPermission]>,
+    requested: &Access,
+) -> Option<Access> {
+    let Some(rules) = rules.filter(|_| !role.is_admin()) else {
+        return scope::grant_for_role(role, requested);
+    };
+
+    let granted: Vec<String> = rules
+        .iter()
+        .filter(|rule| RouteMatcher::pattern_matches(&rule.pattern, &requested.name))
+        .flat_map(|rule| rule.actions.iter().cloned())
+        .collect();
+    scope::grant_for_rules(role, requested, &granted)
+}
+
+/// Load the per-repository grants of a restricted user. Admins and users
+/// never given grants are not restricted (None).
+async fn load_rules(
+    state: &AppState,
+    user: &AuthUser,
+) -> Result<Option<Vec<RepositoryPermission>>, ApiError> {
+    if user.role.is_admin() || !state.db.is_user_restricted(user.id).await? {
+        return Ok(None);
+    }
+    Ok(Some(state.db.get_permissions_for_user(user.id).await?))
+}
+
+/// Build the `WWW-Authenticate` challenge for a registry request
+fn bearer_challenge(
+    state: &AppState,
+    headers: &HeaderMap,
+    scope: Option<&Access>,
+    error: Option<&str>,
+) -> String {
+    let realm = state.registry_auth.realm.clone().unwrap_or_else(|| {
+        let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+        let proto = header("x-forwarded-proto").unwrap_or("http");
+        let host = header("x-forwarded-host")
+            .or_else(|| header("host"))
+            .unwrap_or("localhost");
+        format!("{}://{}{}", proto, host, TOKEN_PATH)
+    });
+
+    let mut challenge = format!(
+        "Bearer realm=\"{}\",service=\"{}\"",
+        realm, state.registry_auth.service
+    );
+    if let Some(scope) = scope {
+        challenge.push_str(&format!(",scope=\"{}\"", scope.to_scope()));
+    }
+    if let Some(error) = error {
+        challenge.push_str(&format!(",error=\"{}\"", error));
+    }
+    challenge
+}
+
+/// Check that a registry request carries a token granting `action` on
+/// `repository`. With no repository, any valid token is accepted (`/v2/`).
+///
+/// Missing, invalid or unscoped tokens get a 401 challenge so the client
+/// fetches a new token; a token scoped to the repository without the
+/// action is denied with 403. Grants are re-checked so that revoked
+/// permissions apply before the token expires.
+pub(super) async fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    repository: Option<&str>,
+    action: &str,
+) -> Result<(), ApiError> {
+    if !state.auth_enabled {
+        return Ok(());
+    }
+
+    // Pushing clients also need pull for blob existence checks
+    let scope = repository.map(|name| {
+        if action == PUSH {
+            Access::repository(name, &[PULL, PUSH])
+        } else {
+            Access::repository(name, &[action])
+        }
+    });
+    let challenge = |error: Option<&str>| ApiError::RegistryUnauthorized {
+        challenge: bearer_challenge(state, headers, scope.as_ref(), error),
+    };
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| challenge(None))?;
+    let claims = state
+        .jwt
+        .validate_token(token)
+        .map_err(|_| challenge(Some("invalid_token")))?;
+
+    let Some(name) = repository else {
+        return Ok(());
+    };
+
+    let denied = || ApiError::Denied(format!("requested access to {} is denied", name));
+    match claims.access_for(REPOSITORY, name) {
+        Some(access) if access.allows(action) => {}
+        Some(_) => return Err(denied()),
+        None => return Err(challenge(Some("insufficient_scope"))),
+    }
+
+    let user = AuthUser::from_claims(&claims);
+    let rules = load_rules(state, &user).await?;
+    resolve_access(
+        &user.role,
+        rules.as_deref(),
+        &Access::repository(name, &[action]),
+    )
+    .map(|_| ())
+    .ok_or_else(denied)
+}
+
+/// Repositories visible in the catalog to an authorized caller
+pub(super) struct CatalogFilter {
+    /// Role and grants of the caller (None when auth is disabled)
+    caller: Option<(UserRole, Option<Vec<RepositoryPermission>>)>,
+}
+
+impl CatalogFilter {
+    /// Whether the caller may pull the repository
+    pub(super) fn allows(&self, repository: &str) -> bool {
+        self.caller.as_ref().is_none_or(|(role, rules)| {
+            resolve_access(
+                role,
+                rules.as_deref(),
+                &Access::repository(repository, &[PULL]),
+            )
+            .is_some()
+        })
+    }
+}
+
+/// Check that a registry request may list the catalog.
+///
+/// Catalog access needs a token scoped to `registry:catalog:*`; tokens
+/// without it get a 401 challenge for that scope. The returned filter
+/// limits the listing to repositories the caller may pull.
+pub(super) async fn authorize_catalog(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<CatalogFilter, ApiError> {
+    if !state.auth_enabled {
+        return Ok(CatalogFilter { caller: None });
+    }
+
+    let scope = Access {
+        resource_type: REGISTRY.to_string(),
+        name: "catalog".to_string(),
+        actions: vec!["*".to_string()],
+    };
+    let challenge = |error: Option<&str>| ApiError::RegistryUnauthorized {
+        challenge: bearer_challenge(state, headers, Some(&scope), error),
+    };
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| challenge(None))?;
+    let claims = state
+        .jwt
+        .validate_token(token)
+        .map_err(|_| challenge(Some("invalid_token")))?;
+
+    if !claims.allows(REGISTRY, "catalog", "*") {
+        return Err(challenge(Some("insufficient_scope")));
+    }
+
+    let user = AuthUser::from_claims(&claims);
+    let rules = load_rules(state, &user).await?;
+    Ok(CatalogFilter {
+        caller: Some((user.role, rules)),
+    })
+}
+
+// ==================== Token Endpoint ====================
+
+/// Authenticate the basic credentials sent to the token endpoint
+async fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<AuthUser, ApiError> {
+    // Mirror RequireAuth: everyone is admin when auth is disabled
+    if !state.auth_enabled {
+        return Ok(AuthUser {
+            id: 0,
+            username: "anonymous".to_string(),
+            role: UserRole::Admin,
+        });
+    }
+
+    let header = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .ok_or(ApiError::Unauthorized)?;
+    let (username, password) = parse_basic_auth(header).map_err(|_| ApiError::Unauthorized)?;
+
+    let user = state
+        .db
+        .get_user_by_username(&username)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+
+    if !verify_password(&password, &user.password_hash)? {
+        return Err(ApiError::Unauthorized);
+    }
+
+    Ok(AuthUser {
+        id: user.id,
+        username: user.username,
+        role: user.role,
+    })
+}
+
+/// GET /service/token - Issue a registry token for the requested scopes
+async fn issue_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Result<Json<TokenResponse>, ApiError> {
+    let user = authenticate(&state, &headers).await?;
+
+    if let Some((_, service)) = params.iter().find(|(key, _)| key == "service")
+        && service != &state.registry_auth.service
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown service: {}",
+            service
+        )));
+    }
+
+    // Scopes may be repeated and/or space-separated
+    let rules = load_rules(&state, &user).await?;
+    let access: Vec<Access> = params
+        .iter()
+        .filter(|(key, _)| key == "scope")
+        .flat_map(|(_, value)| scope::parse_scopes(value))
+        .filter_map(|requested| resolve_access(&user.role, rules.as_deref(), &requested))
+        .collect();
+
+    debug!(
+        "Issuing registry token for {}: {:?}",
+        user.username,
+        access.iter().map(Access::to_scope).collect::<Vec<_>>()
+    );
+
+    let ttl = state.registry_auth.token_ttl_secs;
+    let token = state.jwt.generate_registry_token(
+        user.id,
+        &user.username,
+        user.role.as_str(),
+        access,
+        ttl as i64,
+    )?;
+
+    Ok(Json(TokenResponse {
+        access_token: token.clone(),
+        token,
+        expires_in: ttl,
+        issued_at: Utc::now().to_rfc3339(),
+    }))
+}
+
+/// Create token endpoint routes
+pub fn routes() -> Router<AppState> {
+    Router::new().route(TOKEN_PATH, get(issue_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(pattern: &str, actions: &[&str]) -> RepositoryPermission {
+        RepositoryPermission {
+            id: 1,
+            user_id: Some(2),
+            group_name: None,
+            pattern: pattern.to_string(),
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_restricted_user_without_grants_has_no_access() {
+        let pull = Access::repository("team-a/app", &[PULL]);
+
+        // Unrestricted users get role-based access
+        assert!(resolve_access(&UserRole::ReadWrite, None, &pull).is_some());
+
+        let rules = [grant("team-a/**", &[PULL])];
+        assert!(resolve_access(&UserRole::ReadWrite, Some(&rules), &pull).is_some());
+        let other = Access::repository("team-b/app", &[PULL]);
+        assert!(resolve_access(&UserRole::ReadWrite, Some(&rules), &other).is_none());
+
+        // Deleting the last grant does not widen access
+        assert!(resolve_access(&UserRole::ReadWrite, Some(&[]), &pull).is_none());
+        assert!(resolve_access(&UserRole::Admin, Some(&[]), &pull).is_some());
+    }
+}
@@ -46,6 +46,28 @@ impl Default for BlobServingConfig {
     }
 }
 
+/// Docker registry token authentication configuration
+#[derive(Clone, Debug)]
+pub struct RegistryAuthConfig {
+    /// Token endpoint URL advertised in `WWW-Authenticate` challenges.
+    /// When unset, it is derived from the request host.
+    pub realm: Option<String>,
+    /// Service name advertised in challenges and expected by the token endpoint
+    pub service: String,
+    /// Lifetime of issued registry tokens in seconds
+    pub token_ttl_secs: u64,
+}
+
+impl Default for RegistryAuthConfig {
+    fn default() -> Self {
+        Self {
+            realm: None,
+            service: "harbor-cache".to_string(),
+            token_ttl_secs: 300,
+        }
+    }
+}
+
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
@@ -61,6 +83,8 @@ pub struct AppState {
     pub config_provider: Arc<dyn UpstreamConfigProvider>,
     /// Blob serving configuration (presigned URL redirects)
     pub blob_serving: BlobServingConfig,
+    /// Registry token authentication configuration
+    pub registry_auth: RegistryAuthConfig,
 }
 
 impl AppState {
@@ -75,6 +99,7 @@ impl AppState {
         upstream_manager: Arc<UpstreamManager>,
         config_provider: Arc<dyn UpstreamConfigProvider>,
         blob_serving: BlobServingConfig,
+        registry_auth: RegistryAuthConfig,
     ) -> Self {
         Self {
             db,
@@ -86,6 +111,7 @@ impl AppState {
             upstream_manager,
             config_provider,
             blob_serving,
+            registry_auth,
         }
     }
 }
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::error::AuthError;
+use crate::scope::Access;
 
 /// JWT claims
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +21,24 @@ pub struct Claims {
     pub exp: i64,
     /// Issued at (Unix timestamp)
     pub iat: i64,
+    /// Registry access granted by the token endpoint
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub access: Vec<Access>,
+}
+
+impl Claims {
+    /// Get the access entry granted for a resource, if any
+    pub fn access_for(&self, resource_type: &str, name: &str) -> Option<&Access> {
+        self.access
+            .iter()
+            .find(|a| a.resource_type == resource_type && a.name == name)
+    }
+
+    /// Check whether the token grants an action on a resource
+    pub fn allows(&self, resource_type: &str, name: &str, action: &str) -> bool {
+        self.access_for(resource_type, name)
+            .is_some_and(|a| a.allows(action))
+    }
 }
 
 /// JWT manager for token generation and validation
@@ -56,6 +75,7 @@ impl JwtManager {
             role: role.to_string(),
             exp: exp.timestamp(),
             iat: now.timestamp(),
+            access: Vec::new(),
         };
 
         debug!("Generating token for user: {}", username);
@@ -63,6 +83,36 @@ impl JwtManager {
         encode(&Header::default(), &claims, &self.encoding_key).map_err(AuthError::Jwt)
     }
 
+    /// Generate a short-lived registry token carrying granted access
+    pub fn generate_registry_token(
+        &self,
+        user_id: i64,
+        username: &str,
+        role: &str,
+        access: Vec<Access>,
+        ttl_secs: i64,
+    ) -> Result<String, AuthError> {
+        let now = Utc::now();
+        let exp = now + Duration::seconds(ttl_secs);
+
+        let claims = Claims {
+            sub: user_id.to_string(),
+            username: username.to_string(),
+            role: role.to_string(),
+            exp: exp.timestamp(),
+            iat: now.timestamp(),
+            access,
+        };
+
+        debug!(
+            "Generating registry token for user: {} ({} scopes)",
+            username,
+            claims.access.len()
+        );
+
+        encode(&Header::default(), &claims, &self.encoding_key).map_err(AuthError::Jwt)
+    }
+
     /// Validate a JWT token and return claims
     pub fn validate_token(&self, token: &str) -> Result<Claims, AuthError> {
         let validation = Validation::default();
@@ -95,6 +145,21 @@ mod tests {
         assert_eq!(claims.role, "admin");
     }
 
+    #[test]
+    fn test_registry_token_carries_access() {
+        let manager = JwtManager::new("test-secret-key", 24);
+
+        let access = vec![Access::repository("library/alpine", &["pull"])];
+        let token = manager
+            .generate_registry_token(1, "testuser", "read-only", access, 300)
+            .unwrap();
+        let claims = manager.validate_token(&token).unwrap();
+
+        assert!(claims.allows("repository", "library/alpine", "pull"));
+        assert!(!claims.allows("repository", "library/alpine", "push"));
+        assert!(!claims.allows("repository", "library/nginx", "pull"));
+    }
+
     #[test]
     fn test_invalid_token() {
         let manager = JwtManager::new("test-secret-key", 24);
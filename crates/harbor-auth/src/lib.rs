@@ -7,8 +7,10 @@ pub mod error;
 pub mod jwt;
 pub mod middleware;
 pub mod password;
+pub mod scope;
 
 pub use error::AuthError;
 pub use jwt::{Claims, JwtManager};
-pub use middleware::{AuthUser, auth_middleware, require_admin, require_write};
+pub use middleware::{AuthUser, auth_middleware, parse_basic_auth, require_admin, require_write};
 pub use password::{hash_password, verify_password};
+pub use scope::Access;
@@ -6,6 +6,7 @@ use axum::{
     middleware::Next,
     response::Response,
 };
+use base64::{Engine, engine::general_purpose::STANDARD};
 use harbor_db::UserRole;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -41,6 +42,22 @@ fn extract_bearer_token(header: &str) -> Result<&str, AuthError> {
     Ok(&header[7..])
 }
 
+/// Decode HTTP basic credentials from an authorization header
+pub fn parse_basic_auth(header: &str) -> Result<(String, String), AuthError> {
+    let encoded = header
+        .strip_prefix("Basic ")
+        .ok_or(AuthError::InvalidAuthHeader)?;
+    let decoded = STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| AuthError::InvalidAuthHeader)?;
+    let decoded = String::from_utf8(decoded).map_err(|_| AuthError::InvalidAuthHeader)?;
+
+    let (username, password) = decoded
+        .split_once(':')
+        .ok_or(AuthError::InvalidAuthHeader)?;
+    Ok((username.to_string(), password.to_string()))
+}
+
 /// Authentication middleware
 ///
 /// This middleware extracts and validates JWT tokens from the Authorization header.
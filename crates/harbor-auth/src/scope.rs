@@ -0,0 +1,243 @@
+//! Docker registry token scopes
+//!
+//! Scopes are requested from the token endpoint as `type:name:actions`,
+//! e.g. `repository:library/alpine:pull,push`, and granted back to the
+//! client as `access` entries in the issued token.
+
+use harbor_db::UserRole;
+use serde::{Deserialize, Serialize};
+
+/// Resource type for repository scopes
+pub const REPOSITORY: &str = "repository";
+
+/// Resource type for registry-wide scopes (e.g. the catalog)
+pub const REGISTRY: &str = "registry";
+
+/// Pull (read) action
+pub const PULL: &str = "pull";
+
+/// Push (write) action
+pub const PUSH: &str = "push";
+
+/// Delete action
+pub const DELETE: &str = "delete";
+
+/// Access to a single resource, as requested in a scope or granted in a token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Access {
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub name: String,
+    pub actions: Vec<String>,
+}
+
+impl Access {
+    /// Create access for a repository
+    pub fn repository(name: &str, actions: &[&str]) -> Self {
+        Self {
+            resource_type: REPOSITORY.to_string(),
+            name: name.to_string(),
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// Parse a scope string of the form `type:name:action[,action...]`.
+    ///
+    /// The name may itself contain colons (e.g. a registry host with a port),
+    /// so the type is taken up to the first colon and the actions after the last.
+    pub fn parse(scope: &str) -> Option<Self> {
+        let (resource_type, rest) = scope.split_once(':')?;
+        let (name, actions) = rest.rsplit_once(':')?;
+
+        if resource_type.is_empty() || name.is_empty() {
+            return None;
+        }
+
+        let actions = actions
+            .split(',')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Some(Self {
+            resource_type: resource_type.to_string(),
+            name: name.to_string(),
+            actions,
+        })
+    }
+
+    /// Check whether this access grants an action (`*` grants everything)
+    pub fn allows(&self, action: &str) -> bool {
+        self.actions.iter().any(|a| a == action || a == "*")
+    }
+
+    /// Format as a scope string
+    pub fn to_scope(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.resource_type,
+            self.name,
+            self.actions.join(",")
+        )
+    }
+}
+
+/// Parse a space-separated list of scopes, skipping malformed entries
+pub fn parse_scopes(scopes: &str) -> Vec<Access> {
+    scopes
+        .split_whitespace()
+        .filter_map(Access::parse)
+        .collect()
+}
+
+/// Whether a role permits a repository action: every user may pull, push
+/// requires write access and delete requires admin
+fn role_permits(role: &UserRole, action: &str) -> bool {
+    match action {
+        PULL => true,
+        PUSH => role.can_write(),
+        DELETE | "*" => role.is_admin(),
+        _ => false,
+    }
+}
+
+/// Whether granting an action to a user of a role can ever have an effect:
+/// push and delete grants only apply to write-capable users
+pub fn role_can_use_grant(role: &UserRole, action: &str) -> bool {
+    match action {
+        PUSH | DELETE => role.can_write(),
+        _ => true,
+    }
+}
+
+/// Restrict requested access to what a role is permitted.
+///
+/// Every user may pull and list the catalog, push requires write access
+/// and delete requires admin. Returns `None` if nothing was granted.
+pub fn grant_for_role(role: &UserRole, requested: &Access) -> Option<Access> {
+    let actions: Vec<String> = match requested.resource_type.as_str() {
+        REPOSITORY => requested
+            .actions
+            .iter()
+            .filter(|a| role_permits(role, a))
+            .cloned()
+            .collect(),
+        REGISTRY if requested.name == "catalog" => requested.actions.clone(),
+        _ => Vec::new(),
+    };
+
+    with_actions(requested, actions)
+}
+
+/// Restrict requested repository access to explicit per-repository grants.
+///
+/// `granted` holds the actions of every grant whose pattern matches the
+/// repository. The role still caps the result as in [`grant_for_role`],
+/// except that an explicit `delete` grant lets write-capable users delete:
+/// read-only users can only ever pull, and `*` only gives read-write users
+/// pull and push. Non-repository scopes fall back to [`grant_for_role`].
+pub fn grant_for_rules(role: &UserRole, requested: &Access, granted: &[String]) -> Option<Access> {
+    if requested.resource_type != REPOSITORY {
+        return grant_for_role(role, requested);
+    }
+
+    let explicit = |action: &str| granted.iter().any(|g| g == action);
+    let actions = requested
+        .actions
+        .iter()
+        .filter(|a| match a.as_str() {
+            DELETE if explicit(DELETE) => role.can_write(),
+            _ => role_permits(role, a) && (explicit(a) || explicit("*")),
+        })
+        .cloned()
+        .collect();
+
+    with_actions(requested, actions)
+}
+
+/// Build the granted access for a request, or `None` if nothing was granted
+fn with_actions(requested: &Access, actions: Vec<String>) -> Option<Access> {
+    if actions.is_empty() {
+        return None;
+    }
+
+    Some(Access {
+        resource_type: requested.resource_type.clone(),
+        name: requested.name.clone(),
+        actions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repository_scope() {
+        let access = Access::parse("repository:library/alpine:pull,push").unwrap();
+        assert_eq!(access.resource_type, REPOSITORY);
+        assert_eq!(access.name, "library/alpine");
+        assert_eq!(access.actions, vec!["pull", "push"]);
+    }
+
+    #[test]
+    fn test_parse_scope_with_port_in_name() {
+        let access = Access::parse("repository:localhost:5000/app:pull").unwrap();
+        assert_eq!(access.name, "localhost:5000/app");
+        assert_eq!(access.actions, vec!["pull"]);
+    }
+
+    #[test]
+    fn test_parse_invalid_scopes() {
+        assert!(Access::parse("repository").is_none());
+        assert!(Access::parse("repository:alpine").is_none());
+        assert!(Access::parse(":alpine:pull").is_none());
+
+        let scopes = parse_scopes("repository:a:pull bogus registry:catalog:*");
+        assert_eq!(scopes.len(), 2);
+    }
+
+    #[test]
+    fn test_read_only_role_cannot_push() {
+        let requested = Access::repository("library/alpine", &[PULL, PUSH]);
+
+        let granted = grant_for_role(&UserRole::ReadOnly, &requested).unwrap();
+        assert_eq!(granted.actions, vec!["pull"]);
+
+        let granted = grant_for_role(&UserRole::ReadWrite, &requested).unwrap();
+        assert_eq!(granted.actions, vec!["pull", "push"]);
+
+        let delete = Access::repository("library/alpine", &[DELETE]);
+        assert!(grant_for_role(&UserRole::ReadWrite, &delete).is_none());
+        assert!(grant_for_role(&UserRole::Admin, &delete).is_some());
+    }
+
+    #[test]
+    fn test_rules_restrict_and_are_capped_by_role() {
+        let requested = Access::repository("team-a/app", &[PULL, PUSH, DELETE]);
+        let granted = vec![PULL.to_string(), PUSH.to_string()];
+
+        let access = grant_for_rules(&UserRole::ReadWrite, &requested, &granted).unwrap();
+        assert_eq!(access.actions, vec!["pull", "push"]);
+
+        let access = grant_for_rules(&UserRole::ReadOnly, &requested, &granted).unwrap();
+        assert_eq!(access.actions, vec!["pull"]);
+
+        assert!(grant_for_rules(&UserRole::ReadWrite, &requested, &[]).is_none());
+
+        // `*` does not widen the role: delete stays admin-only
+        let all = vec!["*".to_string()];
+        let access = grant_for_rules(&UserRole::ReadWrite, &requested, &all).unwrap();
+        assert_eq!(access.actions, vec!["pull", "push"]);
+        let access = grant_for_rules(&UserRole::Admin, &requested, &all).unwrap();
+        assert_eq!(access.actions, vec!["pull", "push", "delete"]);
+
+        // An explicit delete grant lets write-capable users delete
+        let cleanup = vec![PULL.to_string(), DELETE.to_string()];
+        let access = grant_for_rules(&UserRole::ReadWrite, &requested, &cleanup).unwrap();
+        assert_eq!(access.actions, vec!["pull", "delete"]);
+        let access = grant_for_rules(&UserRole::ReadOnly, &requested, &cleanup).unwrap();
+        assert_eq!(access.actions, vec!["pull"]);
+    }
+}
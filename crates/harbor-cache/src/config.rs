@@ -32,6 +32,12 @@ pub struct Config {
     pub tls: TlsConfig,
     #[serde(default)]
     pub blob_serving: BlobServingConfig,
+    #[serde(default)]
+    pub uploads: UploadsConfig,
+    #[serde(default)]
+    pub scrub: ScrubConfig,
+    #[serde(default)]
+    pub health_check: HealthCheckConfig,
 }
 
 /// Server configuration
@@ -52,6 +58,12 @@ pub struct CacheConfig {
     pub retention_days: u32,
     #[serde(default = "default_eviction_policy")]
     pub eviction_policy: String,
+    /// Serve cached manifests for tag pulls when the upstream errors or times out
+    #[serde(default = "default_serve_stale_on_error")]
+    pub serve_stale_on_error: bool,
+    /// Upstream timeout (seconds) for tag pulls that can fall back to the cache
+    #[serde(default = "default_stale_fallback_timeout_secs")]
+    pub stale_fallback_timeout_secs: u64,
 }
 
 /// Legacy upstream Harbor configuration (for backwards compatibility)
@@ -74,6 +86,33 @@ pub struct UpstreamRouteConfig {
     /// Priority for this route (lower = higher priority)
     #[serde(default = "default_priority")]
     pub priority: i32,
+    /// How long (seconds) a cached tag is served without revalidation
+    #[serde(default)]
+    pub tag_ttl_secs: Option<u64>,
+}
+
+/// Retry policy for idempotent requests to an upstream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamRetryConfig {
+    /// Attempts per request, including the first (1 disables retries)
+    #[serde(default = "default_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds
+    #[serde(default = "default_retry_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the delay between attempts in milliseconds
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for UpstreamRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_max_attempts(),
+            initial_backoff_ms: default_retry_initial_backoff_ms(),
+            max_backoff_ms: default_retry_max_backoff_ms(),
+        }
+    }
 }
 
 /// Project configuration within an upstream
@@ -133,12 +172,25 @@ pub struct UpstreamConfig {
     /// Cache isolation mode: "shared" or "isolated"
     #[serde(default = "default_cache_isolation")]
     pub cache_isolation: String,
+    /// Maximum size in bytes of the cache of an isolated upstream
+    /// (required for isolated upstreams)
+    #[serde(default)]
+    pub cache_max_size: Option<u64>,
     /// Whether this is the default upstream (fallback)
     #[serde(default)]
     pub is_default: bool,
     /// Route patterns for this upstream
     #[serde(default)]
     pub routes: Vec<UpstreamRouteConfig>,
+    /// How long (seconds) a cached tag is served without revalidation
+    #[serde(default)]
+    pub tag_ttl_secs: Option<u64>,
+    /// Upstreams to fail over to, in order, when this one is unavailable
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Retry policy for idempotent requests to this upstream
+    #[serde(default)]
+    pub retry: UpstreamRetryConfig,
 }
 
 #[allow(dead_code)]
@@ -177,6 +229,12 @@ pub struct StorageConfig {
     pub local: LocalStorageConfig,
     #[serde(default)]
     pub s3: S3StorageConfig,
+    #[serde(default)]
+    pub gcs: GcsStorageConfig,
+    #[serde(default)]
+    pub azure: AzureStorageConfig,
+    #[serde(default)]
+    pub tiered: TieredStorageConfig,
 }
 
 /// Local storage configuration
@@ -199,6 +257,62 @@ pub struct S3StorageConfig {
     pub allow_http: bool,
 }
 
+/// Google Cloud Storage configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GcsStorageConfig {
+    pub bucket: Option<String>,
+    /// Path to a service account JSON file
+    pub service_account_path: Option<String>,
+    /// Service account JSON (alternative to `service_account_path`)
+    pub service_account_key: Option<String>,
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub allow_http: bool,
+}
+
+/// Azure Blob Storage configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AzureStorageConfig {
+    pub account: Option<String>,
+    pub container: Option<String>,
+    pub access_key: Option<String>,
+    pub endpoint: Option<String>,
+    /// Use the Azurite emulator
+    #[serde(default)]
+    pub use_emulator: bool,
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub allow_http: bool,
+}
+
+/// Hot tier configuration for tiered storage (the cold tier is `storage.s3`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieredStorageConfig {
+    /// Local directory of the hot tier
+    #[serde(default = "default_hot_tier_path")]
+    pub path: String,
+    /// Maximum size of the hot tier in bytes
+    #[serde(default = "default_hot_tier_max_size")]
+    pub max_size: u64,
+}
+
+impl Default for TieredStorageConfig {
+    fn default() -> Self {
+        Self {
+            path: default_hot_tier_path(),
+            max_size: default_hot_tier_max_size(),
+        }
+    }
+}
+
+fn default_hot_tier_path() -> String {
+    "./data/hot".to_string()
+}
+
+fn default_hot_tier_max_size() -> u64 {
+    10 * 1024 * 1024 * 1024 // 10 GB
+}
+
 /// Database configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
@@ -213,6 +327,16 @@ pub struct AuthConfig {
     pub jwt_secret: String,
     #[serde(default = "default_auth_enabled")]
     pub enabled: bool,
+    /// Token endpoint URL advertised in registry challenges
+    /// (derived from the request host when unset)
+    #[serde(default)]
+    pub token_realm: Option<String>,
+    /// Service name advertised in registry challenges
+    #[serde(default = "default_token_service")]
+    pub token_service: String,
+    /// Lifetime of registry tokens in seconds
+    #[serde(default = "default_token_ttl_secs")]
+    pub token_ttl_secs: u64,
 }
 
 /// Logging configuration
@@ -258,16 +382,17 @@ const MAX_PRESIGNED_URL_TTL_SECS: u64 = 86400;
 /// Blob serving configuration
 ///
 /// Controls how blobs are served to clients, including support for
-/// presigned URL redirects for S3 storage backends.
+/// presigned URL redirects for object storage backends.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlobServingConfig {
     /// Enable presigned URL redirects for blob downloads
     ///
-    /// When enabled and using S3 storage, blob GET requests will return
-    /// HTTP 307 redirects to presigned S3 URLs, allowing clients to download
-    /// directly from S3. This reduces server bandwidth and improves performance.
+    /// When enabled and using S3, GCS or Azure storage, blob GET requests will
+    /// return HTTP 307 redirects to presigned URLs, allowing clients to download
+    /// directly from the bucket. This reduces server bandwidth and improves
+    /// performance.
     ///
-    /// Requires S3 storage backend. Has no effect with local storage.
+    /// Requires an object storage backend. Has no effect with local storage.
     #[serde(default)]
     pub enable_presigned_redirects: bool,
 
@@ -317,6 +442,131 @@ fn default_presigned_url_ttl_secs() -> u64 {
     900 // 15 minutes
 }
 
+/// Blob upload session configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadsConfig {
+    /// Expire upload sessions that have not received a chunk for this many
+    /// seconds, deleting their temporary data
+    #[serde(default = "default_upload_session_timeout_secs")]
+    pub session_timeout_secs: u64,
+    /// How often (seconds) abandoned sessions and orphaned temp files are reaped
+    #[serde(default = "default_upload_reaper_interval_secs")]
+    pub reaper_interval_secs: u64,
+}
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        Self {
+            session_timeout_secs: default_upload_session_timeout_secs(),
+            reaper_interval_secs: default_upload_reaper_interval_secs(),
+        }
+    }
+}
+
+fn default_upload_session_timeout_secs() -> u64 {
+    86400 // 24 hours
+}
+
+fn default_upload_reaper_interval_secs() -> u64 {
+    3600 // 1 hour
+}
+
+/// Background blob scrubbing configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubConfig {
+    /// Periodically re-hash stored content to detect corruption
+    #[serde(default = "default_scrub_enabled")]
+    pub enabled: bool,
+    /// Seconds between the end of a scrub pass and the start of the next
+    #[serde(default = "default_scrub_interval_secs")]
+    pub interval_secs: u64,
+    /// Maximum read rate while scrubbing, in bytes per second (0 = unlimited)
+    #[serde(default = "default_scrub_rate_limit")]
+    pub rate_limit_bytes_per_sec: u64,
+}
+
+impl Default for ScrubConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_scrub_enabled(),
+            interval_secs: default_scrub_interval_secs(),
+            rate_limit_bytes_per_sec: default_scrub_rate_limit(),
+        }
+    }
+}
+
+fn default_scrub_enabled() -> bool {
+    true
+}
+
+fn default_scrub_interval_secs() -> u64 {
+    604800 // 7 days
+}
+
+fn default_scrub_rate_limit() -> u64 {
+    10 * 1024 * 1024 // 10 MiB/s
+}
+
+/// Upstream health probing and circuit breaker configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckConfig {
+    /// Probe every upstream in the background
+    #[serde(default = "default_health_check_enabled")]
+    pub enabled: bool,
+    /// Seconds between probes
+    #[serde(default = "default_health_check_interval_secs")]
+    pub interval_secs: u64,
+    /// Seconds after which a probe counts as failed
+    #[serde(default = "default_health_check_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Consecutive failures that open an upstream's circuit
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Consecutive successes that close a half-open circuit
+    #[serde(default = "default_success_threshold")]
+    pub success_threshold: u32,
+    /// Seconds a circuit stays open before it may half-open
+    #[serde(default = "default_open_secs")]
+    pub open_secs: u64,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_health_check_enabled(),
+            interval_secs: default_health_check_interval_secs(),
+            timeout_secs: default_health_check_timeout_secs(),
+            failure_threshold: default_failure_threshold(),
+            success_threshold: default_success_threshold(),
+            open_secs: default_open_secs(),
+        }
+    }
+}
+
+fn default_health_check_enabled() -> bool {
+    true
+}
+
+fn default_health_check_interval_secs() -> u64 {
+    30
+}
+
+fn default_health_check_timeout_secs() -> u64 {
+    5
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_success_threshold() -> u32 {
+    2
+}
+
+fn default_open_secs() -> u64 {
+    60
+}
+
 // Default value functions
 fn default_bind_address() -> String {
     "0.0.0.0".to_string()
@@ -338,6 +588,14 @@ fn default_eviction_policy() -> String {
     "lru".to_string()
 }
 
+fn default_serve_stale_on_error() -> bool {
+    true
+}
+
+fn default_stale_fallback_timeout_secs() -> u64 {
+    10
+}
+
 fn default_registry() -> String {
     "library".to_string()
 }
@@ -362,6 +620,14 @@ fn default_auth_enabled() -> bool {
     true
 }
 
+fn default_token_service() -> String {
+    "harbor-cache".to_string()
+}
+
+fn default_token_ttl_secs() -> u64 {
+    300
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -378,6 +644,18 @@ fn default_cache_isolation() -> String {
     "shared".to_string()
 }
 
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_backoff_ms() -> u64 {
+    200
+}
+
+fn default_retry_max_backoff_ms() -> u64 {
+    5000
+}
+
 impl Config {
     /// Load configuration from a file
     pub fn load(path: &str) -> Result<Self> {
@@ -420,8 +698,12 @@ impl Config {
                 priority: default_priority(),
                 enabled: true,
                 cache_isolation: default_cache_isolation(),
+                cache_max_size: None,
                 is_default: true,
                 routes: vec![],
+                tag_ttl_secs: None,
+                mirrors: vec![],
+                retry: UpstreamRetryConfig::default(),
             });
         }
     }
@@ -552,6 +834,8 @@ impl Default for Config {
                 max_size: default_max_size(),
                 retention_days: default_retention_days(),
                 eviction_policy: default_eviction_policy(),
+                serve_stale_on_error: default_serve_stale_on_error(),
+                stale_fallback_timeout_secs: default_stale_fallback_timeout_secs(),
             },
             upstream: None,
             upstreams: vec![UpstreamConfig {
@@ -566,8 +850,12 @@ impl Default for Config {
                 priority: default_priority(),
                 enabled: true,
                 cache_isolation: default_cache_isolation(),
+                cache_max_size: None,
                 is_default: true,
                 routes: vec![],
+                tag_ttl_secs: None,
+                mirrors: vec![],
+                retry: UpstreamRetryConfig::default(),
             }],
             storage: StorageConfig {
                 backend: default_backend(),
@@ -575,6 +863,9 @@ impl Default for Config {
                     path: default_local_path(),
                 },
                 s3: S3StorageConfig::default(),
+                gcs: GcsStorageConfig::default(),
+                azure: AzureStorageConfig::default(),
+                tiered: TieredStorageConfig::default(),
             },
             database: DatabaseConfig {
                 path: default_db_path(),
@@ -582,10 +873,16 @@ impl Default for Config {
             auth: AuthConfig {
                 jwt_secret: default_jwt_secret(),
                 enabled: default_auth_enabled(),
+                token_realm: None,
+                token_service: default_token_service(),
+                token_ttl_secs: default_token_ttl_secs(),
             },
             logging: LoggingConfig::default(),
             tls: TlsConfig::default(),
             blob_serving: BlobServingConfig::default(),
+            uploads: UploadsConfig::default(),
+            scrub: ScrubConfig::default(),
+            health_check: HealthCheckConfig::default(),
         }
     }
 }
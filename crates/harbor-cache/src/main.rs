@@ -1,7 +1,7 @@
 //! Harbor Cache - Lightweight caching proxy for Harbor container registries
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::fs::File;
 use std::io::BufReader;
 use std::net::SocketAddr;
@@ -17,15 +17,21 @@ use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 mod config;
 
 use config::{Config, ConfigManager, UpstreamConfig};
-use harbor_api::{AppState, BlobServingConfig, MetricsHandle, create_router};
+use harbor_api::{AppState, BlobServingConfig, MetricsHandle, RegistryAuthConfig, create_router};
 use harbor_auth::JwtManager;
 use harbor_core::config::UpstreamConfigProvider;
 use harbor_core::{
-    CacheConfig, CacheManager, RegistryService, UpstreamManager, spawn_cleanup_task,
+    CacheConfig, CacheManager, ConsistencyChecker, EvictionPolicy, FsckOptions, HealthCheckConfig,
+    RegistryService, ScrubConfig, Scrubber, UploadReaper, UploadReaperConfig, UpstreamManager,
+    spawn_circuit_logger, spawn_cleanup_task, spawn_health_prober, spawn_scrubber,
+    spawn_upload_reaper,
 };
 use harbor_db::Database;
 use harbor_proxy::{HarborClient, HarborClientConfig};
-use harbor_storage::{LocalStorage, S3Config, S3Storage, StorageBackend};
+use harbor_storage::{
+    AzureConfig, GcsConfig, LocalStorage, ObjectStorage, S3Config, StorageBackend, TieredConfig,
+    TieredStorage,
+};
 
 /// Harbor Cache - Lightweight caching proxy for Harbor registries
 #[derive(Parser, Debug)]
@@ -42,6 +48,24 @@ struct Args {
     /// Port
     #[arg(short, long, env = "HARBOR_CACHE_PORT")]
     port: Option<u16>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Maintenance commands (the server is started when none is given)
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check that storage and the database agree and optionally repair them
+    Fsck {
+        /// Repair the discrepancies found
+        #[arg(long)]
+        repair: bool,
+
+        /// Skip hashing stored content (only presence and sizes are checked)
+        #[arg(long)]
+        no_verify: bool,
+    },
 }
 
 /// Adapter to make ConfigManager implement UpstreamConfigProvider
@@ -100,6 +124,22 @@ impl UpstreamConfigProvider for ConfigManagerAdapter {
     }
 }
 
+/// Build the S3 backend configuration from the storage settings
+fn s3_config(s3: &config::S3StorageConfig) -> S3Config {
+    S3Config {
+        bucket: s3
+            .bucket
+            .clone()
+            .unwrap_or_else(|| "harbor-cache".to_string()),
+        region: s3.region.clone().unwrap_or_else(|| "us-east-1".to_string()),
+        endpoint: s3.endpoint.clone(),
+        access_key_id: s3.access_key.clone(),
+        secret_access_key: s3.secret_key.clone(),
+        prefix: s3.prefix.clone(),
+        allow_http: s3.allow_http,
+    }
+}
+
 /// Convert config::UpstreamConfig to harbor_core::UpstreamConfig
 fn config_to_core_upstream(config: &UpstreamConfig) -> harbor_core::UpstreamConfig {
     harbor_core::UpstreamConfig {
@@ -123,6 +163,7 @@ fn config_to_core_upstream(config: &UpstreamConfig) -> harbor_core::UpstreamConf
         priority: config.priority,
         enabled: config.enabled,
         cache_isolation: config.cache_isolation.clone(),
+        cache_max_size: config.cache_max_size,
         is_default: config.is_default,
         routes: config
             .routes
@@ -130,8 +171,16 @@ fn config_to_core_upstream(config: &UpstreamConfig) -> harbor_core::UpstreamConf
             .map(|r| harbor_core::UpstreamRouteConfig {
                 pattern: r.pattern.clone(),
                 priority: r.priority,
+                tag_ttl_secs: r.tag_ttl_secs,
             })
             .collect(),
+        tag_ttl_secs: config.tag_ttl_secs,
+        mirrors: config.mirrors.clone(),
+        retry: harbor_core::UpstreamRetryConfig {
+            max_attempts: config.retry.max_attempts,
+            initial_backoff_ms: config.retry.initial_backoff_ms,
+            max_backoff_ms: config.retry.max_backoff_ms,
+        },
     }
 }
 
@@ -158,6 +207,7 @@ fn core_to_config_upstream(core: &harbor_core::UpstreamConfig) -> UpstreamConfig
         priority: core.priority,
         enabled: core.enabled,
         cache_isolation: core.cache_isolation.clone(),
+        cache_max_size: core.cache_max_size,
         is_default: core.is_default,
         routes: core
             .routes
@@ -165,8 +215,16 @@ fn core_to_config_upstream(core: &harbor_core::UpstreamConfig) -> UpstreamConfig
             .map(|r| config::UpstreamRouteConfig {
                 pattern: r.pattern.clone(),
                 priority: r.priority,
+                tag_ttl_secs: r.tag_ttl_secs,
             })
             .collect(),
+        tag_ttl_secs: core.tag_ttl_secs,
+        mirrors: core.mirrors.clone(),
+        retry: config::UpstreamRetryConfig {
+            max_attempts: core.retry.max_attempts,
+            initial_backoff_ms: core.retry.initial_backoff_ms,
+            max_backoff_ms: core.retry.max_backoff_ms,
+        },
     }
 }
 
@@ -207,27 +265,64 @@ async fn main() -> Result<()> {
     // Initialize storage backend
     let storage: Arc<dyn StorageBackend> = match config.storage.backend.as_str() {
         "s3" => {
-            let s3_config = S3Config {
-                bucket: config
-                    .storage
-                    .s3
+            let s3_config = s3_config(&config.storage.s3);
+            info!("Using S3 storage backend: bucket={}", s3_config.bucket);
+            Arc::new(ObjectStorage::s3(s3_config).await?)
+        }
+        "gcs" => {
+            let gcs = &config.storage.gcs;
+            let gcs_config = GcsConfig {
+                bucket: gcs
                     .bucket
                     .clone()
                     .unwrap_or_else(|| "harbor-cache".to_string()),
-                region: config
-                    .storage
-                    .s3
-                    .region
+                service_account_path: gcs.service_account_path.clone(),
+                service_account_key: gcs.service_account_key.clone(),
+                prefix: gcs.prefix.clone(),
+                allow_http: gcs.allow_http,
+            };
+            info!("Using GCS storage backend: bucket={}", gcs_config.bucket);
+            Arc::new(ObjectStorage::gcs(gcs_config).await?)
+        }
+        "azure" => {
+            let azure = &config.storage.azure;
+            let azure_config = AzureConfig {
+                account: azure.account.clone(),
+                container: azure
+                    .container
                     .clone()
-                    .unwrap_or_else(|| "us-east-1".to_string()),
-                endpoint: config.storage.s3.endpoint.clone(),
-                access_key_id: config.storage.s3.access_key.clone(),
-                secret_access_key: config.storage.s3.secret_key.clone(),
-                prefix: config.storage.s3.prefix.clone(),
-                allow_http: config.storage.s3.allow_http,
+                    .unwrap_or_else(|| "harbor-cache".to_string()),
+                access_key: azure.access_key.clone(),
+                endpoint: azure.endpoint.clone(),
+                use_emulator: azure.use_emulator,
+                prefix: azure.prefix.clone(),
+                allow_http: azure.allow_http,
             };
-            info!("Using S3 storage backend: bucket={}", s3_config.bucket);
-            Arc::new(S3Storage::new(s3_config).await?)
+            info!(
+                "Using Azure storage backend: container={}",
+                azure_config.container
+            );
+            Arc::new(ObjectStorage::azure(azure_config).await?)
+        }
+        "tiered" => {
+            let s3_config = s3_config(&config.storage.s3);
+            let tiered = &config.storage.tiered;
+            tokio::fs::create_dir_all(&tiered.path).await?;
+            info!(
+                "Using tiered storage backend: hot path={} (max {} bytes), cold bucket={}",
+                tiered.path, tiered.max_size, s3_config.bucket
+            );
+            let cold: Arc<dyn StorageBackend> = Arc::new(ObjectStorage::s3(s3_config).await?);
+            let tiered_config = TieredConfig {
+                hot_max_size: tiered.max_size,
+                policy: config
+                    .cache
+                    .eviction_policy
+                    .parse::<EvictionPolicy>()
+                    .unwrap_or_default()
+                    .into(),
+            };
+            Arc::new(TieredStorage::new(&tiered.path, cold, tiered_config).await?)
         }
         _ => {
             // Default to local storage
@@ -246,48 +341,119 @@ async fn main() -> Result<()> {
         Arc::new(ConfigManagerAdapter::new(config_manager.clone()));
 
     // Initialize upstream manager with config provider
+    let health_check = HealthCheckConfig {
+        interval_secs: config.health_check.interval_secs,
+        timeout_secs: config.health_check.timeout_secs,
+        failure_threshold: config.health_check.failure_threshold,
+        success_threshold: config.health_check.success_threshold,
+        open_secs: config.health_check.open_secs,
+    };
     let upstream_manager = Arc::new(
-        UpstreamManager::new(config_provider.clone())
+        UpstreamManager::with_health_check(config_provider.clone(), health_check)
             .context("Failed to initialize upstream manager")?,
     );
 
-    // Get the default upstream for the legacy RegistryService
-    // For compatibility, we still need a single HarborClient for RegistryService
-    let default_upstream = config
-        .get_default_upstream()
-        .ok_or_else(|| anyhow::anyhow!("No default upstream configured"))?;
-
-    let upstream = Arc::new(HarborClient::new(HarborClientConfig {
-        url: default_upstream.url.clone(),
-        registry: default_upstream.registry.clone(),
-        username: default_upstream.username.clone(),
-        password: default_upstream.password.clone(),
-        skip_tls_verify: default_upstream.skip_tls_verify,
-    })?);
-
-    info!(
-        "Default upstream: {} -> {}",
-        default_upstream.name, default_upstream.url
-    );
-
     // Initialize cache manager
     let cache_config = CacheConfig {
         max_size: config.cache.max_size,
         retention_days: config.cache.retention_days,
         eviction_policy: config.cache.eviction_policy.parse().unwrap_or_default(),
+        serve_stale_on_error: config.cache.serve_stale_on_error,
+        stale_fallback_timeout_secs: config.cache.stale_fallback_timeout_secs,
     };
     let cache = Arc::new(CacheManager::new(db.clone(), storage.clone(), cache_config));
 
+    // Initialize registry service. Pulls and pushes are routed through the
+    // upstream manager; the legacy single-upstream mode is only used when no
+    // upstream could be loaded into the manager.
+    let registry = if upstream_manager.upstream_count() > 0 {
+        info!(
+            "Routing registry traffic through upstream manager ({} upstreams)",
+            upstream_manager.upstream_count()
+        );
+        Arc::new(RegistryService::with_upstream_manager(
+            cache.clone(),
+            upstream_manager.clone(),
+            db.clone(),
+            storage.clone(),
+        ))
+    } else {
+        let default_upstream = config
+            .get_default_upstream()
+            .ok_or_else(|| anyhow::anyhow!("No default upstream configured"))?;
+
+        tracing::warn!(
+            "Upstream manager has no usable upstreams, falling back to single upstream mode: {} -> {}",
+            default_upstream.name,
+            default_upstream.url
+        );
+
+        let upstream = Arc::new(HarborClient::new(HarborClientConfig {
+            url: default_upstream.url.clone(),
+            registry: default_upstream.registry.clone(),
+            username: default_upstream.username.clone(),
+            password: default_upstream.password.clone(),
+            skip_tls_verify: default_upstream.skip_tls_verify,
+            retry: config_to_core_upstream(default_upstream).retry.policy(),
+        })?);
+
+        Arc::new(RegistryService::new(
+            cache.clone(),
+            upstream,
+            db.clone(),
+            storage.clone(),
+        ))
+    };
+
+    // Open the isolated upstream caches so maintenance covers them
+    registry.register_upstreams().await?;
+
+    if let Some(Command::Fsck { repair, no_verify }) = args.command {
+        let checker = ConsistencyChecker::new(cache, registry);
+        // Offline: nothing else is reading storage, so hash at full speed
+        let options = FsckOptions {
+            repair,
+            verify_digests: !no_verify,
+            bytes_per_sec: 0,
+        };
+        return run_fsck(&checker, options).await;
+    }
+
+    // Record circuit breaker transitions in the activity log
+    let _circuit_logger_handle = spawn_circuit_logger(&upstream_manager, db.clone());
+
+    // Spawn background prober that feeds the upstream circuit breakers
+    if config.health_check.enabled {
+        let _prober_handle = spawn_health_prober(upstream_manager.clone());
+    }
+
     // Spawn background cleanup task (runs every hour)
     let _cleanup_handle = spawn_cleanup_task(cache.clone(), 1);
 
-    // Initialize registry service
-    let registry = Arc::new(RegistryService::new(
-        cache.clone(),
-        upstream,
+    // Spawn background reaper for abandoned upload sessions
+    let upload_reaper = Arc::new(UploadReaper::new(
         db.clone(),
         storage.clone(),
+        UploadReaperConfig {
+            session_timeout_secs: config.uploads.session_timeout_secs,
+            interval_secs: config.uploads.reaper_interval_secs,
+        },
     ));
+    let _reaper_handle = spawn_upload_reaper(upload_reaper);
+
+    // Spawn background scrubber that re-verifies stored digests
+    if config.scrub.enabled {
+        let scrubber = Arc::new(Scrubber::new(
+            db.clone(),
+            cache.clone(),
+            registry.clone(),
+            ScrubConfig {
+                interval_secs: config.scrub.interval_secs,
+                bytes_per_sec: config.scrub.rate_limit_bytes_per_sec,
+            },
+        ));
+        let _scrub_handle = spawn_scrubber(scrubber);
+    }
 
     // Initialize JWT manager
     let jwt = Arc::new(JwtManager::new(&config.auth.jwt_secret, 24));
@@ -305,6 +471,12 @@ async fn main() -> Result<()> {
         );
     }
 
+    let registry_auth = RegistryAuthConfig {
+        realm: config.auth.token_realm.clone(),
+        service: config.auth.token_service.clone(),
+        token_ttl_secs: config.auth.token_ttl_secs,
+    };
+
     // Create application state
     let state = AppState::new(
         db,
@@ -316,6 +488,7 @@ async fn main() -> Result<()> {
         upstream_manager,
         config_provider,
         blob_serving,
+        registry_auth,
     );
 
     // Initialize Prometheus metrics
@@ -407,6 +580,36 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Run a consistency check and print its report.
+///
+/// Fails if discrepancies remain, so scripts can act on the exit status.
+async fn run_fsck(checker: &ConsistencyChecker, options: FsckOptions) -> Result<()> {
+    let report = checker.run(options).await?;
+
+    for issue in &report.issues {
+        let repair = match &issue.repair {
+            Some(repair) => format!(" [repair: {:?}]", repair),
+            None => String::new(),
+        };
+        println!(
+            "{:?} {}: {}{}",
+            issue.kind, issue.digest, issue.detail, repair
+        );
+    }
+    println!(
+        "Checked {} cache entries and {} stored blobs: {} issues, {} unresolved",
+        report.entries_checked,
+        report.blobs_checked,
+        report.issues.len(),
+        report.unresolved()
+    );
+
+    if report.unresolved() > 0 {
+        anyhow::bail!("{} discrepancies left unresolved", report.unresolved());
+    }
+    Ok(())
+}
+
 /// Initialize logging
 fn init_logging(level: &str) {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
@@ -434,6 +637,50 @@ fn init_metrics() -> Option<MetricsHandle> {
             metrics::describe_counter!("harbor_cache_misses_total", "Total number of cache misses");
             metrics::describe_gauge!("harbor_cache_size_bytes", "Current cache size in bytes");
             metrics::describe_gauge!("harbor_cache_entries", "Current number of cache entries");
+            metrics::describe_counter!(
+                "harbor_cache_upload_sessions_expired_total",
+                "Total number of idle upload sessions expired by the reaper"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_upload_orphans_deleted_total",
+                "Total number of orphaned upload temp files deleted by the reaper"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_upload_reclaimed_bytes_total",
+                "Total bytes of upload temp data reclaimed by the reaper"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_scrub_verified_blobs_total",
+                "Total number of stored blobs whose digest was re-verified by the scrubber"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_scrub_verified_bytes_total",
+                "Total bytes re-verified by the scrubber"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_scrub_corrupt_blobs_total",
+                "Total number of corrupt or missing blobs found by the scrubber"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_coalesced_fetches_total",
+                "Total number of requests that shared a concurrent upstream fetch"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_upstream_failovers_total",
+                "Total number of upstream fetches retried against the next mirror"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_upstream_retries_total",
+                "Total number of upstream requests retried and blob downloads resumed"
+            );
+            metrics::describe_counter!(
+                "harbor_cache_upstream_circuit_transitions_total",
+                "Total number of upstream circuit breaker state changes"
+            );
+            metrics::describe_gauge!(
+                "harbor_cache_upstream_circuit_state",
+                "Upstream circuit breaker state (0 = closed, 1 = half-open, 2 = open)"
+            );
             metrics::describe_histogram!(
                 "harbor_cache_request_duration_seconds",
                 "Request duration in seconds"
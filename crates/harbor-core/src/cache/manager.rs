@@ -3,14 +3,18 @@
 use bytes::Bytes;
 use chrono::{Duration, Utc};
 use futures::StreamExt;
-use harbor_db::{CacheEntry, CacheStats, Database, EntryType, NewCacheEntry};
+use harbor_db::{CacheEntry, CacheStats, Database, EntryType, NewCacheEntry, SHARED_NAMESPACE};
+use harbor_proxy::ContentRange;
 use harbor_storage::{StorageBackend, backend::ByteStream};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 use super::policy::EvictionPolicy;
 use crate::error::CoreError;
+use crate::manifest::{referenced_digests, subject_of};
+use crate::range::ByteRange;
 
 /// Configuration for the cache manager
 #[derive(Debug, Clone)]
@@ -21,6 +25,10 @@ pub struct CacheConfig {
     pub retention_days: u32,
     /// Eviction policy
     pub eviction_policy: EvictionPolicy,
+    /// Serve cached manifests for tag pulls when the upstream is unavailable
+    pub serve_stale_on_error: bool,
+    /// Upstream timeout in seconds for tag pulls that have a cached fallback
+    pub stale_fallback_timeout_secs: u64,
 }
 
 impl Default for CacheConfig {
@@ -29,16 +37,26 @@ impl Default for CacheConfig {
             max_size: 10 * 1024 * 1024 * 1024, // 10 GB
             retention_days: 30,
             eviction_policy: EvictionPolicy::Lru,
+            serve_stale_on_error: true,
+            stale_fallback_timeout_secs: 10,
         }
     }
 }
 
 /// Cache manager for handling blob and manifest caching
+///
+/// The root manager owns the shared cache namespace. Upstreams with an
+/// isolated cache get a child manager with its own namespace, storage
+/// prefix and size limit; see [`CacheManager::isolated`].
 pub struct CacheManager {
     db: Database,
     storage: Arc<dyn StorageBackend>,
     config: CacheConfig,
-    stats: RwLock<CacheStats>,
+    /// Hit and miss counters, shared with the isolated caches
+    stats: Arc<RwLock<CacheStats>>,
+    namespace: i64,
+    /// Isolated caches by upstream ID
+    isolated: RwLock<HashMap<i64, Arc<CacheManager>>>,
 }
 
 impl CacheManager {
@@ -55,10 +73,90 @@ impl CacheManager {
             db,
             storage,
             config,
-            stats: RwLock::new(CacheStats::default()),
+            stats: Arc::new(RwLock::new(CacheStats::default())),
+            namespace: SHARED_NAMESPACE,
+            isolated: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Get the cache configuration
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Namespace of the entries managed by this cache
+    pub fn namespace(&self) -> i64 {
+        self.namespace
+    }
+
+    /// Get the isolated cache of an upstream, creating it on first use.
+    ///
+    /// Its entries live in the upstream's own namespace and storage prefix,
+    /// both keyed by the upstream ID so that renaming the upstream keeps its
+    /// cached content, and are evicted to stay within `max_size` independently of the shared
+    /// cache. Retention and eviction policy are inherited from this cache.
+    pub async fn isolated(
+        &self,
+        upstream_id: i64,
+        name: &str,
+        max_size: u64,
+    ) -> Result<Arc<CacheManager>, CoreError> {
+        if let Some(cache) = self.isolated.read().await.get(&upstream_id)
+            && cache.config.max_size == max_size
+        {
+            return Ok(cache.clone());
+        }
+
+        let mut isolated = self.isolated.write().await;
+        if let Some(cache) = isolated.get(&upstream_id)
+            && cache.config.max_size == max_size
+        {
+            return Ok(cache.clone());
+        }
+
+        info!(
+            "Using isolated cache for upstream {} (max_size: {} bytes)",
+            name, max_size
+        );
+        let cache = Arc::new(CacheManager {
+            db: self.db.clone(),
+            storage: self.storage.namespace(&upstream_id.to_string()).await?,
+            config: CacheConfig {
+                max_size,
+                ..self.config.clone()
+            },
+            stats: self.stats.clone(),
+            namespace: upstream_id,
+            isolated: RwLock::new(HashMap::new()),
+        });
+        isolated.insert(upstream_id, cache.clone());
+        Ok(cache)
+    }
+
+    /// Isolated caches created so far
+    async fn isolated_caches(&self) -> Vec<Arc<CacheManager>> {
+        self.isolated.read().await.values().cloned().collect()
+    }
+
+    /// This cache followed by the isolated caches created so far, in
+    /// namespace order
+    pub(crate) async fn with_isolated(self: &Arc<Self>) -> Vec<Arc<CacheManager>> {
+        let mut caches = self.isolated_caches().await;
+        caches.sort_by_key(|cache| cache.namespace);
+        caches.insert(0, self.clone());
+        caches
+    }
+
+    /// Storage backend holding the content of this cache
+    pub(crate) fn storage(&self) -> &Arc<dyn StorageBackend> {
+        &self.storage
+    }
+
+    /// Database of the cache entries
+    pub(crate) fn db(&self) -> &Database {
+        &self.db
+    }
+
     /// Get cache statistics
     pub async fn stats(&self) -> CacheStats {
         let mut stats: CacheStats = self.stats.read().await.clone();
@@ -76,7 +174,10 @@ impl CacheManager {
 
     /// Check if a blob/manifest is cached
     pub async fn exists(&self, digest: &str) -> Result<bool, CoreError> {
-        let entry = self.db.get_cache_entry_by_digest(digest).await?;
+        let entry = self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?;
         if entry.is_some() {
             // Also verify storage
             return Ok(self.storage.exists(digest).await?);
@@ -86,7 +187,11 @@ impl CacheManager {
 
     /// Get a cached entry
     pub async fn get(&self, digest: &str) -> Result<Option<(Bytes, CacheEntry)>, CoreError> {
-        let entry = match self.db.get_cache_entry_by_digest(digest).await? {
+        let entry = match self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?
+        {
             Some(e) => e,
             None => {
                 self.record_miss().await;
@@ -98,14 +203,14 @@ impl CacheManager {
         match self.storage.read(digest).await {
             Ok(data) => {
                 // Update access time
-                self.db.touch_cache_entry(digest).await?;
+                self.db.touch_cache_entry(digest, self.namespace).await?;
                 self.record_hit().await;
                 Ok(Some((data, entry)))
             }
             Err(harbor_storage::StorageError::NotFound(_)) => {
                 // Storage doesn't have it, clean up database
                 warn!("Cache entry in database but not in storage: {}", digest);
-                self.db.delete_cache_entry(digest).await?;
+                self.db.delete_cache_entry(digest, self.namespace).await?;
                 self.record_miss().await;
                 Ok(None)
             }
@@ -118,7 +223,11 @@ impl CacheManager {
         &self,
         digest: &str,
     ) -> Result<Option<(ByteStream, CacheEntry)>, CoreError> {
-        let entry = match self.db.get_cache_entry_by_digest(digest).await? {
+        let entry = match self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?
+        {
             Some(e) => e,
             None => {
                 self.record_miss().await;
@@ -130,14 +239,61 @@ impl CacheManager {
         match self.storage.stream(digest).await {
             Ok(stream) => {
                 // Update access time
-                self.db.touch_cache_entry(digest).await?;
+                self.db.touch_cache_entry(digest, self.namespace).await?;
                 self.record_hit().await;
                 Ok(Some((stream, entry)))
             }
             Err(harbor_storage::StorageError::NotFound(_)) => {
                 // Storage doesn't have it, clean up database
                 warn!("Cache entry in database but not in storage: {}", digest);
-                self.db.delete_cache_entry(digest).await?;
+                self.db.delete_cache_entry(digest, self.namespace).await?;
+                self.record_miss().await;
+                Ok(None)
+            }
+            Err(e) => Err(CoreError::Storage(e)),
+        }
+    }
+
+    /// Get a byte range of a cached entry as a stream.
+    ///
+    /// Fails with `RangeNotSatisfiable` if the range lies outside the entry.
+    pub async fn get_range_stream(
+        &self,
+        digest: &str,
+        range: &ByteRange,
+    ) -> Result<Option<(ByteStream, ContentRange)>, CoreError> {
+        let entry = match self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?
+        {
+            Some(e) => e,
+            None => {
+                self.record_miss().await;
+                return Ok(None);
+            }
+        };
+
+        let content_range =
+            range
+                .resolve(entry.size as u64)
+                .ok_or(CoreError::RangeNotSatisfiable {
+                    size: Some(entry.size as u64),
+                })?;
+
+        match self
+            .storage
+            .stream_range(digest, content_range.start, content_range.end)
+            .await
+        {
+            Ok(stream) => {
+                self.db.touch_cache_entry(digest, self.namespace).await?;
+                self.record_hit().await;
+                Ok(Some((stream, content_range)))
+            }
+            Err(harbor_storage::StorageError::NotFound(_)) => {
+                warn!("Cache entry in database but not in storage: {}", digest);
+                self.db.delete_cache_entry(digest, self.namespace).await?;
                 self.record_miss().await;
                 Ok(None)
             }
@@ -147,10 +303,14 @@ impl CacheManager {
 
     /// Get a cached entry's metadata only
     pub async fn get_metadata(&self, digest: &str) -> Result<Option<CacheEntry>, CoreError> {
-        Ok(self.db.get_cache_entry_by_digest(digest).await?)
+        Ok(self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?)
     }
 
     /// Store a blob/manifest in the cache
+    #[allow(clippy::too_many_arguments)]
     pub async fn put(
         &self,
         entry_type: EntryType,
@@ -159,6 +319,7 @@ impl CacheManager {
         digest: &str,
         content_type: &str,
         data: Bytes,
+        upstream_id: Option<i64>,
     ) -> Result<CacheEntry, CoreError> {
         let size = data.len() as i64;
 
@@ -170,15 +331,27 @@ impl CacheManager {
         );
 
         // Check if already cached
-        if let Some(entry) = self.db.get_cache_entry_by_digest(digest).await? {
+        if let Some(entry) = self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?
+        {
             debug!("Entry already cached: {}", digest);
-            self.db.touch_cache_entry(digest).await?;
+            self.db.touch_cache_entry(digest, self.namespace).await?;
             return Ok(entry);
         }
 
         // Ensure we have space
         self.ensure_space(size as u64).await?;
 
+        let (references, subject) = match entry_type {
+            EntryType::Manifest => (
+                referenced_digests(&data),
+                subject_of(&data, digest, content_type),
+            ),
+            EntryType::Blob => (Vec::new(), None),
+        };
+
         // Write to storage
         let storage_path = self.storage.write(digest, data).await?;
 
@@ -193,10 +366,27 @@ impl CacheManager {
                 content_type: content_type.to_string(),
                 size,
                 storage_path,
-                upstream_id: None,
+                upstream_id,
+                namespace: self.namespace,
             })
             .await?;
 
+        if entry.entry_type == EntryType::Manifest {
+            let children: Vec<_> = references
+                .into_iter()
+                .map(|r| (r.digest, r.entry_type))
+                .collect();
+            self.db.set_manifest_references(digest, &children).await?;
+
+            if let Some(repository) = &entry.repository
+                && let Some((subject, referrer)) = subject
+            {
+                self.db
+                    .upsert_manifest_referrer(repository, &subject, &referrer.to_record())
+                    .await?;
+            }
+        }
+
         debug!("Cached entry: {}", digest);
         Ok(entry)
     }
@@ -212,6 +402,7 @@ impl CacheManager {
         content_type: &str,
         stream: ByteStream,
         expected_size: Option<u64>,
+        upstream_id: Option<i64>,
     ) -> Result<CacheEntry, CoreError> {
         debug!(
             "Caching {} {} (streaming, expected size: {:?})",
@@ -221,9 +412,13 @@ impl CacheManager {
         );
 
         // Check if already cached
-        if let Some(entry) = self.db.get_cache_entry_by_digest(digest).await? {
+        if let Some(entry) = self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?
+        {
             debug!("Entry already cached: {}", digest);
-            self.db.touch_cache_entry(digest).await?;
+            self.db.touch_cache_entry(digest, self.namespace).await?;
             return Ok(entry);
         }
 
@@ -252,7 +447,8 @@ impl CacheManager {
                 content_type: content_type.to_string(),
                 size: actual_size,
                 storage_path,
-                upstream_id: None,
+                upstream_id,
+                namespace: self.namespace,
             })
             .await?;
 
@@ -278,6 +474,7 @@ impl CacheManager {
         content_type: &str,
         mut source_stream: ByteStream,
         expected_size: Option<u64>,
+        upstream_id: Option<i64>,
     ) -> Result<
         (
             ByteStream,
@@ -293,9 +490,13 @@ impl CacheManager {
         );
 
         // Check if already cached
-        if let Some(entry) = self.db.get_cache_entry_by_digest(digest).await? {
+        if let Some(entry) = self
+            .db
+            .get_cache_entry_by_digest(digest, self.namespace)
+            .await?
+        {
             debug!("Entry already cached during tee: {}", digest);
-            self.db.touch_cache_entry(digest).await?;
+            self.db.touch_cache_entry(digest, self.namespace).await?;
             // Return the cached stream
             let stream = self.storage.stream(digest).await?;
             let handle = tokio::spawn(async move { Ok(entry) });
@@ -313,6 +514,7 @@ impl CacheManager {
         // Clone data needed for the spawned task
         let storage = self.storage.clone();
         let db = self.db.clone();
+        let namespace = self.namespace;
         let digest_owned = digest.to_string();
         let content_type_owned = content_type.to_string();
 
@@ -382,7 +584,8 @@ impl CacheManager {
                     content_type: content_type_owned,
                     size: actual_size,
                     storage_path,
-                    upstream_id: None,
+                    upstream_id,
+                    namespace,
                 })
                 .await?;
 
@@ -396,6 +599,46 @@ impl CacheManager {
         Ok((client_stream, cache_handle))
     }
 
+    /// Record content that is already in storage as a cache entry.
+    ///
+    /// Used to re-register content whose entry was lost. The entry has no
+    /// repository; manifests get their references indexed.
+    pub async fn register_stored(
+        &self,
+        entry_type: EntryType,
+        digest: &str,
+        content_type: &str,
+        size: u64,
+    ) -> Result<CacheEntry, CoreError> {
+        let entry = self
+            .db
+            .insert_cache_entry(NewCacheEntry {
+                entry_type,
+                repository: None,
+                reference: None,
+                digest: digest.to_string(),
+                content_type: content_type.to_string(),
+                size: size as i64,
+                storage_path: self.storage.storage_path(digest),
+                // Isolated namespaces are the ID of their upstream
+                upstream_id: (self.namespace != SHARED_NAMESPACE).then_some(self.namespace),
+                namespace: self.namespace,
+            })
+            .await?;
+
+        if entry.entry_type == EntryType::Manifest {
+            let data = self.storage.read(digest).await?;
+            let children: Vec<_> = referenced_digests(&data)
+                .into_iter()
+                .map(|r| (r.digest, r.entry_type))
+                .collect();
+            self.db.set_manifest_references(digest, &children).await?;
+        }
+
+        debug!("Registered stored entry: {} ({} bytes)", digest, size);
+        Ok(entry)
+    }
+
     /// Delete a cached entry
     pub async fn delete(&self, digest: &str) -> Result<bool, CoreError> {
         debug!("Deleting cache entry: {}", digest);
@@ -404,7 +647,20 @@ impl CacheManager {
         self.storage.delete(digest).await?;
 
         // Delete from database
-        let deleted = self.db.delete_cache_entry(digest).await?;
+        let deleted = self.db.delete_cache_entry(digest, self.namespace).await?;
+        if !self.db.is_digest_cached(digest).await? {
+            self.db.delete_manifest_references(digest).await?;
+            self.db.delete_manifest_referrer(digest).await?;
+        }
+        Ok(deleted)
+    }
+
+    /// Delete an entry from the shared cache and every isolated cache
+    pub async fn purge(&self, digest: &str) -> Result<bool, CoreError> {
+        let mut deleted = self.delete(digest).await?;
+        for cache in self.isolated_caches().await {
+            deleted |= cache.delete(digest).await?;
+        }
         Ok(deleted)
     }
 
@@ -412,16 +668,15 @@ impl CacheManager {
     pub async fn clear(&self) -> Result<u64, CoreError> {
         info!("Clearing all cache entries");
 
-        let entries = self.db.get_cache_entries_lru(10000).await?;
-        let count = entries.len() as u64;
+        let entries = self.db.get_cache_entries_lru(self.namespace, 10000).await?;
+        let mut count = entries.len() as u64;
 
         for entry in entries {
-            if let Err(e) = self.storage.delete(&entry.digest).await {
-                warn!("Failed to delete storage for {}: {}", entry.digest, e);
-            }
-            if let Err(e) = self.db.delete_cache_entry(&entry.digest).await {
-                warn!("Failed to delete db entry for {}: {}", entry.digest, e);
-            }
+            self.remove_entry(&entry).await;
+        }
+
+        for cache in self.isolated_caches().await {
+            count += Box::pin(cache.clear()).await?;
         }
 
         info!("Cleared {} cache entries", count);
@@ -430,7 +685,7 @@ impl CacheManager {
 
     /// Ensure there's enough space for a new entry
     async fn ensure_space(&self, required: u64) -> Result<(), CoreError> {
-        let current_size = self.db.get_total_cache_size().await? as u64;
+        let current_size = self.db.get_namespace_cache_size(self.namespace).await? as u64;
 
         if current_size + required <= self.config.max_size {
             return Ok(());
@@ -442,41 +697,38 @@ impl CacheManager {
         self.evict(to_free).await
     }
 
+    /// Get eviction candidates in the order dictated by the configured policy
+    async fn eviction_candidates(&self, limit: i64) -> Result<Vec<CacheEntry>, CoreError> {
+        let entries = match self.config.eviction_policy {
+            EvictionPolicy::Lru => self.db.get_cache_entries_lru(self.namespace, limit).await?,
+            EvictionPolicy::Lfu => self.db.get_cache_entries_lfu(self.namespace, limit).await?,
+            EvictionPolicy::Fifo => {
+                self.db
+                    .get_cache_entries_fifo(self.namespace, limit)
+                    .await?
+            }
+        };
+        Ok(entries)
+    }
+
     /// Evict entries to free up space
+    ///
+    /// Entries referenced by a cached manifest are never evicted on their own.
+    /// Evicting a manifest also evicts the blobs and child manifests that no
+    /// other cached manifest references, so images are removed as a whole.
     async fn evict(&self, bytes_to_free: u64) -> Result<(), CoreError> {
         let mut freed = 0u64;
 
         // Get entries to evict based on policy
-        let entries = match self.config.eviction_policy {
-            EvictionPolicy::Lru => self.db.get_cache_entries_lru(100).await?,
-            EvictionPolicy::Lfu => {
-                // For LFU, we'd need a different query sorted by access_count
-                // For now, use LRU as fallback
-                self.db.get_cache_entries_lru(100).await?
-            }
-            EvictionPolicy::Fifo => {
-                // For FIFO, we'd need a query sorted by created_at
-                // For now, use LRU as fallback
-                self.db.get_cache_entries_lru(100).await?
-            }
-        };
+        let entries = self.eviction_candidates(1000).await?;
 
         for entry in entries {
             if freed >= bytes_to_free {
                 break;
             }
 
-            debug!("Evicting cache entry: {}", entry.digest);
-
-            if let Err(e) = self.storage.delete(&entry.digest).await {
-                warn!("Failed to delete storage for {}: {}", entry.digest, e);
-            }
-
-            if let Err(e) = self.db.delete_cache_entry(&entry.digest).await {
-                warn!("Failed to delete db entry for {}: {}", entry.digest, e);
-            }
-
-            freed += entry.size as u64;
+            let (_, bytes) = self.remove_unreferenced(entry, |_| true).await?;
+            freed += bytes;
         }
 
         info!("Evicted {} bytes from cache", freed);
@@ -484,31 +736,165 @@ impl CacheManager {
     }
 
     /// Run cleanup of expired entries
+    ///
+    /// Expired blobs stay cached while a non-expired manifest references them.
     pub async fn cleanup_expired(&self) -> Result<u64, CoreError> {
         let cutoff = Utc::now() - Duration::days(self.config.retention_days as i64);
         info!("Cleaning up entries older than {:?}", cutoff);
 
-        let entries = self.db.get_cache_entries_lru(10000).await?;
-        let mut cleaned = 0u64;
+        let mut entries: Vec<CacheEntry> = self
+            .db
+            .get_cache_entries_lru(self.namespace, 10000)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.last_accessed_at < cutoff)
+            .collect();
+
+        // Release manifests first so the blobs they hold become removable
+        entries.sort_by_key(|entry| entry.entry_type != EntryType::Manifest);
 
+        let mut cleaned = 0u64;
         for entry in entries {
-            if entry.last_accessed_at < cutoff {
-                debug!("Cleaning expired entry: {}", entry.digest);
+            let (removed, _) = self
+                .remove_unreferenced(entry, |child| child.last_accessed_at < cutoff)
+                .await?;
+            cleaned += removed;
+        }
 
-                if let Err(e) = self.storage.delete(&entry.digest).await {
-                    warn!("Failed to delete storage for {}: {}", entry.digest, e);
-                }
+        for cache in self.isolated_caches().await {
+            cleaned += Box::pin(cache.cleanup_expired()).await?;
+        }
+
+        info!("Cleaned up {} expired entries", cleaned);
+        Ok(cleaned)
+    }
+
+    /// Remove an entry unless a cached manifest still references it.
+    ///
+    /// Manifests cascade to their children that are no longer referenced and
+    /// satisfy `cascade`. Returns the number of entries and bytes removed.
+    async fn remove_unreferenced(
+        &self,
+        entry: CacheEntry,
+        cascade: impl Fn(&CacheEntry) -> bool,
+    ) -> Result<(u64, u64), CoreError> {
+        // The entry may already be gone as part of an earlier image
+        if self
+            .db
+            .get_cache_entry_by_digest(&entry.digest, self.namespace)
+            .await?
+            .is_none()
+        {
+            return Ok((0, 0));
+        }
+        if self
+            .db
+            .is_referenced_by_cached_manifest(&entry.digest, self.namespace)
+            .await?
+        {
+            debug!("Keeping {}: referenced by a cached manifest", entry.digest);
+            return Ok((0, 0));
+        }
+
+        let mut removed = 0u64;
+        let mut freed = 0u64;
+        let mut pending = vec![entry];
+
+        while let Some(entry) = pending.pop() {
+            let children = match entry.entry_type {
+                EntryType::Manifest => self.db.get_manifest_references(&entry.digest).await?,
+                EntryType::Blob => Vec::new(),
+            };
 
-                if let Err(e) = self.db.delete_cache_entry(&entry.digest).await {
-                    warn!("Failed to delete db entry for {}: {}", entry.digest, e);
+            debug!("Evicting cache entry: {}", entry.digest);
+            self.remove_entry(&entry).await;
+            removed += 1;
+            freed += entry.size as u64;
+
+            for child in children {
+                let Some(child_entry) = self
+                    .db
+                    .get_cache_entry_by_digest(&child.child_digest, self.namespace)
+                    .await?
+                else {
+                    continue;
+                };
+                if cascade(&child_entry)
+                    && !self
+                        .db
+                        .is_referenced_by_cached_manifest(&child_entry.digest, self.namespace)
+                        .await?
+                {
+                    pending.push(child_entry);
                 }
+            }
+        }
 
-                cleaned += 1;
+        Ok((removed, freed))
+    }
+
+    /// Delete an entry from storage and the database, logging failures
+    async fn remove_entry(&self, entry: &CacheEntry) {
+        if let Err(e) = self.storage.delete(&entry.digest).await {
+            warn!("Failed to delete storage for {}: {}", entry.digest, e);
+        }
+
+        if let Err(e) = self
+            .db
+            .delete_cache_entry(&entry.digest, self.namespace)
+            .await
+        {
+            warn!("Failed to delete db entry for {}: {}", entry.digest, e);
+        }
+
+        // Another namespace may still hold the same manifest
+        let shared = matches!(self.db.is_digest_cached(&entry.digest).await, Ok(true));
+        if entry.entry_type == EntryType::Manifest && !shared {
+            if let Err(e) = self.db.delete_manifest_references(&entry.digest).await {
+                warn!("Failed to delete references for {}: {}", entry.digest, e);
+            }
+            if let Err(e) = self.db.delete_manifest_referrer(&entry.digest).await {
+                warn!("Failed to delete referrer {}: {}", entry.digest, e);
             }
         }
+    }
 
-        info!("Cleaned up {} expired entries", cleaned);
-        Ok(cleaned)
+    /// Record references for cached manifests that predate the reference graph
+    pub async fn index_manifest_references(&self) -> Result<u64, CoreError> {
+        let manifests = self.db.get_unindexed_manifests(self.namespace).await?;
+        let mut indexed = 0u64;
+
+        for entry in manifests {
+            let data = match self.storage.read(&entry.digest).await {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("Failed to read manifest {}: {}", entry.digest, e);
+                    continue;
+                }
+            };
+
+            let children: Vec<_> = referenced_digests(&data)
+                .into_iter()
+                .map(|r| (r.digest, r.entry_type))
+                .collect();
+            if children.is_empty() {
+                continue;
+            }
+
+            self.db
+                .set_manifest_references(&entry.digest, &children)
+                .await?;
+            indexed += 1;
+        }
+
+        for cache in self.isolated_caches().await {
+            indexed += Box::pin(cache.index_manifest_references()).await?;
+        }
+
+        if indexed > 0 {
+            info!("Indexed references for {} cached manifests", indexed);
+        }
+        Ok(indexed)
     }
 
     /// Record a cache hit
@@ -525,20 +911,24 @@ impl CacheManager {
 
     /// Run size enforcement to ensure cache is within limits
     pub async fn enforce_size_limit(&self) -> Result<u64, CoreError> {
-        let current_size = self.db.get_total_cache_size().await? as u64;
-
-        if current_size <= self.config.max_size {
-            return Ok(0);
+        let current_size = self.db.get_namespace_cache_size(self.namespace).await? as u64;
+
+        let mut freed = 0;
+        if current_size > self.config.max_size {
+            let to_free = current_size - self.config.max_size;
+            info!(
+                "Cache size {} exceeds limit {}, freeing {} bytes",
+                current_size, self.config.max_size, to_free
+            );
+
+            self.evict(to_free).await?;
+            freed += to_free;
         }
 
-        let to_free = current_size - self.config.max_size;
-        info!(
-            "Cache size {} exceeds limit {}, freeing {} bytes",
-            current_size, self.config.max_size, to_free
-        );
-
-        self.evict(to_free).await?;
-        Ok(to_free)
+        for cache in self.isolated_caches().await {
+            freed += Box::pin(cache.enforce_size_limit()).await?;
+        }
+        Ok(freed)
     }
 
     /// Run full maintenance: cleanup expired entries and enforce size limits
@@ -573,6 +963,11 @@ pub fn spawn_cleanup_task(
     );
 
     tokio::spawn(async move {
+        // Build the reference graph for manifests cached before it existed
+        if let Err(e) = cache.index_manifest_references().await {
+            warn!("Failed to index manifest references: {}", e);
+        }
+
         let mut ticker = interval(Duration::from_secs(interval_hours * 3600));
 
         // Skip the first tick (which fires immediately)
@@ -598,3 +993,387 @@ pub fn spawn_cleanup_task(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harbor_storage::LocalStorage;
+    use harbor_storage::backend::compute_sha256;
+
+    /// Insert a blob and then pin its bookkeeping columns
+    async fn insert_entry(
+        cache: &CacheManager,
+        data: &'static [u8],
+        created_at: &str,
+        last_accessed_at: &str,
+        access_count: i64,
+    ) -> String {
+        let digest = compute_sha256(data);
+        cache
+            .put(
+                EntryType::Blob,
+                Some("library/test".to_string()),
+                None,
+                &digest,
+                "application/octet-stream",
+                Bytes::from_static(data),
+                None,
+            )
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "UPDATE cache_entries SET created_at = ?, last_accessed_at = ?, access_count = ? WHERE digest = ?",
+        )
+        .bind(created_at)
+        .bind(last_accessed_at)
+        .bind(access_count)
+        .bind(&digest)
+        .execute(cache.db.pool())
+        .await
+        .unwrap();
+
+        digest
+    }
+
+    /// Build a cache with three entries where each policy has a different victim:
+    /// - `oldest`: created first, but recently and frequently used (FIFO victim)
+    /// - `stale`: not accessed for the longest time, but frequently used (LRU victim)
+    /// - `rare`: newest and recently used, but accessed only once (LFU victim)
+    async fn setup(policy: EvictionPolicy, dir: &tempfile::TempDir) -> (CacheManager, [String; 3]) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage = Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+        let cache = CacheManager::new(
+            db,
+            storage,
+            CacheConfig {
+                eviction_policy: policy,
+                ..CacheConfig::default()
+            },
+        );
+
+        let oldest = insert_entry(
+            &cache,
+            b"oldest",
+            "2024-01-01T00:00:00+00:00",
+            "2024-03-01T00:00:00+00:00",
+            50,
+        )
+        .await;
+        let stale = insert_entry(
+            &cache,
+            b"stale",
+            "2024-01-02T00:00:00+00:00",
+            "2024-01-02T00:00:00+00:00",
+            40,
+        )
+        .await;
+        let rare = insert_entry(
+            &cache,
+            b"rare",
+            "2024-01-03T00:00:00+00:00",
+            "2024-03-02T00:00:00+00:00",
+            1,
+        )
+        .await;
+
+        (cache, [oldest, stale, rare])
+    }
+
+    /// Evict a single byte's worth and return the digest that was removed
+    async fn evict_one(cache: &CacheManager, digests: &[String; 3]) -> String {
+        cache.evict(1).await.unwrap();
+
+        let mut evicted = Vec::new();
+        for digest in digests {
+            if cache.get_metadata(digest).await.unwrap().is_none() {
+                evicted.push(digest.clone());
+            }
+        }
+        assert_eq!(evicted.len(), 1, "exactly one entry should be evicted");
+        evicted.remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_lru_evicts_least_recently_accessed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, digests) = setup(EvictionPolicy::Lru, &dir).await;
+
+        assert_eq!(evict_one(&cache, &digests).await, digests[1]);
+    }
+
+    #[tokio::test]
+    async fn test_lfu_evicts_least_frequently_accessed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, digests) = setup(EvictionPolicy::Lfu, &dir).await;
+
+        assert_eq!(evict_one(&cache, &digests).await, digests[2]);
+    }
+
+    #[tokio::test]
+    async fn test_fifo_evicts_oldest_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, digests) = setup(EvictionPolicy::Fifo, &dir).await;
+
+        assert_eq!(evict_one(&cache, &digests).await, digests[0]);
+    }
+
+    #[tokio::test]
+    async fn test_policies_pick_different_victims() {
+        let mut victims = Vec::new();
+        for policy in [
+            EvictionPolicy::Lru,
+            EvictionPolicy::Lfu,
+            EvictionPolicy::Fifo,
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let (cache, digests) = setup(policy, &dir).await;
+            victims.push(evict_one(&cache, &digests).await);
+        }
+
+        victims.sort();
+        victims.dedup();
+        assert_eq!(victims.len(), 3);
+    }
+
+    /// Cache an image manifest referencing the given blobs and pin its access time
+    async fn insert_manifest(
+        cache: &CacheManager,
+        layers: &[&String],
+        last_accessed_at: &str,
+    ) -> String {
+        let layers: Vec<_> = layers
+            .iter()
+            .map(|digest| {
+                serde_json::json!({
+                    "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                    "digest": digest,
+                    "size": 1,
+                })
+            })
+            .collect();
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "layers": layers,
+        }))
+        .unwrap();
+        let digest = compute_sha256(&manifest);
+
+        cache
+            .put(
+                EntryType::Manifest,
+                Some("library/test".to_string()),
+                Some("latest".to_string()),
+                &digest,
+                "application/vnd.oci.image.manifest.v1+json",
+                Bytes::from(manifest),
+                None,
+            )
+            .await
+            .unwrap();
+
+        sqlx::query("UPDATE cache_entries SET last_accessed_at = ? WHERE digest = ?")
+            .bind(last_accessed_at)
+            .bind(&digest)
+            .execute(cache.db.pool())
+            .await
+            .unwrap();
+
+        digest
+    }
+
+    #[tokio::test]
+    async fn test_eviction_skips_blobs_referenced_by_cached_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, [oldest, stale, rare]) = setup(EvictionPolicy::Lru, &dir).await;
+        insert_manifest(&cache, &[&stale], "2024-04-01T00:00:00+00:00").await;
+
+        cache.evict(1).await.unwrap();
+
+        // `stale` is the LRU victim but still belongs to a cached image
+        assert!(cache.get_metadata(&stale).await.unwrap().is_some());
+        assert!(cache.get_metadata(&oldest).await.unwrap().is_none());
+        assert!(cache.get_metadata(&rare).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_evicting_manifest_removes_unshared_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, [oldest, stale, rare]) = setup(EvictionPolicy::Lru, &dir).await;
+        let evicted =
+            insert_manifest(&cache, &[&oldest, &stale], "2023-01-01T00:00:00+00:00").await;
+        let retained = insert_manifest(&cache, &[&oldest], "2024-04-01T00:00:00+00:00").await;
+
+        cache.evict(1).await.unwrap();
+
+        assert!(cache.get_metadata(&evicted).await.unwrap().is_none());
+        assert!(cache.get_metadata(&stale).await.unwrap().is_none());
+        // Shared with a retained manifest
+        assert!(cache.get_metadata(&oldest).await.unwrap().is_some());
+        assert!(cache.get_metadata(&retained).await.unwrap().is_some());
+        assert!(cache.get_metadata(&rare).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_with_subject_is_indexed_as_referrer() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, _) = setup(EvictionPolicy::Lru, &dir).await;
+
+        let signature = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "artifactType": "application/vnd.dev.cosign.artifact.sig.v1+json",
+            "layers": [],
+            "subject": {
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": "sha256:subject",
+                "size": 1,
+            },
+        }))
+        .unwrap();
+        let digest = compute_sha256(&signature);
+        cache
+            .put(
+                EntryType::Manifest,
+                Some("library/test".to_string()),
+                Some(digest.clone()),
+                &digest,
+                "application/vnd.oci.image.manifest.v1+json",
+                Bytes::from(signature),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let referrers = cache
+            .db
+            .list_manifest_referrers("library/test", "sha256:subject", None)
+            .await
+            .unwrap();
+        assert_eq!(referrers.len(), 1);
+        assert_eq!(referrers[0].digest, digest);
+
+        let filtered = cache
+            .db
+            .list_manifest_referrers("library/test", "sha256:subject", Some("text/spdx"))
+            .await
+            .unwrap();
+        assert!(filtered.is_empty());
+
+        cache.delete(&digest).await.unwrap();
+        let referrers = cache
+            .db
+            .list_manifest_referrers("library/test", "sha256:subject", None)
+            .await
+            .unwrap();
+        assert!(referrers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_range_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cache, _) = setup(EvictionPolicy::Lru, &dir).await;
+        let digest = insert_entry(
+            &cache,
+            b"0123456789",
+            "2024-01-01T00:00:00+00:00",
+            "2024-01-01T00:00:00+00:00",
+            1,
+        )
+        .await;
+
+        let range = ByteRange::parse("bytes=2-5").unwrap();
+        let (stream, content_range) = cache
+            .get_range_stream(&digest, &range)
+            .await
+            .unwrap()
+            .unwrap();
+        let data: Vec<Bytes> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(data.concat(), b"2345");
+        assert_eq!(content_range.header_value(), "bytes 2-5/10");
+
+        let range = ByteRange::parse("bytes=10-").unwrap();
+        assert!(matches!(
+            cache.get_range_stream(&digest, &range).await,
+            Err(CoreError::RangeNotSatisfiable { size: Some(10) })
+        ));
+    }
+
+    async fn put(cache: &CacheManager, data: &'static [u8], upstream_id: Option<i64>) -> String {
+        let digest = compute_sha256(data);
+        cache
+            .put(
+                EntryType::Blob,
+                Some("library/test".to_string()),
+                None,
+                &digest,
+                "application/octet-stream",
+                Bytes::from_static(data),
+                upstream_id,
+            )
+            .await
+            .unwrap();
+        digest
+    }
+
+    #[tokio::test]
+    async fn test_isolated_cache_is_separate_from_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage = Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+        let cache = CacheManager::new(db.clone(), storage, CacheConfig::default());
+        let upstream_id = db
+            .register_upstream(harbor_db::NewUpstream {
+                name: "team-a".to_string(),
+                display_name: "Team A".to_string(),
+                url: "https://harbor.example.com".to_string(),
+                registry: "library".to_string(),
+                username: None,
+                password: None,
+                skip_tls_verify: false,
+                priority: 100,
+                enabled: true,
+                cache_isolation: harbor_db::CacheIsolation::Isolated,
+                is_default: false,
+            })
+            .await
+            .unwrap();
+        let isolated = cache.isolated(upstream_id, "team-a", 10).await.unwrap();
+        assert_eq!(isolated.namespace(), upstream_id);
+        assert!(Arc::ptr_eq(
+            &isolated,
+            &cache.isolated(upstream_id, "team-a", 10).await.unwrap()
+        ));
+
+        // The same digest is cached once per namespace
+        let first = put(&cache, b"0123456789", None).await;
+        put(&isolated, b"0123456789", Some(upstream_id)).await;
+        assert!(isolated.delete(&first).await.unwrap());
+        assert!(cache.exists(&first).await.unwrap());
+        assert!(!isolated.exists(&first).await.unwrap());
+
+        // The isolated cache evicts within its own quota only
+        put(&isolated, b"0123456789", Some(upstream_id)).await;
+        let second = put(&isolated, b"abcdefghij", Some(upstream_id)).await;
+        assert!(!isolated.exists(&first).await.unwrap());
+        assert!(isolated.exists(&second).await.unwrap());
+        assert!(cache.exists(&first).await.unwrap());
+        assert!(
+            dir.path()
+                .join("namespaces")
+                .join(upstream_id.to_string())
+                .exists()
+        );
+
+        let stats = db.get_cache_stats_by_upstream(upstream_id).await.unwrap();
+        assert_eq!(stats.entry_count, 1);
+        assert_eq!(stats.total_size, 10);
+
+        // Purging removes the digest from every namespace
+        put(&cache, b"abcdefghij", None).await;
+        assert!(cache.purge(&second).await.unwrap());
+        assert!(!cache.exists(&second).await.unwrap());
+        assert!(!isolated.exists(&second).await.unwrap());
+    }
+}
@@ -39,6 +39,17 @@ impl EvictionPolicy {
     }
 }
 
+/// The hot tier of tiered storage demotes blobs with the cache's policy
+impl From<EvictionPolicy> for harbor_storage::DemotionPolicy {
+    fn from(policy: EvictionPolicy) -> Self {
+        match policy {
+            EvictionPolicy::Lru => Self::Lru,
+            EvictionPolicy::Lfu => Self::Lfu,
+            EvictionPolicy::Fifo => Self::Fifo,
+        }
+    }
+}
+
 impl FromStr for EvictionPolicy {
     type Err = ParseEvictionPolicyError;
 
@@ -4,9 +4,11 @@
 //! The main config loading is done in harbor-cache, but these types
 //! define the upstream configuration structure used by harbor-core.
 
+use harbor_proxy::RetryPolicy;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 
 // ==================== Validation Constants ====================
 
@@ -109,6 +111,10 @@ pub struct UpstreamRouteConfig {
     /// Priority for this route (lower = higher priority)
     #[serde(default = "default_priority")]
     pub priority: i32,
+    /// How long (seconds) a cached tag is served without revalidation.
+    /// Overrides the upstream-level `tag_ttl_secs` for matching repositories.
+    #[serde(default)]
+    pub tag_ttl_secs: Option<u64>,
 }
 
 impl UpstreamRouteConfig {
@@ -157,6 +163,58 @@ impl UpstreamProjectConfig {
     }
 }
 
+/// Retry policy for idempotent requests to an upstream
+///
+/// Applies to manifest and blob pulls (GET/HEAD) and token fetches.
+/// Interrupted blob downloads are resumed with a range request, using
+/// the same attempt budget.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamRetryConfig {
+    /// Attempts per request, including the first (1 disables retries)
+    #[serde(default = "default_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds, doubled for every
+    /// further retry
+    #[serde(default = "default_retry_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the delay between attempts in milliseconds.
+    /// Responses asking to retry later than this are not retried.
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for UpstreamRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_max_attempts(),
+            initial_backoff_ms: default_retry_initial_backoff_ms(),
+            max_backoff_ms: default_retry_max_backoff_ms(),
+        }
+    }
+}
+
+impl UpstreamRetryConfig {
+    /// Get the retry policy for the upstream client
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            initial_backoff: Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.max_backoff_ms),
+        }
+    }
+
+    /// Validate this retry configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        if self.initial_backoff_ms > self.max_backoff_ms {
+            return Err("initial_backoff_ms must not exceed max_backoff_ms".to_string());
+        }
+        Ok(())
+    }
+}
+
 /// Upstream configuration for a single Harbor registry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpstreamConfig {
@@ -193,12 +251,28 @@ pub struct UpstreamConfig {
     /// Cache isolation mode: "shared" or "isolated"
     #[serde(default = "default_cache_isolation")]
     pub cache_isolation: String,
+    /// Maximum size in bytes of the cache of an isolated upstream, required
+    /// for isolated upstreams. It comes on top of the global cache size limit.
+    #[serde(default)]
+    pub cache_max_size: Option<u64>,
     /// Whether this is the default upstream (fallback)
     #[serde(default)]
     pub is_default: bool,
     /// Route patterns for this upstream
     #[serde(default)]
     pub routes: Vec<UpstreamRouteConfig>,
+    /// How long (seconds) a cached tag is served without revalidation.
+    /// When unset, cached tags are revalidated with a HEAD request on every pull.
+    #[serde(default)]
+    pub tag_ttl_secs: Option<u64>,
+    /// Names of upstreams holding the same content (e.g. a DR replica).
+    /// Pulls routed to this upstream fail over to them, in order, when it
+    /// errors or times out.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Retry policy for idempotent requests to this upstream
+    #[serde(default)]
+    pub retry: UpstreamRetryConfig,
 }
 
 impl UpstreamConfig {
@@ -298,6 +372,48 @@ impl UpstreamConfig {
             }
         }
 
+        // Validate mirrors
+        for (idx, mirror) in self.mirrors.iter().enumerate() {
+            if mirror == &self.name {
+                return Err(format!("Upstream '{}' cannot be its own mirror", self.name));
+            }
+            if self.mirrors[..idx].contains(mirror) {
+                return Err(format!(
+                    "Upstream '{}' lists mirror '{}' more than once",
+                    self.name, mirror
+                ));
+            }
+        }
+
+        // Validate the cache quota, which isolated upstreams must set so that
+        // the disk used by every namespace is bounded by explicit limits
+        match (self.uses_isolated_cache(), self.cache_max_size) {
+            (true, None) => {
+                return Err(format!(
+                    "Upstream '{}' uses an isolated cache without cache_max_size",
+                    self.name
+                ));
+            }
+            (false, Some(_)) => {
+                return Err(format!(
+                    "Upstream '{}' sets cache_max_size without an isolated cache",
+                    self.name
+                ));
+            }
+            _ => {}
+        }
+        if self.cache_max_size == Some(0) {
+            return Err(format!(
+                "Upstream '{}' cache_max_size must be greater than 0",
+                self.name
+            ));
+        }
+
+        // Validate retry policy
+        if let Err(e) = self.retry.validate() {
+            return Err(format!("Upstream '{}' retry: {}", self.name, e));
+        }
+
         // Validate registry name if using single-project mode
         if self.projects.is_empty()
             && let Err(e) = validate_project_name(&self.registry)
@@ -531,6 +647,18 @@ fn default_cache_isolation() -> String {
     "shared".to_string()
 }
 
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_backoff_ms() -> u64 {
+    200
+}
+
+fn default_retry_max_backoff_ms() -> u64 {
+    5000
+}
+
 /// Trait for providing upstream configuration
 /// This allows the config to be managed externally (e.g., by harbor-cache)
 /// while harbor-core can use it for upstream management
@@ -657,8 +785,12 @@ mod tests {
             priority: 100,
             enabled: true,
             cache_isolation: "shared".to_string(),
+            cache_max_size: None,
             is_default: true,
             routes: vec![],
+            tag_ttl_secs: None,
+            mirrors: vec![],
+            retry: UpstreamRetryConfig::default(),
         }
     }
 
@@ -909,6 +1041,55 @@ mod tests {
         assert!(upstream.validate().is_ok());
     }
 
+    #[test]
+    fn test_upstream_validate_mirrors() {
+        let mut upstream = create_test_upstream(vec![]);
+        upstream.mirrors = vec!["dr".to_string(), "edge".to_string()];
+        assert!(upstream.validate().is_ok());
+
+        upstream.mirrors = vec!["test".to_string()];
+        assert!(upstream.validate().is_err());
+
+        upstream.mirrors = vec!["dr".to_string(), "dr".to_string()];
+        assert!(upstream.validate().is_err());
+    }
+
+    #[test]
+    fn test_upstream_validate_cache_quota() {
+        let mut upstream = create_test_upstream(vec![]);
+        upstream.cache_max_size = Some(1024);
+        assert!(upstream.validate().is_err());
+
+        upstream.cache_isolation = "isolated".to_string();
+        assert!(upstream.validate().is_ok());
+
+        upstream.cache_max_size = Some(0);
+        assert!(upstream.validate().is_err());
+
+        // Isolated upstreams need their own quota
+        upstream.cache_max_size = None;
+        assert!(upstream.validate().is_err());
+
+        // Isolated storage is keyed by ID, so any name will do
+        upstream.cache_max_size = Some(1024);
+        upstream.name = "team a".to_string();
+        assert!(upstream.validate().is_ok());
+    }
+
+    #[test]
+    fn test_upstream_validate_retry() {
+        let mut upstream = create_test_upstream(vec![]);
+        assert!(upstream.validate().is_ok());
+        assert_eq!(upstream.retry.policy(), RetryPolicy::default());
+
+        upstream.retry.max_attempts = 0;
+        assert!(upstream.validate().is_err());
+
+        upstream.retry.max_attempts = 1;
+        upstream.retry.initial_backoff_ms = 10_000;
+        assert!(upstream.validate().is_err());
+    }
+
     // ==================== ReDoS Protection Tests ====================
 
     #[test]
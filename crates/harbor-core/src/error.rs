@@ -24,4 +24,22 @@ pub enum CoreError {
 
     #[error("Cache miss")]
     CacheMiss,
+
+    /// The upstream does not support the operation (e.g. deletes disabled)
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+
+    /// The failure of a concurrent request for the same content that this
+    /// request waited on
+    #[error("Coalesced request failed: {0}")]
+    Coalesced(String),
+
+    /// A byte range outside the blob; carries the blob size if known
+    #[error("Requested range not satisfiable")]
+    RangeNotSatisfiable { size: Option<u64> },
+
+    /// An upload chunk that does not continue the session; carries the
+    /// number of bytes the session has received
+    #[error("Upload chunk does not start at offset {offset}")]
+    UploadRangeInvalid { offset: u64 },
 }
@@ -0,0 +1,696 @@
+//! Storage / database consistency checker
+//!
+//! The cache manager only notices missing or damaged content when it is
+//! requested. The checker walks storage and the cache entries table,
+//! reports every discrepancy between the two and can repair them.
+//! The shared cache and every isolated upstream cache are checked, each
+//! against its own storage prefix.
+
+use futures::StreamExt;
+use harbor_db::{CacheEntry, EntryType};
+use harbor_storage::StorageBackend;
+use harbor_storage::backend::{ByteStream, parse_digest, validate_digest};
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::cache::CacheManager;
+use crate::error::CoreError;
+use crate::manifest::manifest_media_type;
+use crate::registry::RegistryService;
+use crate::scrub::Throttle;
+
+/// Stored content larger than this is never inspected as a manifest
+const MAX_MANIFEST_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Hash a stream with the given algorithm and return its digest
+async fn hash_stream<D: Digest>(
+    algorithm: &str,
+    mut stream: ByteStream,
+    mut throttle: Option<&mut Throttle>,
+) -> Result<String, CoreError> {
+    let mut hasher = D::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        hasher.update(&chunk);
+        if let Some(throttle) = throttle.as_deref_mut() {
+            throttle.consume(chunk.len() as u64).await;
+        }
+    }
+    Ok(format!("{}:{}", algorithm, hex::encode(hasher.finalize())))
+}
+
+/// Hash stored content with the algorithm of its digest and return the
+/// digest of what is actually stored. Reads are paced by the throttle, if
+/// any.
+pub(crate) async fn stored_digest(
+    storage: &dyn StorageBackend,
+    digest: &str,
+    throttle: Option<&mut Throttle>,
+) -> Result<String, CoreError> {
+    let (algorithm, _) = parse_digest(digest)?;
+    let stream = storage.verify_stream(digest).await?;
+    match algorithm {
+        "sha256" => hash_stream::<Sha256>(algorithm, stream, throttle).await,
+        "sha512" => hash_stream::<Sha512>(algorithm, stream, throttle).await,
+        _ => Err(CoreError::BadRequest(format!(
+            "Unsupported digest algorithm: {}",
+            algorithm
+        ))),
+    }
+}
+
+/// Drop a lost or corrupt entry and fetch its content again from the
+/// upstream of its repository
+pub(crate) async fn replace_entry(
+    cache: &CacheManager,
+    registry: &RegistryService,
+    entry: &CacheEntry,
+) -> FsckRepair {
+    if let Err(e) = cache.delete(&entry.digest).await {
+        return FsckRepair::Failed(e.to_string());
+    }
+    if entry.repository.is_none() {
+        return FsckRepair::Removed;
+    }
+
+    match registry.refetch_entry(entry).await {
+        Ok(()) => FsckRepair::Refetched,
+        Err(e) => {
+            warn!("Failed to re-fetch {}: {}", entry.digest, e);
+            FsckRepair::Removed
+        }
+    }
+}
+
+/// Kind of discrepancy between storage and the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsckIssueKind {
+    /// Content in storage without a cache entry
+    Unregistered,
+    /// Cache entry whose content is missing from storage
+    Missing,
+    /// Stored size differs from the size recorded in the cache entry
+    SizeMismatch,
+    /// Stored content does not hash to its digest
+    DigestMismatch,
+}
+
+/// Repair applied to an issue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsckRepair {
+    /// Stored content was registered as a cache entry
+    Registered,
+    /// The recorded size was corrected
+    SizeUpdated,
+    /// Corrupt content without a cache entry was deleted from storage
+    Deleted,
+    /// The entry was dropped and its content fetched again from upstream
+    Refetched,
+    /// The entry was dropped; the content is fetched again on the next pull
+    Removed,
+    /// The repair failed
+    Failed(String),
+}
+
+/// A discrepancy found by the checker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FsckIssue {
+    pub kind: FsckIssueKind,
+    pub digest: String,
+    pub detail: String,
+    /// Repair applied (None when only reporting)
+    pub repair: Option<FsckRepair>,
+}
+
+/// Result of a consistency check
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FsckReport {
+    /// Distinct digests with a cache entry
+    pub entries_checked: u64,
+    /// Blobs found in storage
+    pub blobs_checked: u64,
+    pub issues: Vec<FsckIssue>,
+}
+
+impl FsckReport {
+    /// Number of issues that were not repaired
+    pub fn unresolved(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| matches!(issue.repair, None | Some(FsckRepair::Failed(_))))
+            .count()
+    }
+}
+
+/// What a consistency check does
+#[derive(Debug, Clone, Copy)]
+pub struct FsckOptions {
+    /// Repair the discrepancies found
+    pub repair: bool,
+    /// Hash all stored content against its digest (reads everything)
+    pub verify_digests: bool,
+    /// Maximum average read rate while hashing (0 = unlimited)
+    pub bytes_per_sec: u64,
+}
+
+impl Default for FsckOptions {
+    fn default() -> Self {
+        Self {
+            repair: false,
+            verify_digests: true,
+            bytes_per_sec: 0,
+        }
+    }
+}
+
+/// Checks that storage and the cache entries table agree
+pub struct ConsistencyChecker {
+    cache: Arc<CacheManager>,
+    registry: Arc<RegistryService>,
+}
+
+impl ConsistencyChecker {
+    /// Create a new consistency checker
+    pub fn new(cache: Arc<CacheManager>, registry: Arc<RegistryService>) -> Self {
+        Self { cache, registry }
+    }
+
+    /// Check storage against the database, repairing issues if requested.
+    ///
+    /// Storage is listed before the database and every finding is
+    /// re-checked, so content being cached while the check runs is not
+    /// reported.
+    pub async fn run(&self, options: FsckOptions) -> Result<FsckReport, CoreError> {
+        info!(
+            "Checking cache consistency (repair: {}, verify digests: {})",
+            options.repair, options.verify_digests
+        );
+
+        let mut report = FsckReport::default();
+        let mut throttle = Throttle::new(options.bytes_per_sec);
+        for cache in self.cache.with_isolated().await {
+            self.check_namespace(&cache, options, &mut throttle, &mut report)
+                .await?;
+        }
+
+        info!(
+            "Consistency check done: {} entries, {} blobs, {} issues ({} unresolved)",
+            report.entries_checked,
+            report.blobs_checked,
+            report.issues.len(),
+            report.unresolved()
+        );
+        Ok(report)
+    }
+
+    /// Check the entries of one cache namespace against its storage
+    async fn check_namespace(
+        &self,
+        cache: &CacheManager,
+        options: FsckOptions,
+        throttle: &mut Throttle,
+        report: &mut FsckReport,
+    ) -> Result<(), CoreError> {
+        debug!("Checking cache namespace {}", cache.namespace());
+        let stored: BTreeMap<String, u64> = cache
+            .storage()
+            .list_blobs()
+            .await?
+            .into_iter()
+            .map(|blob| (blob.digest, blob.size))
+            .collect();
+        let entries = cache.db().list_all_cache_entries(cache.namespace()).await?;
+        report.blobs_checked += stored.len() as u64;
+
+        let mut registered = HashSet::new();
+        for entry in entries {
+            registered.insert(entry.digest.clone());
+            report.entries_checked += 1;
+
+            if let Some(issue) = self
+                .check_entry(cache, &entry, stored.get(&entry.digest), options, throttle)
+                .await?
+            {
+                report
+                    .issues
+                    .push(self.resolve(cache, issue, &entry, options).await);
+            }
+        }
+
+        for (digest, size) in &stored {
+            if registered.contains(digest) {
+                continue;
+            }
+            if let Some(issue) = self
+                .check_unregistered(cache, digest, *size, options, throttle)
+                .await?
+            {
+                report.issues.push(issue);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compare a cache entry with its stored content
+    async fn check_entry(
+        &self,
+        cache: &CacheManager,
+        entry: &CacheEntry,
+        stored_size: Option<&u64>,
+        options: FsckOptions,
+        throttle: &mut Throttle,
+    ) -> Result<Option<FsckIssue>, CoreError> {
+        let issue = |kind, detail| FsckIssue {
+            kind,
+            digest: entry.digest.clone(),
+            detail,
+            repair: None,
+        };
+
+        let stored_size = match stored_size {
+            Some(size) => *size,
+            // Written after storage was listed
+            None if cache.storage().exists(&entry.digest).await? => return Ok(None),
+            None => {
+                return Ok(Some(issue(
+                    FsckIssueKind::Missing,
+                    format!(
+                        "{} has a cache entry but no content",
+                        entry.entry_type.as_str()
+                    ),
+                )));
+            }
+        };
+
+        // A size mismatch is only a metadata error if the content is intact
+        let size_matches = stored_size == entry.size as u64;
+        if size_matches && !options.verify_digests {
+            return Ok(None);
+        }
+
+        let actual =
+            match stored_digest(cache.storage().as_ref(), &entry.digest, Some(throttle)).await {
+                Ok(actual) => actual,
+                Err(e) => {
+                    warn!("Failed to hash {}: {}", entry.digest, e);
+                    return Ok(None);
+                }
+            };
+
+        if actual != entry.digest {
+            Ok(Some(issue(
+                FsckIssueKind::DigestMismatch,
+                format!("stored content hashes to {}", actual),
+            )))
+        } else if !size_matches {
+            Ok(Some(issue(
+                FsckIssueKind::SizeMismatch,
+                format!(
+                    "recorded size {} but {} bytes stored",
+                    entry.size, stored_size
+                ),
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Repair an issue of a cache entry if requested
+    async fn resolve(
+        &self,
+        cache: &CacheManager,
+        mut issue: FsckIssue,
+        entry: &CacheEntry,
+        options: FsckOptions,
+    ) -> FsckIssue {
+        debug!("{:?} {}: {}", issue.kind, issue.digest, issue.detail);
+        if !options.repair {
+            return issue;
+        }
+
+        let repair = match issue.kind {
+            FsckIssueKind::SizeMismatch => update_size(cache, &entry.digest)
+                .await
+                .unwrap_or_else(|e| FsckRepair::Failed(e.to_string())),
+            _ => replace_entry(cache, &self.registry, entry).await,
+        };
+
+        issue.repair = Some(repair);
+        issue
+    }
+
+    /// Check content found in storage without a cache entry, registering
+    /// intact content and deleting corrupt content if requested
+    async fn check_unregistered(
+        &self,
+        cache: &CacheManager,
+        digest: &str,
+        size: u64,
+        options: FsckOptions,
+        throttle: &mut Throttle,
+    ) -> Result<Option<FsckIssue>, CoreError> {
+        // Registered after the database was read
+        if cache.get_metadata(digest).await?.is_some() {
+            return Ok(None);
+        }
+
+        let mut issue = FsckIssue {
+            kind: FsckIssueKind::Unregistered,
+            digest: digest.to_string(),
+            detail: format!("{} bytes stored without a cache entry", size),
+            repair: None,
+        };
+
+        let intact = if validate_digest(digest).is_err() {
+            issue.kind = FsckIssueKind::DigestMismatch;
+            issue.detail = "stored under an invalid digest".to_string();
+            false
+        } else if options.verify_digests || options.repair {
+            match stored_digest(cache.storage().as_ref(), digest, Some(throttle)).await {
+                Ok(actual) if actual == digest => true,
+                Ok(actual) => {
+                    issue.kind = FsckIssueKind::DigestMismatch;
+                    issue.detail = format!(
+                        "{} bytes stored without a cache entry, content hashes to {}",
+                        size, actual
+                    );
+                    false
+                }
+                Err(e) => {
+                    warn!("Failed to hash {}: {}", digest, e);
+                    return Ok(None);
+                }
+            }
+        } else {
+            true
+        };
+
+        debug!("{:?} {}: {}", issue.kind, issue.digest, issue.detail);
+        if !options.repair {
+            return Ok(Some(issue));
+        }
+
+        let repair = if intact {
+            register(cache, digest, size).await
+        } else {
+            cache
+                .storage()
+                .delete(digest)
+                .await
+                .map(|_| FsckRepair::Deleted)
+                .map_err(CoreError::from)
+        };
+        issue.repair = Some(repair.unwrap_or_else(|e| FsckRepair::Failed(e.to_string())));
+        Ok(Some(issue))
+    }
+}
+
+/// Record the stored size of intact content in its entry
+async fn update_size(cache: &CacheManager, digest: &str) -> Result<FsckRepair, CoreError> {
+    let size = cache.storage().size(digest).await?;
+    cache
+        .db()
+        .update_cache_entry_size(digest, cache.namespace(), size as i64)
+        .await?;
+    Ok(FsckRepair::SizeUpdated)
+}
+
+/// Register intact stored content as a manifest or blob entry
+async fn register(cache: &CacheManager, digest: &str, size: u64) -> Result<FsckRepair, CoreError> {
+    let media_type = if size <= MAX_MANIFEST_SIZE {
+        manifest_media_type(&cache.storage().read(digest).await?)
+    } else {
+        None
+    };
+
+    let (entry_type, content_type) = match media_type {
+        Some(media_type) => (EntryType::Manifest, media_type),
+        None => (EntryType::Blob, "application/octet-stream".to_string()),
+    };
+    cache
+        .register_stored(entry_type, digest, &content_type, size)
+        .await?;
+    Ok(FsckRepair::Registered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use harbor_db::{CacheIsolation, Database, NewUpstream, SHARED_NAMESPACE};
+    use harbor_proxy::{HarborClient, HarborClientConfig};
+    use harbor_storage::LocalStorage;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    fn sha256(data: &[u8]) -> String {
+        format!("sha256:{}", hex::encode(Sha256::digest(data)))
+    }
+
+    fn blob_file(dir: &Path, digest: &str) -> PathBuf {
+        let hash = digest.strip_prefix("sha256:").unwrap();
+        dir.join("blobs").join("sha256").join(&hash[..2]).join(hash)
+    }
+
+    async fn checker(
+        dir: &tempfile::TempDir,
+    ) -> (
+        ConsistencyChecker,
+        Database,
+        Arc<dyn StorageBackend>,
+        Arc<CacheManager>,
+    ) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+        let cache = Arc::new(CacheManager::new(
+            db.clone(),
+            storage.clone(),
+            crate::CacheConfig::default(),
+        ));
+        // Unreachable upstream: re-fetches fail
+        let client = HarborClient::new(HarborClientConfig {
+            url: "http://127.0.0.1:1".to_string(),
+            registry: "library".to_string(),
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            retry: Default::default(),
+        })
+        .unwrap();
+        let registry = Arc::new(RegistryService::new(
+            cache.clone(),
+            Arc::new(client),
+            db.clone(),
+            storage.clone(),
+        ));
+        let checker = ConsistencyChecker::new(cache.clone(), registry);
+        (checker, db, storage, cache)
+    }
+
+    #[tokio::test]
+    async fn test_reports_and_repairs_discrepancies() {
+        let dir = tempfile::tempdir().unwrap();
+        let (checker, db, storage, cache) = checker(&dir).await;
+
+        let put = |data: &'static [u8], repository: Option<&str>| {
+            let cache = cache.clone();
+            let repository = repository.map(str::to_string);
+            async move {
+                let digest = sha256(data);
+                cache
+                    .put(
+                        EntryType::Blob,
+                        repository,
+                        None,
+                        &digest,
+                        "application/octet-stream",
+                        Bytes::from_static(data),
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                digest
+            }
+        };
+
+        put(b"intact", Some("library/app")).await;
+
+        let corrupt = put(b"corrupt", Some("library/app")).await;
+        std::fs::write(blob_file(dir.path(), &corrupt), b"CORRUPT").unwrap();
+
+        let resized = put(b"resized", None).await;
+        db.update_cache_entry_size(&resized, SHARED_NAMESPACE, 1)
+            .await
+            .unwrap();
+
+        let missing = put(b"missing", None).await;
+        storage.delete(&missing).await.unwrap();
+
+        let manifest: &[u8] = br#"{"schemaVersion": 2, "config": {"digest": "sha256:c0"}, "layers": [{"digest": "sha256:l1"}]}"#;
+        let unregistered = sha256(manifest);
+        storage
+            .write(&unregistered, Bytes::from_static(manifest))
+            .await
+            .unwrap();
+
+        let stray = sha256(b"stray");
+        let path = blob_file(dir.path(), &stray);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"not stray").unwrap();
+
+        let kinds = |report: &FsckReport| {
+            let mut kinds: Vec<_> = report
+                .issues
+                .iter()
+                .map(|issue| (issue.digest.clone(), issue.kind, issue.repair.clone()))
+                .collect();
+            kinds.sort_by(|a, b| a.0.cmp(&b.0));
+            kinds
+        };
+        let mut expected = vec![
+            (corrupt.clone(), FsckIssueKind::DigestMismatch),
+            (resized.clone(), FsckIssueKind::SizeMismatch),
+            (missing.clone(), FsckIssueKind::Missing),
+            (unregistered.clone(), FsckIssueKind::Unregistered),
+            (stray.clone(), FsckIssueKind::DigestMismatch),
+        ];
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Report only: nothing changes
+        let report = checker.run(FsckOptions::default()).await.unwrap();
+        assert_eq!(report.entries_checked, 4);
+        assert_eq!(report.blobs_checked, 5);
+        assert_eq!(report.unresolved(), 5);
+        let found: Vec<_> = kinds(&report).into_iter().map(|(d, k, _)| (d, k)).collect();
+        assert_eq!(found, expected);
+
+        let report = checker
+            .run(FsckOptions {
+                repair: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.unresolved(), 0);
+        let repairs: HashMap<_, _> = kinds(&report)
+            .into_iter()
+            .map(|(d, _, r)| (d, r.unwrap()))
+            .collect();
+        assert_eq!(repairs[&corrupt], FsckRepair::Removed);
+        assert_eq!(repairs[&resized], FsckRepair::SizeUpdated);
+        assert_eq!(repairs[&missing], FsckRepair::Removed);
+        assert_eq!(repairs[&unregistered], FsckRepair::Registered);
+        assert_eq!(repairs[&stray], FsckRepair::Deleted);
+
+        let entry = db
+            .get_cache_entry_by_digest(&unregistered, SHARED_NAMESPACE)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.entry_type, EntryType::Manifest);
+        assert_eq!(entry.content_type, crate::manifest::OCI_MANIFEST_MEDIA_TYPE);
+        assert_eq!(
+            db.get_manifest_references(&unregistered)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(!storage.exists(&stray).await.unwrap());
+        assert!(!storage.exists(&corrupt).await.unwrap());
+
+        let report = checker.run(FsckOptions::default()).await.unwrap();
+        assert!(report.issues.is_empty());
+        assert_eq!(report.entries_checked, 3);
+    }
+
+    #[tokio::test]
+    async fn test_checks_isolated_namespaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let (checker, db, _, cache) = checker(&dir).await;
+        let upstream_id = db
+            .register_upstream(NewUpstream {
+                name: "team-a".to_string(),
+                display_name: "Team A".to_string(),
+                url: "https://harbor.example.com".to_string(),
+                registry: "library".to_string(),
+                username: None,
+                password: None,
+                skip_tls_verify: false,
+                priority: 100,
+                enabled: true,
+                cache_isolation: CacheIsolation::Isolated,
+                is_default: false,
+            })
+            .await
+            .unwrap();
+        let isolated = cache.isolated(upstream_id, "team-a", 1024).await.unwrap();
+        let namespace_dir = dir.path().join("namespaces").join(upstream_id.to_string());
+
+        for (cache, data) in [(&cache, b"shared"), (&isolated, b"broken")] {
+            cache
+                .put(
+                    EntryType::Blob,
+                    None,
+                    None,
+                    &sha256(data),
+                    "application/octet-stream",
+                    Bytes::from_static(data),
+                    Some(upstream_id),
+                )
+                .await
+                .unwrap();
+        }
+        let corrupt = sha256(b"broken");
+        std::fs::write(blob_file(&namespace_dir, &corrupt), b"BROKEN").unwrap();
+        let unregistered = sha256(b"unregistered");
+        isolated
+            .storage()
+            .write(&unregistered, Bytes::from_static(b"unregistered"))
+            .await
+            .unwrap();
+
+        let report = checker.run(FsckOptions::default()).await.unwrap();
+        assert_eq!(report.entries_checked, 2);
+        assert_eq!(report.blobs_checked, 3);
+        let mut found: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.digest.clone(), issue.kind))
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut expected = vec![
+            (corrupt.clone(), FsckIssueKind::DigestMismatch),
+            (unregistered.clone(), FsckIssueKind::Unregistered),
+        ];
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(found, expected);
+
+        let report = checker
+            .run(FsckOptions {
+                repair: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.unresolved(), 0);
+
+        // Repairs stay within the isolated namespace
+        assert!(!isolated.exists(&corrupt).await.unwrap());
+        let entry = isolated.get_metadata(&unregistered).await.unwrap().unwrap();
+        assert_eq!(entry.upstream_id, Some(upstream_id));
+        assert!(
+            db.get_cache_entry_by_digest(&unregistered, SHARED_NAMESPACE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(cache.exists(&sha256(b"shared")).await.unwrap());
+    }
+}
@@ -6,14 +6,27 @@
 pub mod cache;
 pub mod config;
 pub mod error;
+pub mod fsck;
+pub mod manifest;
+pub mod range;
+pub mod reaper;
 pub mod registry;
+pub mod scrub;
+mod singleflight;
 pub mod upstream;
 
 pub use cache::{CacheConfig, CacheManager, EvictionPolicy, spawn_cleanup_task};
 pub use config::{
     MAX_PROJECTS_PER_UPSTREAM, UpstreamConfig, UpstreamConfigProvider, UpstreamProjectConfig,
-    UpstreamRouteConfig, validate_pattern, validate_project_name,
+    UpstreamRetryConfig, UpstreamRouteConfig, validate_pattern, validate_project_name,
 };
 pub use error::CoreError;
+pub use fsck::{ConsistencyChecker, FsckIssue, FsckIssueKind, FsckOptions, FsckRepair, FsckReport};
+pub use range::{ByteRange, ChunkRange, upload_range};
+pub use reaper::{ReapStats, UploadReaper, UploadReaperConfig, spawn_upload_reaper};
 pub use registry::RegistryService;
-pub use upstream::{UpstreamHealth, UpstreamInfo, UpstreamManager};
+pub use scrub::{ScrubConfig, Scrubber, spawn_scrubber};
+pub use upstream::{
+    CircuitState, CircuitTransition, HealthCheckConfig, RouteMatcher, UpstreamHealth, UpstreamInfo,
+    UpstreamManager, spawn_circuit_logger, spawn_health_prober,
+};
@@ -0,0 +1,342 @@
+//! Manifest parsing helpers
+//!
+//! Extracts the content a manifest points to so the cache can track which
+//! blobs and child manifests belong to a cached image.
+
+use std::collections::BTreeMap;
+
+use harbor_db::{EntryType, ManifestReferrer, NewManifestReferrer};
+use serde::{Deserialize, Serialize};
+
+/// OCI image index media type, used for referrers responses
+pub const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+
+/// OCI image manifest media type
+pub const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Docker schema1 manifest media type
+pub const DOCKER_SCHEMA1_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v1+json";
+
+/// A descriptor as found in OCI / Docker manifests
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    digest: String,
+    #[serde(default)]
+    media_type: Option<String>,
+}
+
+/// Docker schema1 layer reference
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FsLayer {
+    blob_sum: String,
+}
+
+/// The subset of manifest fields needed to find referenced content.
+/// Covers OCI image manifests and indexes, Docker schema2 manifests and
+/// manifest lists, and Docker schema1 manifests.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestDocument {
+    #[serde(default)]
+    schema_version: Option<u32>,
+    #[serde(default)]
+    media_type: Option<String>,
+    #[serde(default)]
+    artifact_type: Option<String>,
+    #[serde(default)]
+    subject: Option<Descriptor>,
+    #[serde(default)]
+    annotations: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    #[serde(default)]
+    fs_layers: Vec<FsLayer>,
+}
+
+/// Content referenced by a manifest (a blob or a child manifest)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestChild {
+    pub digest: String,
+    /// Manifest for index/list entries, blob for config and layers
+    pub entry_type: EntryType,
+}
+
+/// Parse a manifest and return the blobs and child manifests it references.
+///
+/// Unparseable manifests reference nothing. Duplicates are removed while
+/// preserving order.
+pub fn referenced_digests(data: &[u8]) -> Vec<ManifestChild> {
+    let document: ManifestDocument = match serde_json::from_slice(data) {
+        Ok(document) => document,
+        Err(_) => return Vec::new(),
+    };
+
+    let manifests = document
+        .manifests
+        .into_iter()
+        .map(|d| (d.digest, EntryType::Manifest));
+    let blobs = document
+        .config
+        .into_iter()
+        .chain(document.layers)
+        .map(|d| d.digest)
+        .chain(document.fs_layers.into_iter().map(|l| l.blob_sum))
+        .map(|digest| (digest, EntryType::Blob));
+
+    let mut references: Vec<ManifestChild> = Vec::new();
+    for (digest, entry_type) in manifests.chain(blobs) {
+        if !references.iter().any(|r| r.digest == digest) {
+            references.push(ManifestChild { digest, entry_type });
+        }
+    }
+    references
+}
+
+/// Recognize a manifest document and return its media type.
+///
+/// Returns None for content that is not a manifest (layers, image
+/// configs). Documents without a `mediaType` field are typed by their
+/// schema version and shape.
+pub fn manifest_media_type(data: &[u8]) -> Option<String> {
+    let document: ManifestDocument = serde_json::from_slice(data).ok()?;
+    let schema_version = document.schema_version?;
+
+    if let Some(media_type) = document.media_type {
+        return Some(media_type);
+    }
+    if schema_version == 1 {
+        return Some(DOCKER_SCHEMA1_MEDIA_TYPE.to_string());
+    }
+    if !document.manifests.is_empty() {
+        return Some(OCI_INDEX_MEDIA_TYPE.to_string());
+    }
+    document.config.map(|_| OCI_MANIFEST_MEDIA_TYPE.to_string())
+}
+
+/// Descriptor of a manifest that refers to a subject, as listed in a
+/// referrers response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferrerDescriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+impl ReferrerDescriptor {
+    /// Convert to a record for the local referrers index
+    pub fn to_record(&self) -> NewManifestReferrer {
+        NewManifestReferrer {
+            digest: self.digest.clone(),
+            media_type: self.media_type.clone(),
+            artifact_type: self.artifact_type.clone(),
+            size: self.size,
+            annotations: self
+                .annotations
+                .as_ref()
+                .and_then(|a| serde_json::to_string(a).ok()),
+        }
+    }
+}
+
+impl From<ManifestReferrer> for ReferrerDescriptor {
+    fn from(referrer: ManifestReferrer) -> Self {
+        Self {
+            media_type: referrer.media_type,
+            digest: referrer.digest,
+            size: referrer.size,
+            artifact_type: referrer.artifact_type,
+            annotations: referrer
+                .annotations
+                .and_then(|a| serde_json::from_str(&a).ok()),
+        }
+    }
+}
+
+/// Image index body of a referrers response
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReferrersIndex {
+    schema_version: u32,
+    media_type: String,
+    #[serde(default)]
+    manifests: Vec<ReferrerDescriptor>,
+}
+
+/// Parse a manifest with a `subject` field and return the subject digest
+/// together with the descriptor the referrers API lists for it.
+///
+/// The artifact type falls back to the config media type, as specified by
+/// OCI 1.1.
+pub fn subject_of(
+    data: &[u8],
+    digest: &str,
+    content_type: &str,
+) -> Option<(String, ReferrerDescriptor)> {
+    let document: ManifestDocument = serde_json::from_slice(data).ok()?;
+    let subject = document.subject?;
+
+    let artifact_type = document
+        .artifact_type
+        .or_else(|| document.config.and_then(|c| c.media_type));
+    let descriptor = ReferrerDescriptor {
+        media_type: document
+            .media_type
+            .unwrap_or_else(|| content_type.to_string()),
+        digest: digest.to_string(),
+        size: data.len() as i64,
+        artifact_type,
+        annotations: document.annotations,
+    };
+    Some((subject.digest, descriptor))
+}
+
+/// Parse the descriptors of a referrers response (empty if unparseable)
+pub fn parse_referrers(data: &[u8]) -> Vec<ReferrerDescriptor> {
+    serde_json::from_slice::<ReferrersIndex>(data)
+        .map(|index| index.manifests)
+        .unwrap_or_default()
+}
+
+/// Build the image index returned by the referrers API
+pub fn referrers_index(manifests: Vec<ReferrerDescriptor>) -> Vec<u8> {
+    let index = ReferrersIndex {
+        schema_version: 2,
+        media_type: OCI_INDEX_MEDIA_TYPE.to_string(),
+        manifests,
+    };
+    serde_json::to_vec(&index).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_manifest_references() {
+        let manifest = br#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {"mediaType": "application/vnd.oci.image.config.v1+json", "digest": "sha256:c0", "size": 1},
+            "layers": [
+                {"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": "sha256:l1", "size": 2},
+                {"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": "sha256:l2", "size": 3}
+            ]
+        }"#;
+
+        let refs = referenced_digests(manifest);
+        let digests: Vec<_> = refs.iter().map(|r| r.digest.as_str()).collect();
+        assert_eq!(digests, vec!["sha256:c0", "sha256:l1", "sha256:l2"]);
+        assert!(refs.iter().all(|r| r.entry_type == EntryType::Blob));
+    }
+
+    #[test]
+    fn test_index_references_child_manifests() {
+        let index = br#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.index.v1+json",
+            "manifests": [
+                {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:amd64", "size": 1},
+                {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:arm64", "size": 1}
+            ]
+        }"#;
+
+        let refs = referenced_digests(index);
+        assert_eq!(refs.len(), 2);
+        assert!(refs.iter().all(|r| r.entry_type == EntryType::Manifest));
+    }
+
+    #[test]
+    fn test_schema1_manifest_deduplicates_layers() {
+        let manifest = br#"{
+            "schemaVersion": 1,
+            "fsLayers": [{"blobSum": "sha256:a"}, {"blobSum": "sha256:a"}, {"blobSum": "sha256:b"}]
+        }"#;
+
+        let refs = referenced_digests(manifest);
+        let digests: Vec<_> = refs.iter().map(|r| r.digest.as_str()).collect();
+        assert_eq!(digests, vec!["sha256:a", "sha256:b"]);
+    }
+
+    #[test]
+    fn test_subject_of_signature_manifest() {
+        let manifest = br#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {"mediaType": "application/vnd.dev.cosign.artifact.sig.v1+json", "digest": "sha256:c0", "size": 2},
+            "layers": [],
+            "subject": {"mediaType": "application/vnd.oci.image.manifest.v1+json", "digest": "sha256:img", "size": 10},
+            "annotations": {"org.opencontainers.image.created": "2024-01-01T00:00:00Z"}
+        }"#;
+
+        let (subject, descriptor) =
+            subject_of(manifest, "sha256:sig", "application/octet-stream").unwrap();
+        assert_eq!(subject, "sha256:img");
+        assert_eq!(descriptor.digest, "sha256:sig");
+        assert_eq!(
+            descriptor.media_type,
+            "application/vnd.oci.image.manifest.v1+json"
+        );
+        assert_eq!(
+            descriptor.artifact_type.as_deref(),
+            Some("application/vnd.dev.cosign.artifact.sig.v1+json")
+        );
+        assert_eq!(descriptor.size, manifest.len() as i64);
+
+        let index = referrers_index(vec![descriptor.clone()]);
+        assert_eq!(parse_referrers(&index), vec![descriptor]);
+    }
+
+    #[test]
+    fn test_manifest_without_subject() {
+        let manifest = br#"{"schemaVersion": 2, "layers": []}"#;
+        assert!(subject_of(manifest, "sha256:x", OCI_INDEX_MEDIA_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_manifest_media_type() {
+        let typed = br#"{"schemaVersion": 2, "mediaType": "application/vnd.docker.distribution.manifest.v2+json"}"#;
+        assert_eq!(
+            manifest_media_type(typed).as_deref(),
+            Some("application/vnd.docker.distribution.manifest.v2+json")
+        );
+
+        let untyped_index = br#"{"schemaVersion": 2, "manifests": [{"digest": "sha256:a"}]}"#;
+        assert_eq!(
+            manifest_media_type(untyped_index).as_deref(),
+            Some(OCI_INDEX_MEDIA_TYPE)
+        );
+
+        let untyped_manifest =
+            br#"{"schemaVersion": 2, "config": {"digest": "sha256:c"}, "layers": []}"#;
+        assert_eq!(
+            manifest_media_type(untyped_manifest).as_deref(),
+            Some(OCI_MANIFEST_MEDIA_TYPE)
+        );
+
+        let schema1 = br#"{"schemaVersion": 1, "fsLayers": []}"#;
+        assert_eq!(
+            manifest_media_type(schema1).as_deref(),
+            Some(DOCKER_SCHEMA1_MEDIA_TYPE)
+        );
+
+        // Image configs and layers are not manifests
+        assert!(manifest_media_type(br#"{"architecture": "amd64", "os": "linux"}"#).is_none());
+        assert!(manifest_media_type(b"\x1f\x8b binary").is_none());
+    }
+
+    #[test]
+    fn test_invalid_manifest_has_no_references() {
+        assert!(referenced_digests(b"not json").is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//! HTTP byte range requests
+//!
+//! Single-range `Range: bytes=...` requests (RFC 7233) on blobs, as used by
+//! clients resuming interrupted layer downloads and lazy-pull snapshotters,
+//! and the `Content-Range` / `Range` offsets of chunked blob uploads.
+
+use harbor_proxy::ContentRange;
+
+/// A single byte range requested by a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `bytes=start-` or `bytes=start-end` (inclusive)
+    FromTo { start: u64, end: Option<u64> },
+    /// `bytes=-n`: the last `n` bytes
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parse a `Range` header value.
+    ///
+    /// Returns `None` for malformed values and for multiple ranges, which
+    /// are not supported; such headers are ignored and the full blob is
+    /// served, as RFC 7233 allows.
+    pub fn parse(value: &str) -> Option<Self> {
+        let spec = value.trim().strip_prefix("bytes=")?.trim();
+        if spec.contains(',') {
+            return None;
+        }
+
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+
+        if start.is_empty() {
+            return end.parse().ok().map(Self::Suffix);
+        }
+
+        let start = start.parse().ok()?;
+        let end = match end {
+            "" => None,
+            end => Some(end.parse().ok()?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+        Some(Self::FromTo { start, end })
+    }
+
+    /// Resolve against the blob size; `None` if the range is not satisfiable
+    pub fn resolve(&self, size: u64) -> Option<ContentRange> {
+        let (start, end) = match *self {
+            Self::FromTo { start, end } => {
+                if start >= size {
+                    return None;
+                }
+                (start, end.map_or(size - 1, |end| end.min(size - 1)))
+            }
+            Self::Suffix(n) => {
+                if n == 0 || size == 0 {
+                    return None;
+                }
+                (size.saturating_sub(n), size - 1)
+            }
+        };
+
+        Some(ContentRange {
+            start,
+            end,
+            total: Some(size),
+        })
+    }
+
+    /// Format as a `Range` header value
+    pub fn header_value(&self) -> String {
+        match self {
+            Self::FromTo { start, end: None } => format!("bytes={}-", start),
+            Self::FromTo {
+                start,
+                end: Some(end),
+            } => format!("bytes={}-{}", start, end),
+            Self::Suffix(n) => format!("bytes=-{}", n),
+        }
+    }
+}
+
+/// Byte range of a chunk sent to an upload session (`Content-Range: start-end`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRange {
+    pub start: u64,
+    /// Inclusive end offset
+    pub end: u64,
+}
+
+impl ChunkRange {
+    /// Parse a chunk `Content-Range` header value.
+    ///
+    /// The distribution spec uses a bare `start-end`; the RFC 7233 forms
+    /// `bytes start-end/total` and `bytes=start-end` are accepted as well.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let spec = value
+            .strip_prefix("bytes=")
+            .or_else(|| value.strip_prefix("bytes "))
+            .unwrap_or(value);
+        let spec = spec.split_once('/').map_or(spec, |(range, _)| range);
+
+        let (start, end) = spec.split_once('-')?;
+        let start = start.trim().parse().ok()?;
+        let end = end.trim().parse().ok()?;
+        if end < start {
+            return None;
+        }
+        Some(Self { start, end })
+    }
+
+    /// Number of bytes in the chunk
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// `Range` header value reporting the bytes an upload session has received.
+///
+/// Ranges are inclusive, so an empty session reports `0-0` like the
+/// reference registry does.
+pub fn upload_range(received: u64) -> String {
+    format!("0-{}", received.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(
+            ByteRange::parse("bytes=0-99"),
+            Some(ByteRange::FromTo {
+                start: 0,
+                end: Some(99)
+            })
+        );
+        assert_eq!(
+            ByteRange::parse("bytes=100-"),
+            Some(ByteRange::FromTo {
+                start: 100,
+                end: None
+            })
+        );
+        assert_eq!(ByteRange::parse("bytes=-50"), Some(ByteRange::Suffix(50)));
+
+        assert_eq!(ByteRange::parse("bytes=0-1,5-9"), None);
+        assert_eq!(ByteRange::parse("bytes=9-5"), None);
+        assert_eq!(ByteRange::parse("items=0-1"), None);
+        assert_eq!(ByteRange::parse("bytes=abc"), None);
+    }
+
+    #[test]
+    fn test_resolve_ranges() {
+        let range = ByteRange::parse("bytes=10-2000")
+            .unwrap()
+            .resolve(100)
+            .unwrap();
+        assert_eq!((range.start, range.end, range.total), (10, 99, Some(100)));
+
+        let range = ByteRange::parse("bytes=-30").unwrap().resolve(100).unwrap();
+        assert_eq!((range.start, range.end), (70, 99));
+
+        let range = ByteRange::parse("bytes=-300")
+            .unwrap()
+            .resolve(100)
+            .unwrap();
+        assert_eq!((range.start, range.end), (0, 99));
+
+        assert!(
+            ByteRange::parse("bytes=100-")
+                .unwrap()
+                .resolve(100)
+                .is_none()
+        );
+        assert!(ByteRange::parse("bytes=-0").unwrap().resolve(100).is_none());
+    }
+
+    #[test]
+    fn test_parse_chunk_ranges() {
+        let range = ChunkRange::parse("0-1023").unwrap();
+        assert_eq!((range.start, range.end, range.length()), (0, 1023, 1024));
+        assert_eq!(
+            ChunkRange::parse("bytes 1024-2047/4096"),
+            Some(ChunkRange {
+                start: 1024,
+                end: 2047
+            })
+        );
+        assert_eq!(ChunkRange::parse("bytes=5-5").map(|r| r.length()), Some(1));
+
+        assert_eq!(ChunkRange::parse("10-5"), None);
+        assert_eq!(ChunkRange::parse("10-"), None);
+        assert_eq!(ChunkRange::parse("abc"), None);
+    }
+
+    #[test]
+    fn test_upload_range() {
+        assert_eq!(upload_range(0), "0-0");
+        assert_eq!(upload_range(1), "0-0");
+        assert_eq!(upload_range(1024), "0-1023");
+    }
+}
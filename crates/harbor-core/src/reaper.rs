@@ -0,0 +1,260 @@
+//! Upload session reaper
+//!
+//! Upload sessions are only removed when a push completes or is cancelled,
+//! so aborted pushes would leak their temporary data forever. The reaper
+//! periodically expires sessions that have been idle for too long and
+//! deletes temporary upload data that no session refers to.
+
+use chrono::Utc;
+use harbor_db::Database;
+use harbor_storage::StorageBackend;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::error::CoreError;
+use crate::registry::upload_state;
+
+/// Upload reaper configuration
+#[derive(Debug, Clone)]
+pub struct UploadReaperConfig {
+    /// Expire sessions that have not received a chunk for this long
+    pub session_timeout_secs: u64,
+    /// How often the reaper runs
+    pub interval_secs: u64,
+}
+
+impl Default for UploadReaperConfig {
+    fn default() -> Self {
+        Self {
+            session_timeout_secs: 24 * 3600,
+            interval_secs: 3600,
+        }
+    }
+}
+
+/// What a reaper run removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReapStats {
+    /// Idle sessions expired together with their data
+    pub expired_sessions: u64,
+    /// Temporary uploads without a session that were deleted
+    pub orphaned_uploads: u64,
+    /// Bytes of temporary data reclaimed
+    pub bytes_reclaimed: u64,
+}
+
+/// Expires abandoned upload sessions and their temporary data
+pub struct UploadReaper {
+    db: Database,
+    storage: Arc<dyn StorageBackend>,
+    config: UploadReaperConfig,
+}
+
+impl UploadReaper {
+    /// Create a new upload reaper
+    pub fn new(db: Database, storage: Arc<dyn StorageBackend>, config: UploadReaperConfig) -> Self {
+        Self {
+            db,
+            storage,
+            config,
+        }
+    }
+
+    /// Expire idle sessions and delete orphaned upload data once.
+    ///
+    /// Orphans must be as old as the session timeout, so data written just
+    /// before its session row is created is never mistaken for one.
+    pub async fn run_once(&self) -> Result<ReapStats, CoreError> {
+        let timeout = chrono::Duration::seconds(self.config.session_timeout_secs as i64);
+        let cutoff = Utc::now() - timeout;
+        let mut stats = ReapStats::default();
+
+        // List before expiring so expired sessions are not seen as orphans
+        let uploads = self.storage.list_uploads().await?;
+
+        let mut expired = HashSet::new();
+        for session in self.db.list_idle_upload_sessions(cutoff).await? {
+            let state = upload_state(&self.db, &session).await?;
+            if let Err(e) = self
+                .storage
+                .cancel_chunked_upload(&session.id, &state)
+                .await
+            {
+                warn!("Failed to expire upload session {}: {}", session.id, e);
+                continue;
+            }
+            self.db.delete_upload_session(&session.id).await?;
+
+            debug!(
+                "Expired upload session {} for {} ({} bytes)",
+                session.id, session.repository, session.bytes_received
+            );
+            stats.expired_sessions += 1;
+            stats.bytes_reclaimed += session.bytes_received.max(0) as u64;
+            expired.insert(session.id);
+        }
+
+        let cutoff = SystemTime::from(cutoff);
+        for upload in uploads {
+            if expired.contains(&upload.session_id)
+                || upload.last_modified >= cutoff
+                || Uuid::parse_str(&upload.session_id).is_err()
+                || self
+                    .db
+                    .get_upload_session(&upload.session_id)
+                    .await?
+                    .is_some()
+            {
+                continue;
+            }
+
+            if let Err(e) = self
+                .storage
+                .cancel_chunked_upload(&upload.session_id, &Default::default())
+                .await
+            {
+                warn!(
+                    "Failed to delete orphaned upload {}: {}",
+                    upload.session_id, e
+                );
+                continue;
+            }
+
+            debug!(
+                "Deleted orphaned upload {} ({} bytes)",
+                upload.session_id, upload.size
+            );
+            stats.orphaned_uploads += 1;
+            stats.bytes_reclaimed += upload.size;
+        }
+
+        metrics::counter!("harbor_cache_upload_sessions_expired_total")
+            .increment(stats.expired_sessions);
+        metrics::counter!("harbor_cache_upload_orphans_deleted_total")
+            .increment(stats.orphaned_uploads);
+        metrics::counter!("harbor_cache_upload_reclaimed_bytes_total")
+            .increment(stats.bytes_reclaimed);
+
+        Ok(stats)
+    }
+}
+
+/// Spawn a background task that runs the upload reaper periodically
+pub fn spawn_upload_reaper(reaper: Arc<UploadReaper>) -> tokio::task::JoinHandle<()> {
+    use tokio::time::{Duration, interval};
+
+    let interval_secs = reaper.config.interval_secs.max(1);
+    info!(
+        "Starting upload reaper (interval: {}s, session timeout: {}s)",
+        interval_secs, reaper.config.session_timeout_secs
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(interval_secs));
+
+        loop {
+            ticker.tick().await;
+
+            match reaper.run_once().await {
+                Ok(stats) => {
+                    if stats != ReapStats::default() {
+                        info!(
+                            "Upload reaper: {} idle sessions expired, {} orphaned uploads deleted, {} bytes reclaimed",
+                            stats.expired_sessions, stats.orphaned_uploads, stats.bytes_reclaimed
+                        );
+                    }
+                }
+                Err(e) => {
+                    warn!("Error during upload reaping: {}", e);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use harbor_db::NewUploadSession;
+    use harbor_storage::LocalStorage;
+
+    async fn start_session(db: &Database, storage: &Arc<dyn StorageBackend>) -> String {
+        let session_id = Uuid::new_v4().to_string();
+        let temp_path = storage.init_chunked_upload(&session_id).await.unwrap();
+        db.create_upload_session(NewUploadSession {
+            id: session_id.clone(),
+            repository: "library/app".to_string(),
+            temp_path,
+        })
+        .await
+        .unwrap();
+        session_id
+    }
+
+    #[tokio::test]
+    async fn test_reaps_idle_sessions_and_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+
+        let idle = start_session(&db, &storage).await;
+        let mut state = Default::default();
+        storage
+            .append_chunk(&idle, &mut state, Bytes::from_static(b"abandoned"))
+            .await
+            .unwrap();
+        db.update_upload_session(&idle, 9).await.unwrap();
+        sqlx::query("UPDATE upload_sessions SET last_chunk_at = ? WHERE id = ?")
+            .bind("2000-01-01T00:00:00+00:00")
+            .bind(&idle)
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        let active = start_session(&db, &storage).await;
+
+        // An orphan is only deleted once it is as old as the timeout
+        let orphan = Uuid::new_v4().to_string();
+        let fresh_orphan = Uuid::new_v4().to_string();
+        for session_id in [&orphan, &fresh_orphan] {
+            storage.init_chunked_upload(session_id).await.unwrap();
+        }
+        std::fs::File::options()
+            .write(true)
+            .open(dir.path().join("uploads").join(&orphan))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        let reaper = UploadReaper::new(db.clone(), storage.clone(), UploadReaperConfig::default());
+        let stats = reaper.run_once().await.unwrap();
+
+        assert_eq!(
+            stats,
+            ReapStats {
+                expired_sessions: 1,
+                orphaned_uploads: 1,
+                bytes_reclaimed: 9,
+            }
+        );
+        assert!(db.get_upload_session(&idle).await.unwrap().is_none());
+        assert!(db.get_upload_session(&active).await.unwrap().is_some());
+
+        let mut remaining: Vec<_> = storage
+            .list_uploads()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|upload| upload.session_id)
+            .collect();
+        remaining.sort();
+        let mut expected = vec![active, fresh_orphan];
+        expected.sort();
+        assert_eq!(remaining, expected);
+    }
+}
@@ -1,16 +1,26 @@
 //! Registry service for OCI Distribution API operations
 
 use bytes::Bytes;
-use harbor_db::{Database, EntryType, NewUploadSession, UploadSession};
-use harbor_proxy::HarborClient;
-use harbor_storage::StorageBackend;
+use chrono::Utc;
+use harbor_db::{
+    CacheEntry, CacheIsolation, Database, EntryType, ManifestTag, NewUploadSession, NewUpstream,
+    SHARED_NAMESPACE, UploadSession, UploadSessionPart,
+};
+use harbor_proxy::{ContentRange, HarborClient, RepositoryList, TagList};
+use harbor_storage::{ChunkedUploadState, StorageBackend, UploadedPart};
+use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::cache::CacheManager;
+use crate::config::UpstreamConfig;
 use crate::error::CoreError;
-use crate::upstream::UpstreamManager;
+use crate::manifest::{ReferrerDescriptor, parse_referrers, referrers_index};
+use crate::range::{ByteRange, ChunkRange};
+use crate::singleflight::{BlobFlights, BlobLead, SingleFlight};
+use crate::upstream::{MatchReason, UpstreamInfo, UpstreamManager};
 
 // ==================== Input Validation ====================
 
@@ -61,11 +71,16 @@ fn validate_tag_reference(tag: &str) -> Result<(), CoreError> {
     Ok(())
 }
 
+/// Whether a manifest reference is a digest rather than a tag
+fn is_digest_reference(reference: &str) -> bool {
+    reference.starts_with("sha256:") || reference.starts_with("sha512:")
+}
+
 /// Validate a manifest reference (either a tag or a digest).
 /// Digests are validated separately; this validates tags.
 fn validate_reference(reference: &str) -> Result<(), CoreError> {
     // If it's a digest, validate as digest
-    if reference.starts_with("sha256:") || reference.starts_with("sha512:") {
+    if is_digest_reference(reference) {
         harbor_storage::backend::validate_digest(reference)?;
         return Ok(());
     }
@@ -74,11 +89,199 @@ fn validate_reference(reference: &str) -> Result<(), CoreError> {
     validate_tag_reference(reference)
 }
 
+/// Paginate a lexically sorted tag list: skip tags up to and including
+/// `last`, then return at most `n` tags
+fn paginate_tags(tags: Vec<String>, n: Option<usize>, last: Option<&str>) -> TagList {
+    let mut remaining = tags
+        .into_iter()
+        .filter(|tag| last.is_none_or(|last| tag.as_str() > last))
+        .peekable();
+
+    let mut page = Vec::new();
+    while let Some(tag) = remaining.next_if(|_| n.is_none_or(|n| page.len() < n)) {
+        page.push(tag);
+    }
+
+    let next_last = if remaining.peek().is_some() {
+        page.last().cloned()
+    } else {
+        None
+    };
+
+    TagList {
+        tags: page,
+        next_last,
+    }
+}
+
+/// Merge sorted repository pages into one catalog page of at most `n`
+/// repositories.
+///
+/// `local` is complete past `last` up to its own length, `upstream` holds
+/// one page per upstream. Upstreams with more pages only cover names up to
+/// their last entry, so the merged page stops at the smallest such name to
+/// avoid skipping repositories on the next page.
+fn merge_catalog_pages(
+    local: Vec<String>,
+    local_complete: bool,
+    upstream: Vec<RepositoryList>,
+    n: Option<usize>,
+) -> RepositoryList {
+    let mut frontier: Option<String> = if local_complete {
+        None
+    } else {
+        local.last().cloned()
+    };
+    for page in &upstream {
+        if let Some(last) = &page.next_last
+            && frontier.as_ref().is_none_or(|f| last < f)
+        {
+            frontier = Some(last.clone());
+        }
+    }
+
+    let mut all: Vec<String> = local
+        .into_iter()
+        .chain(upstream.into_iter().flat_map(|page| page.repositories))
+        .collect();
+    all.sort();
+    all.dedup();
+
+    let more_beyond = frontier.is_some();
+    if let Some(frontier) = &frontier {
+        all.retain(|name| name <= frontier);
+    }
+
+    let page = paginate_tags(all, n, None);
+    let next_last = match page.next_last {
+        Some(last) => Some(last),
+        None if more_beyond => page.tags.last().cloned(),
+        None => None,
+    };
+
+    RepositoryList {
+        repositories: page.tags,
+        next_last,
+    }
+}
+
+/// Load the backend state of an upload session persisted with it
+pub(crate) async fn upload_state(
+    db: &Database,
+    session: &UploadSession,
+) -> Result<ChunkedUploadState, CoreError> {
+    let parts = if session.multipart_upload_id.is_some() {
+        db.get_upload_session_parts(&session.id)
+            .await?
+            .into_iter()
+            .map(|part| UploadedPart {
+                etag: part.etag,
+                size: part.size as u64,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(ChunkedUploadState {
+        multipart_upload_id: session.multipart_upload_id.clone(),
+        parts,
+    })
+}
+
+/// Convert an upstream stream into a storage stream for caching
+fn storage_stream(stream: harbor_proxy::client::ByteStream) -> harbor_storage::backend::ByteStream {
+    use futures::StreamExt;
+    Box::pin(stream.map(|result| {
+        result.map_err(|e| harbor_storage::StorageError::Io(std::io::Error::other(e.to_string())))
+    }))
+}
+
+/// Map the error of a delete forwarded upstream. Registries that do not
+/// allow deletes answer 405, which is passed on to the client.
+fn delete_error(e: harbor_proxy::ProxyError) -> CoreError {
+    match e {
+        harbor_proxy::ProxyError::NotFound(what) => CoreError::NotFound(what),
+        harbor_proxy::ProxyError::UpstreamError { status: 405, .. } => {
+            CoreError::Unsupported("The upstream registry does not allow deletes".to_string())
+        }
+        e => CoreError::Proxy(e),
+    }
+}
+
+/// Wait for a background cache write, logging its outcome
+async fn await_cache_write(
+    digest: String,
+    handle: tokio::task::JoinHandle<Result<CacheEntry, CoreError>>,
+) -> Result<(), String> {
+    match handle.await {
+        Ok(Ok(_entry)) => {
+            debug!("Background cache write succeeded for {}", digest);
+            Ok(())
+        }
+        Ok(Err(e)) => {
+            warn!("Background cache write failed for {}: {}", digest, e);
+            Err(e.to_string())
+        }
+        Err(e) => {
+            warn!("Background cache task panicked for {}: {:?}", digest, e);
+            Err(format!("Cache write for {} panicked", digest))
+        }
+    }
+}
+
+/// A manifest with its content type and digest
+type Manifest = (Bytes, String, String);
+
+/// An upstream selected to serve a repository
+struct SelectedUpstream {
+    /// Upstream name (None in single upstream mode)
+    name: Option<String>,
+    client: Arc<HarborClient>,
+    /// Tag freshness TTL in seconds (None = always revalidate)
+    tag_ttl_secs: Option<u64>,
+    /// Upstream configuration (None in single upstream mode)
+    config: Option<UpstreamConfig>,
+    /// Whether the upstream was selected as a mirror of the routed upstream
+    mirror: bool,
+}
+
+impl SelectedUpstream {
+    fn from_info(info: UpstreamInfo) -> Self {
+        let tag_ttl_secs = info.tag_ttl_secs();
+        Self {
+            name: Some(info.config.name.clone()),
+            client: info.client,
+            tag_ttl_secs,
+            config: Some(info.config),
+            mirror: matches!(info.match_reason, MatchReason::Mirror(_)),
+        }
+    }
+
+    /// Name for log messages
+    fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("upstream")
+    }
+}
+
+/// The routed upstream of a failover group, which owns the group's cache
+fn routed(upstreams: &[SelectedUpstream]) -> Option<&SelectedUpstream> {
+    upstreams.iter().find(|upstream| !upstream.mirror)
+}
+
+/// Cache holding the content of a repository
+struct CacheTarget {
+    cache: Arc<CacheManager>,
+    /// Upstream new entries are attributed to
+    upstream_id: Option<i64>,
+}
+
 /// Registry service handling OCI Distribution API operations
 ///
 /// Supports two modes:
-/// - Single upstream mode: Uses a single HarborClient (legacy/simple mode)
 /// - Multi upstream mode: Uses UpstreamManager for route-based upstream selection
+///   and health tracking (production path)
+/// - Single upstream mode: Uses a single HarborClient (explicit legacy fallback)
 pub struct RegistryService {
     cache: Arc<CacheManager>,
     /// Single upstream client (legacy mode)
@@ -87,10 +290,16 @@ pub struct RegistryService {
     upstream_manager: Option<Arc<UpstreamManager>>,
     db: Database,
     storage: Arc<dyn StorageBackend>,
+    /// Manifest fetches in progress, by repository and reference
+    manifest_flights: SingleFlight<Manifest>,
+    /// Blob downloads in progress, by cache namespace and digest
+    blob_flights: Arc<BlobFlights>,
+    /// Database IDs of configured upstreams, by name
+    upstream_ids: RwLock<HashMap<String, i64>>,
 }
 
 impl RegistryService {
-    /// Create a new registry service with a single upstream (legacy mode)
+    /// Create a new registry service with a single upstream (legacy fallback mode)
     pub fn new(
         cache: Arc<CacheManager>,
         upstream: Arc<HarborClient>,
@@ -103,6 +312,9 @@ impl RegistryService {
             upstream_manager: None,
             db,
             storage,
+            manifest_flights: SingleFlight::new("manifest"),
+            blob_flights: Arc::default(),
+            upstream_ids: RwLock::default(),
         }
     }
 
@@ -119,11 +331,14 @@ impl RegistryService {
             upstream_manager: Some(upstream_manager),
             db,
             storage,
+            manifest_flights: SingleFlight::new("manifest"),
+            blob_flights: Arc::default(),
+            upstream_ids: RwLock::default(),
         }
     }
 
     /// Get the upstream client for a given repository
-    fn get_upstream(&self, repository: &str) -> Option<Arc<HarborClient>> {
+    fn get_upstream(&self, repository: &str) -> Option<SelectedUpstream> {
         // If we have an upstream manager, use it for routing
         if let Some(ref manager) = self.upstream_manager {
             if let Some(info) = manager.find_upstream(repository) {
@@ -131,25 +346,179 @@ impl RegistryService {
                     "Routed {} to upstream {} (reason: {:?})",
                     repository, info.config.name, info.match_reason
                 );
-                return Some(info.client);
+                return Some(SelectedUpstream::from_info(info));
             }
             warn!("No upstream found for repository: {}", repository);
             return None;
         }
 
         // Fall back to single upstream
-        self.single_upstream.clone()
+        self.single_upstream.clone().map(|client| SelectedUpstream {
+            name: None,
+            client,
+            tag_ttl_secs: None,
+            config: None,
+            mirror: false,
+        })
     }
 
-    /// Get the upstream name for cache isolation (if applicable)
-    #[allow(dead_code)]
-    fn get_upstream_name_for_cache(&self, repository: &str) -> Option<String> {
-        if let Some(ref manager) = self.upstream_manager
-            && let Some(info) = manager.find_upstream(repository)
-        {
-            return manager.get_cache_upstream_name(&info.config.name);
+    /// Get the failover group for a given repository: the routed upstream
+    /// followed by its mirrors. Empty when no upstream is available.
+    fn get_upstreams(&self, repository: &str) -> Vec<SelectedUpstream> {
+        let Some(ref manager) = self.upstream_manager else {
+            return self.get_upstream(repository).into_iter().collect();
+        };
+
+        let upstreams: Vec<_> = manager
+            .find_upstreams(repository)
+            .into_iter()
+            .map(SelectedUpstream::from_info)
+            .collect();
+        match upstreams.first() {
+            Some(primary) => debug!(
+                "Routed {} to upstream {} ({} mirrors)",
+                repository,
+                primary.display_name(),
+                upstreams.len() - 1
+            ),
+            None => warn!("No upstream found for repository: {}", repository),
         }
-        None
+        upstreams
+    }
+
+    /// Send a request to each member of a failover group in turn until one
+    /// answers. Only transient errors (connection failures, timeouts, 5xx)
+    /// move on to the next member; definitive answers such as 404 are
+    /// returned as is.
+    async fn with_failover<'a, T, F, Fut>(
+        &self,
+        upstreams: &'a [SelectedUpstream],
+        mut request: F,
+    ) -> Result<T, harbor_proxy::ProxyError>
+    where
+        F: FnMut(&'a SelectedUpstream) -> Fut,
+        Fut: std::future::Future<Output = Result<T, harbor_proxy::ProxyError>>,
+    {
+        let mut members = upstreams.iter().peekable();
+        while let Some(upstream) = members.next() {
+            let result = request(upstream).await;
+            self.record_upstream_result(upstream, &result);
+
+            match (result, members.peek()) {
+                (Err(e), Some(next)) if e.is_transient() => {
+                    warn!(
+                        "Upstream {} failed ({}), failing over to {}",
+                        upstream.display_name(),
+                        e,
+                        next.display_name()
+                    );
+                    metrics::counter!("harbor_cache_upstream_failovers_total").increment(1);
+                }
+                (result, _) => return result,
+            }
+        }
+        Err(harbor_proxy::ProxyError::NotFound(
+            "No upstream configured".to_string(),
+        ))
+    }
+
+    /// Record the outcome of an upstream call in the upstream manager's health state
+    fn record_upstream_result<T>(
+        &self,
+        upstream: &SelectedUpstream,
+        result: &Result<T, harbor_proxy::ProxyError>,
+    ) {
+        let (Some(manager), Some(name)) = (&self.upstream_manager, &upstream.name) else {
+            return;
+        };
+
+        match result {
+            Ok(_) => manager.mark_healthy(name),
+            Err(e) if e.is_transient() => manager.mark_unhealthy(name, &e.to_string()),
+            Err(_) => {}
+        }
+    }
+
+    /// Get the database ID of a configured upstream, registering it on first use
+    async fn upstream_id(&self, config: &UpstreamConfig) -> Result<i64, CoreError> {
+        if let Some(id) = self.upstream_ids.read().get(&config.name) {
+            return Ok(*id);
+        }
+
+        // Credentials stay in the configuration
+        let id = self
+            .db
+            .register_upstream(NewUpstream {
+                name: config.name.clone(),
+                display_name: config
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| config.name.clone()),
+                url: config.url.clone(),
+                registry: config.registry.clone(),
+                username: None,
+                password: None,
+                skip_tls_verify: config.skip_tls_verify,
+                priority: config.priority,
+                enabled: config.enabled,
+                cache_isolation: config
+                    .cache_isolation
+                    .parse()
+                    .unwrap_or(CacheIsolation::Shared),
+                is_default: config.is_default,
+            })
+            .await?;
+        self.upstream_ids.write().insert(config.name.clone(), id);
+        Ok(id)
+    }
+
+    /// Get the cache for content served by the routed upstream of a
+    /// repository: the upstream's isolated cache, or the shared cache.
+    /// Content fetched from a mirror is cached for the routed upstream.
+    async fn cache_target(
+        &self,
+        upstream: Option<&SelectedUpstream>,
+    ) -> Result<CacheTarget, CoreError> {
+        match upstream.and_then(|upstream| upstream.config.as_ref()) {
+            Some(config) => self.upstream_cache(config).await,
+            None => Ok(CacheTarget {
+                cache: self.cache.clone(),
+                upstream_id: None,
+            }),
+        }
+    }
+
+    /// Get the cache for content served by a configured upstream
+    async fn upstream_cache(&self, config: &UpstreamConfig) -> Result<CacheTarget, CoreError> {
+        let upstream_id = self.upstream_id(config).await?;
+        // Validation requires a quota for isolated upstreams; without one
+        // the shared cache is used so the global limit still holds
+        let cache = match config.cache_max_size {
+            Some(max_size) if config.uses_isolated_cache() => {
+                self.cache
+                    .isolated(upstream_id, &config.name, max_size)
+                    .await?
+            }
+            _ => self.cache.clone(),
+        };
+
+        Ok(CacheTarget {
+            cache,
+            upstream_id: Some(upstream_id),
+        })
+    }
+
+    /// Register the configured upstreams and open their isolated caches, so
+    /// that cache maintenance covers them before they serve a request
+    pub async fn register_upstreams(&self) -> Result<(), CoreError> {
+        let Some(ref manager) = self.upstream_manager else {
+            return Ok(());
+        };
+
+        for config in manager.list_upstreams() {
+            self.upstream_cache(&config).await?;
+        }
+        Ok(())
     }
 
     // ==================== Manifest Operations ====================
@@ -164,45 +533,107 @@ impl RegistryService {
         // and ensure tag/digest format compliance
         validate_reference(reference)?;
 
-        // First, check if reference is a digest
-        let _cache_key = if reference.starts_with("sha256:") {
-            reference.to_string()
-        } else {
-            // For tags, we need to check upstream to get the digest
-            // But first try cache with tag as reference
-            format!("{}:{}", repository, reference)
-        };
-
         debug!("Getting manifest: {}:{}", repository, reference);
 
+        let upstreams = self.get_upstreams(repository);
+        let target = self.cache_target(routed(&upstreams)).await?;
+
         // Check cache first (by digest if available)
-        if reference.starts_with("sha256:")
-            && let Some((data, entry)) = self.cache.get(reference).await?
+        if is_digest_reference(reference)
+            && let Some((data, entry)) = target.cache.get(reference).await?
         {
             info!("Cache hit for manifest: {}", reference);
             return Ok((data, entry.content_type, reference.to_string()));
         }
 
+        // Concurrent pulls of the same reference share one upstream fetch
+        let key = format!("{}:{}", repository, reference);
+        self.manifest_flights
+            .run(&key, || {
+                self.fetch_manifest(repository, reference, &upstreams, &target)
+            })
+            .await
+    }
+
+    /// Resolve a manifest not found in the cache by digest: serve a fresh
+    /// tag from the cache or fetch it from upstream
+    async fn fetch_manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+        upstreams: &[SelectedUpstream],
+        target: &CacheTarget,
+    ) -> Result<Manifest, CoreError> {
+        // For tag pulls, look up the digest the tag pointed to last time.
+        // Without it the pull still goes to the upstream.
+        let known_tag = if is_digest_reference(reference) {
+            None
+        } else {
+            match self.db.get_manifest_tag(repository, reference).await {
+                Ok(tag) => tag,
+                Err(e) => {
+                    warn!(
+                        "Failed to look up cached tag {}:{}: {}",
+                        repository, reference, e
+                    );
+                    None
+                }
+            }
+        };
+        // The cached copy may only stand in for an unavailable upstream if allowed
+        let stale_tag = known_tag
+            .as_ref()
+            .filter(|_| self.cache.config().serve_stale_on_error);
+
+        if upstreams.is_empty() {
+            if let Some(stale) = self
+                .serve_stale_manifest(&target.cache, stale_tag, "no upstream available")
+                .await?
+            {
+                return Ok(stale);
+            }
+            return Err(CoreError::NotFound("No upstream configured".to_string()));
+        }
+
+        if let Some(ref tag) = known_tag
+            && let Some(cached) = self
+                .revalidate_cached_tag(&target.cache, upstreams, tag, stale_tag.is_some())
+                .await?
+        {
+            return Ok(cached);
+        }
+
         // Cache miss - fetch from upstream
         info!(
             "Cache miss for manifest: {}:{}, fetching from upstream",
             repository, reference
         );
 
-        let upstream = self
-            .get_upstream(repository)
-            .ok_or_else(|| CoreError::NotFound("No upstream configured".to_string()))?;
+        let result = self
+            .with_failover(upstreams, |upstream| {
+                self.bounded_if_fallback(
+                    stale_tag.is_some(),
+                    upstream.client.get_manifest(repository, reference),
+                )
+            })
+            .await;
 
-        let (data, content_type, digest) = upstream
-            .get_manifest(repository, reference)
-            .await
-            .map_err(|e| {
-                if matches!(e, harbor_proxy::ProxyError::NotFound(_)) {
-                    CoreError::NotFound(format!("{}:{}", repository, reference))
-                } else {
-                    CoreError::Proxy(e)
+        let (data, content_type, digest) = match result {
+            Ok(manifest) => manifest,
+            Err(harbor_proxy::ProxyError::NotFound(_)) => {
+                return Err(CoreError::NotFound(format!("{}:{}", repository, reference)));
+            }
+            Err(e) => {
+                if e.is_transient()
+                    && let Some(stale) = self
+                        .serve_stale_manifest(&target.cache, stale_tag, &e.to_string())
+                        .await?
+                {
+                    return Ok(stale);
                 }
-            })?;
+                return Err(CoreError::Proxy(e));
+            }
+        };
 
         // Compute digest if not provided
         let digest = if digest.is_empty() {
@@ -212,7 +643,8 @@ impl RegistryService {
         };
 
         // Store in cache
-        self.cache
+        target
+            .cache
             .put(
                 EntryType::Manifest,
                 Some(repository.to_string()),
@@ -220,12 +652,148 @@ impl RegistryService {
                 &digest,
                 &content_type,
                 data.clone(),
+                target.upstream_id,
             )
             .await?;
 
+        if !is_digest_reference(reference) {
+            self.db
+                .upsert_manifest_tag(repository, reference, &digest)
+                .await?;
+        }
+
         Ok((data, content_type, digest))
     }
 
+    /// Answer a tag pull from the cache when the tag is still within its TTL,
+    /// or when a HEAD request confirms the upstream tag still points to the
+    /// cached digest. Returns None when a full fetch is needed.
+    ///
+    /// The TTL is taken from the routed upstream of `upstreams`.
+    async fn revalidate_cached_tag(
+        &self,
+        cache: &CacheManager,
+        upstreams: &[SelectedUpstream],
+        tag: &ManifestTag,
+        allow_stale: bool,
+    ) -> Result<Option<(Bytes, String, String)>, CoreError> {
+        let Some(entry) = cache.get_metadata(&tag.digest).await? else {
+            return Ok(None);
+        };
+
+        let ttl_secs = routed(upstreams)
+            .and_then(|upstream| upstream.tag_ttl_secs)
+            .unwrap_or(0);
+        let age = Utc::now().signed_duration_since(tag.updated_at);
+        if ttl_secs > 0 && age < chrono::Duration::seconds(ttl_secs as i64) {
+            debug!(
+                "Tag {}:{} within TTL ({}s), serving {} from cache",
+                tag.repository, tag.tag, ttl_secs, tag.digest
+            );
+            return Self::cached_manifest(cache, &tag.digest, entry.content_type).await;
+        }
+
+        let result = self
+            .with_failover(upstreams, |upstream| {
+                self.bounded_if_fallback(
+                    allow_stale,
+                    upstream.client.head_manifest(&tag.repository, &tag.tag),
+                )
+            })
+            .await;
+
+        match result {
+            Ok((digest, _, _)) if digest == tag.digest => {
+                debug!(
+                    "Tag {}:{} revalidated against upstream ({})",
+                    tag.repository, tag.tag, digest
+                );
+                self.db
+                    .upsert_manifest_tag(&tag.repository, &tag.tag, &tag.digest)
+                    .await?;
+                Self::cached_manifest(cache, &tag.digest, entry.content_type).await
+            }
+            // Tag moved (or the upstream did not report a digest): fetch it
+            Ok(_) => Ok(None),
+            Err(harbor_proxy::ProxyError::NotFound(_)) => Err(CoreError::NotFound(format!(
+                "{}:{}",
+                tag.repository, tag.tag
+            ))),
+            Err(e) if e.is_transient() && allow_stale => {
+                warn!(
+                    "Upstream unavailable for {}:{} ({}), serving cached manifest {}",
+                    tag.repository, tag.tag, e, tag.digest
+                );
+                Self::cached_manifest(cache, &tag.digest, entry.content_type).await
+            }
+            // Revalidation failed: the full fetch decides
+            Err(e) if e.is_transient() => {
+                warn!(
+                    "Failed to revalidate {}:{} ({}), fetching the manifest",
+                    tag.repository, tag.tag, e
+                );
+                Ok(None)
+            }
+            Err(e) => Err(CoreError::Proxy(e)),
+        }
+    }
+
+    /// Serve the manifest a tag last pointed to from the cache (offline mode)
+    async fn serve_stale_manifest(
+        &self,
+        cache: &CacheManager,
+        stale_tag: Option<&ManifestTag>,
+        reason: &str,
+    ) -> Result<Option<(Bytes, String, String)>, CoreError> {
+        let Some(tag) = stale_tag else {
+            return Ok(None);
+        };
+
+        match cache.get(&tag.digest).await? {
+            Some((data, entry)) => {
+                warn!(
+                    "Upstream unavailable for {}:{} ({}), serving cached manifest {}",
+                    tag.repository, tag.tag, reason, tag.digest
+                );
+                Ok(Some((data, entry.content_type, tag.digest.clone())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Read a cached manifest by digest
+    async fn cached_manifest(
+        cache: &CacheManager,
+        digest: &str,
+        content_type: String,
+    ) -> Result<Option<(Bytes, String, String)>, CoreError> {
+        Ok(cache
+            .get(digest)
+            .await?
+            .map(|(data, _)| (data, content_type, digest.to_string())))
+    }
+
+    /// Bound an upstream request by the stale fallback timeout when a cached
+    /// copy can stand in for the upstream
+    async fn bounded_if_fallback<T>(
+        &self,
+        has_fallback: bool,
+        request: impl std::future::Future<Output = Result<T, harbor_proxy::ProxyError>>,
+    ) -> Result<T, harbor_proxy::ProxyError> {
+        if !has_fallback {
+            return request.await;
+        }
+
+        let timeout_secs = self.cache.config().stale_fallback_timeout_secs;
+        match tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), request).await {
+            Ok(result) => result,
+            Err(_) => Err(harbor_proxy::ProxyError::UpstreamError {
+                status: 504,
+                message: format!("Upstream request timed out after {}s", timeout_secs),
+            }),
+        }
+    }
+
     /// Check if a manifest exists (HEAD request)
     pub async fn manifest_exists(
         &self,
@@ -237,8 +805,11 @@ impl RegistryService {
         validate_reference(reference)?;
 
         // Check cache first if reference is a digest
-        if reference.starts_with("sha256:")
-            && let Some(entry) = self.cache.get_metadata(reference).await?
+        let target = self
+            .cache_target(self.get_upstream(repository).as_ref())
+            .await?;
+        if is_digest_reference(reference)
+            && let Some(entry) = target.cache.get_metadata(reference).await?
         {
             return Ok(Some((
                 entry.content_type,
@@ -279,6 +850,7 @@ impl RegistryService {
 
         // Push to upstream first
         let upstream_digest = upstream
+            .client
             .push_manifest(repository, reference, data.clone(), content_type)
             .await?;
 
@@ -290,7 +862,9 @@ impl RegistryService {
         };
 
         // Store in cache
-        self.cache
+        let target = self.cache_target(Some(&upstream)).await?;
+        target
+            .cache
             .put(
                 EntryType::Manifest,
                 Some(repository.to_string()),
@@ -298,9 +872,16 @@ impl RegistryService {
                 &final_digest,
                 content_type,
                 data,
+                target.upstream_id,
             )
             .await?;
 
+        if !is_digest_reference(reference) {
+            self.db
+                .upsert_manifest_tag(repository, reference, &final_digest)
+                .await?;
+        }
+
         info!(
             "Pushed manifest: {}:{} -> {}",
             repository, reference, final_digest
@@ -308,6 +889,238 @@ impl RegistryService {
         Ok(final_digest)
     }
 
+    // ==================== Tag Operations ====================
+
+    /// List the tags of a repository, paginated with `n` and `last`.
+    ///
+    /// Proxied to the routed upstream. When the upstream is unavailable and
+    /// `serve_stale_on_error` is enabled, answers from the local tag index.
+    pub async fn list_tags(
+        &self,
+        repository: &str,
+        n: Option<usize>,
+        last: Option<&str>,
+    ) -> Result<TagList, CoreError> {
+        if let Some(last) = last {
+            validate_tag_reference(last)?;
+        }
+
+        let allow_stale = self.cache.config().serve_stale_on_error;
+
+        let Some(upstream) = self.get_upstream(repository) else {
+            if allow_stale && let Some(tags) = self.local_tags(repository, n, last).await? {
+                return Ok(tags);
+            }
+            return Err(CoreError::NotFound("No upstream configured".to_string()));
+        };
+
+        let result = self
+            .bounded_if_fallback(allow_stale, upstream.client.list_tags(repository, n, last))
+            .await;
+        self.record_upstream_result(&upstream, &result);
+
+        match result {
+            Ok(tags) => Ok(tags),
+            Err(harbor_proxy::ProxyError::NotFound(_)) => {
+                Err(CoreError::NotFound(format!("Repository: {}", repository)))
+            }
+            Err(e) => {
+                if e.is_transient()
+                    && allow_stale
+                    && let Some(tags) = self.local_tags(repository, n, last).await?
+                {
+                    warn!(
+                        "Upstream unavailable for {} ({}), listing cached tags",
+                        repository, e
+                    );
+                    return Ok(tags);
+                }
+                Err(CoreError::Proxy(e))
+            }
+        }
+    }
+
+    /// List tags from the local tag index (None if no tags are known)
+    async fn local_tags(
+        &self,
+        repository: &str,
+        n: Option<usize>,
+        last: Option<&str>,
+    ) -> Result<Option<TagList>, CoreError> {
+        let tags: Vec<String> = self
+            .db
+            .list_manifest_tags(repository)
+            .await?
+            .into_iter()
+            .map(|t| t.tag)
+            .collect();
+
+        if tags.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(paginate_tags(tags, n, last)))
+    }
+
+    // ==================== Referrer Operations ====================
+
+    /// Get the referrers of a manifest as an OCI image index, optionally
+    /// filtered by artifact type.
+    ///
+    /// The upstream answer replaces the local referrers index for the
+    /// subject. Upstreams without referrers support, or unavailable ones
+    /// when `serve_stale_on_error` is enabled, are answered from the local
+    /// index, which also holds manifests cached with a `subject`.
+    pub async fn get_referrers(
+        &self,
+        repository: &str,
+        digest: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Bytes, CoreError> {
+        harbor_storage::backend::validate_digest(digest)?;
+
+        let allow_stale = self.cache.config().serve_stale_on_error;
+
+        if let Some(upstream) = self.get_upstream(repository) {
+            let result = self
+                .bounded_if_fallback(
+                    allow_stale,
+                    upstream.client.get_referrers(repository, digest),
+                )
+                .await;
+            self.record_upstream_result(&upstream, &result);
+
+            match result {
+                Ok(data) => {
+                    let records: Vec<_> = parse_referrers(&data)
+                        .iter()
+                        .map(ReferrerDescriptor::to_record)
+                        .collect();
+                    self.db
+                        .set_manifest_referrers(repository, digest, &records)
+                        .await?;
+                }
+                Err(harbor_proxy::ProxyError::NotFound(_)) => {
+                    debug!(
+                        "Upstream has no referrers for {}, using local index",
+                        digest
+                    );
+                }
+                Err(e) if e.is_transient() && allow_stale => {
+                    warn!(
+                        "Upstream unavailable for {}@{} ({}), serving cached referrers",
+                        repository, digest, e
+                    );
+                }
+                Err(e) => return Err(CoreError::Proxy(e)),
+            }
+        }
+
+        let referrers = self
+            .db
+            .list_manifest_referrers(repository, digest, artifact_type)
+            .await?;
+        let manifests = referrers
+            .into_iter()
+            .map(ReferrerDescriptor::from)
+            .collect();
+        Ok(Bytes::from(referrers_index(manifests)))
+    }
+
+    // ==================== Catalog Operations ====================
+
+    /// List the repositories known to the cache that `allows` accepts,
+    /// paginated with `n` and `last`.
+    ///
+    /// Merges cached repositories with the catalog of every upstream.
+    /// Upstreams that fail or time out are skipped so the cached view is
+    /// still served. Repositories are filtered before they are paginated,
+    /// so a page is only short when no more repositories remain, and the
+    /// `next_last` cursor is always a repository on the page.
+    pub async fn list_repositories(
+        &self,
+        n: Option<usize>,
+        last: Option<&str>,
+        allows: impl Fn(&str) -> bool,
+    ) -> Result<RepositoryList, CoreError> {
+        let mut repositories: Vec<String> = Vec::new();
+        let mut cursor = last.map(str::to_string);
+        if n == Some(0) {
+            return Ok(RepositoryList {
+                repositories,
+                next_last: None,
+            });
+        }
+
+        // Look for one more repository than requested to know whether more
+        // remain
+        let wanted = n.map(|n| n.saturating_add(1));
+        loop {
+            let remaining = wanted.map(|wanted| wanted - repositories.len());
+            let page = self.repository_page(remaining, cursor.as_deref()).await?;
+            repositories.extend(page.repositories.into_iter().filter(|name| allows(name)));
+
+            if let Some(n) = n
+                && repositories.len() > n
+            {
+                repositories.truncate(n);
+                let next_last = repositories.last().cloned();
+                return Ok(RepositoryList {
+                    repositories,
+                    next_last,
+                });
+            }
+
+            // Stop at the end of the catalog, or at a source that does not
+            // move past the cursor
+            let Some(next) = page
+                .next_last
+                .filter(|next| cursor.as_ref().is_none_or(|c| next > c))
+            else {
+                return Ok(RepositoryList {
+                    repositories,
+                    next_last: None,
+                });
+            };
+            cursor = Some(next);
+        }
+    }
+
+    /// Get one page of the merged catalog, unfiltered
+    async fn repository_page(
+        &self,
+        n: Option<usize>,
+        last: Option<&str>,
+    ) -> Result<RepositoryList, CoreError> {
+        // Fetch one extra entry per source to know whether more remain
+        let limit = n.map(|n| n.saturating_add(1));
+
+        // SQLite treats a negative LIMIT as no limit
+        let local = self
+            .db
+            .get_cached_repositories_after(last, limit.map_or(-1, |l| l as i64))
+            .await?;
+        let local_complete = limit.is_none_or(|l| local.len() < l);
+
+        let clients = match (&self.upstream_manager, &self.single_upstream) {
+            (Some(manager), _) => manager.list_clients(),
+            (None, Some(client)) => vec![client.clone()],
+            (None, None) => Vec::new(),
+        };
+
+        let requests = clients
+            .iter()
+            .map(|client| self.bounded_if_fallback(true, client.catalog(limit, last)));
+        let mut upstream = Vec::new();
+        for result in futures::future::join_all(requests).await {
+            match result {
+                Ok(page) => upstream.push(page),
+                Err(e) => warn!("Skipping upstream catalog: {}", e),
+            }
+        }
+
+        Ok(merge_catalog_pages(local, local_complete, upstream, n))
+    }
+
     // ==================== Blob Operations ====================
 
     /// Get a blob as a stream (cache-aside pattern with tee for simultaneous caching and serving)
@@ -320,23 +1133,56 @@ impl RegistryService {
         harbor_storage::backend::validate_digest(digest)?;
         debug!("Getting blob stream: {}", digest);
 
+        let upstreams = self.get_upstreams(repository);
+        let target = self.cache_target(routed(&upstreams)).await?;
+
         // Check cache first
-        if let Some((stream, entry)) = self.cache.get_stream(digest).await? {
+        if let Some((stream, entry)) = target.cache.get_stream(digest).await? {
             info!("Cache hit for blob: {}", digest);
             return Ok((stream, entry.size as u64));
         }
 
-        // Cache miss - fetch from upstream with streaming
+        // Cache miss - concurrent pulls of the digest share one upstream download
         info!("Cache miss for blob: {}, fetching from upstream", digest);
+        loop {
+            let (flight, lead) = self.blob_flights.join(target.cache.namespace(), digest);
+            let receiver = flight.subscribe();
+            let size = match lead {
+                Some(lead) => {
+                    self.lead_blob_download(lead, repository, digest, &upstreams, &target)
+                        .await?
+                }
+                None => match flight.started().await {
+                    Some(size) => {
+                        debug!("Joined in-progress download of blob {}", digest);
+                        size?
+                    }
+                    // The request that started the download was cancelled
+                    None => continue,
+                },
+            };
+            return Ok((flight.reader(receiver, target.cache.clone(), size), size));
+        }
+    }
 
-        let upstream = self
-            .get_upstream(repository)
-            .ok_or_else(|| CoreError::NotFound("No upstream configured".to_string()))?;
-
-        let (stream, size) = upstream
-            .get_blob_stream(repository, digest)
-            .await
-            .map_err(|e| {
+    /// Start the shared upstream download of a blob, teeing it into the
+    /// cache. Returns the blob size.
+    async fn lead_blob_download(
+        &self,
+        lead: BlobLead,
+        repository: &str,
+        digest: &str,
+        upstreams: &[SelectedUpstream],
+        target: &CacheTarget,
+    ) -> Result<u64, CoreError> {
+        let download = async {
+            let result = self
+                .with_failover(upstreams, |upstream| {
+                    upstream.client.get_blob_stream(repository, digest)
+                })
+                .await;
+
+            let (stream, size) = result.map_err(|e| {
                 if matches!(e, harbor_proxy::ProxyError::NotFound(_)) {
                     CoreError::NotFound(digest.to_string())
                 } else {
@@ -344,51 +1190,129 @@ impl RegistryService {
                 }
             })?;
 
-        // Convert ProxyError stream to StorageError stream for caching
-        use futures::StreamExt;
-        let storage_stream: harbor_storage::backend::ByteStream = Box::pin(stream.map(|result| {
-            result
-                .map_err(|e| harbor_storage::StorageError::Io(std::io::Error::other(e.to_string())))
-        }));
+            let (client_stream, cache_handle) = target
+                .cache
+                .tee_and_cache_stream(
+                    EntryType::Blob,
+                    Some(repository.to_string()),
+                    None,
+                    digest,
+                    "application/octet-stream",
+                    storage_stream(stream),
+                    Some(size),
+                    target.upstream_id,
+                )
+                .await?;
+            Ok((client_stream, size, cache_handle))
+        };
+
+        match download.await {
+            Ok((stream, size, cache_handle)) => {
+                lead.broadcast(
+                    size,
+                    stream,
+                    await_cache_write(digest.to_string(), cache_handle),
+                );
+                Ok(size)
+            }
+            Err(e) => {
+                lead.fail(&e);
+                Err(e)
+            }
+        }
+    }
 
+    /// Get a byte range of a blob as a stream.
+    ///
+    /// Cached blobs are served from storage. On a cache miss the range is
+    /// forwarded to the upstream and the partial response is not cached;
+    /// if the upstream ignores the range, the full blob is served and
+    /// cached as usual (no range is returned in that case).
+    pub async fn get_blob_range(
+        &self,
+        repository: &str,
+        digest: &str,
+        range: &ByteRange,
+    ) -> Result<
+        (
+            harbor_storage::backend::ByteStream,
+            u64,
+            Option<ContentRange>,
+        ),
+        CoreError,
+    > {
+        harbor_storage::backend::validate_digest(digest)?;
+        debug!("Getting blob range {}: {}", range.header_value(), digest);
+
+        let upstreams = self.get_upstreams(repository);
+        let target = self.cache_target(routed(&upstreams)).await?;
+        if let Some((stream, content_range)) = target.cache.get_range_stream(digest, range).await? {
+            info!("Cache hit for blob range: {}", digest);
+            return Ok((stream, content_range.length(), Some(content_range)));
+        }
+
+        info!(
+            "Cache miss for blob range: {}, forwarding to upstream",
+            digest
+        );
+
+        let range_header = range.header_value();
+        let result = self
+            .with_failover(&upstreams, |upstream| {
+                upstream
+                    .client
+                    .get_blob_range_stream(repository, digest, &range_header)
+            })
+            .await;
+
+        let (stream, size, content_range) = result.map_err(|e| match e {
+            harbor_proxy::ProxyError::NotFound(_) => CoreError::NotFound(digest.to_string()),
+            harbor_proxy::ProxyError::RangeNotSatisfiable { size } => {
+                CoreError::RangeNotSatisfiable { size }
+            }
+            e => CoreError::Proxy(e),
+        })?;
+
+        match content_range {
+            Some(content_range) => Ok((storage_stream(stream), size, Some(content_range))),
+            None => {
+                let client_stream = self
+                    .tee_upstream_blob(&target, repository, digest, stream, size)
+                    .await?;
+                Ok((client_stream, size, None))
+            }
+        }
+    }
+
+    /// Tee a full upstream blob stream into the cache while serving it.
+    /// Caching runs in the background and does not block the client.
+    async fn tee_upstream_blob(
+        &self,
+        target: &CacheTarget,
+        repository: &str,
+        digest: &str,
+        stream: harbor_proxy::client::ByteStream,
+        size: u64,
+    ) -> Result<harbor_storage::backend::ByteStream, CoreError> {
         // Tee the stream: one copy to cache, one copy to return
-        let (client_stream, cache_handle) = self
+        let (client_stream, cache_handle) = target
             .cache
             .tee_and_cache_stream(
                 EntryType::Blob,
                 Some(repository.to_string()),
-                None,
-                digest,
-                "application/octet-stream",
-                storage_stream,
-                Some(size),
-            )
-            .await?;
-
-        // Spawn a wrapper task that awaits the cache handle and logs errors.
-        // We don't block the client response on caching completion.
-        let digest_for_log = digest.to_string();
-        tokio::spawn(async move {
-            match cache_handle.await {
-                Ok(Ok(_entry)) => {
-                    debug!("Background cache write succeeded for {}", digest_for_log);
-                }
-                Ok(Err(e)) => {
-                    warn!(
-                        "Background cache write failed for {}: {}",
-                        digest_for_log, e
-                    );
-                }
-                Err(e) => {
-                    warn!(
-                        "Background cache task panicked for {}: {:?}",
-                        digest_for_log, e
-                    );
-                }
-            }
-        });
+                None,
+                digest,
+                "application/octet-stream",
+                storage_stream(stream),
+                Some(size),
+                target.upstream_id,
+            )
+            .await?;
 
-        Ok((client_stream, size))
+        // We don't block the client response on caching completion
+        tokio::spawn(await_cache_write(digest.to_string(), cache_handle));
+
+        Ok(client_stream)
     }
 
     /// Get a blob fully buffered (for cases that need in-memory data)
@@ -402,8 +1326,11 @@ impl RegistryService {
         harbor_storage::backend::validate_digest(digest)?;
         debug!("Getting blob buffered: {}", digest);
 
+        let upstream = self.get_upstream(repository);
+        let target = self.cache_target(upstream.as_ref()).await?;
+
         // Check cache first
-        if let Some((data, _entry)) = self.cache.get(digest).await? {
+        if let Some((data, _entry)) = target.cache.get(digest).await? {
             info!("Cache hit for blob: {}", digest);
             return Ok(data);
         }
@@ -411,21 +1338,25 @@ impl RegistryService {
         // Cache miss - fetch from upstream
         info!("Cache miss for blob: {}, fetching from upstream", digest);
 
-        let upstream = self
-            .get_upstream(repository)
-            .ok_or_else(|| CoreError::NotFound("No upstream configured".to_string()))?;
+        let upstream =
+            upstream.ok_or_else(|| CoreError::NotFound("No upstream configured".to_string()))?;
 
         #[allow(deprecated)]
-        let (data, _size) = upstream.get_blob(repository, digest).await.map_err(|e| {
-            if matches!(e, harbor_proxy::ProxyError::NotFound(_)) {
-                CoreError::NotFound(digest.to_string())
-            } else {
-                CoreError::Proxy(e)
-            }
-        })?;
+        let (data, _size) = upstream
+            .client
+            .get_blob(repository, digest)
+            .await
+            .map_err(|e| {
+                if matches!(e, harbor_proxy::ProxyError::NotFound(_)) {
+                    CoreError::NotFound(digest.to_string())
+                } else {
+                    CoreError::Proxy(e)
+                }
+            })?;
 
         // Store in cache
-        self.cache
+        target
+            .cache
             .put(
                 EntryType::Blob,
                 Some(repository.to_string()),
@@ -433,6 +1364,7 @@ impl RegistryService {
                 digest,
                 "application/octet-stream",
                 data.clone(),
+                target.upstream_id,
             )
             .await?;
 
@@ -447,18 +1379,26 @@ impl RegistryService {
     ) -> Result<Option<i64>, CoreError> {
         // Validate digest format at service boundary to prevent path traversal
         harbor_storage::backend::validate_digest(digest)?;
+        let upstreams = self.get_upstreams(repository);
+        let target = self.cache_target(routed(&upstreams)).await?;
+
         // Check cache first
-        if let Some(entry) = self.cache.get_metadata(digest).await? {
+        if let Some(entry) = target.cache.get_metadata(digest).await? {
             return Ok(Some(entry.size));
         }
 
         // Check upstream with HEAD request only (no download)
-        let upstream = match self.get_upstream(repository) {
-            Some(u) => u,
-            None => return Ok(None),
-        };
+        if upstreams.is_empty() {
+            return Ok(None);
+        }
+
+        let result = self
+            .with_failover(&upstreams, |upstream| {
+                upstream.client.get_blob_size(repository, digest)
+            })
+            .await;
 
-        match upstream.get_blob_size(repository, digest).await {
+        match result {
             Ok((size, _content_type)) => {
                 // Optionally trigger background cache warm-up
                 // For now, just return the size without downloading
@@ -469,6 +1409,120 @@ impl RegistryService {
         }
     }
 
+    // ==================== Repair Operations ====================
+
+    /// Fetch the content of a lost or corrupt cache entry again from the
+    /// upstream of its repository.
+    ///
+    /// The broken entry must have been removed first. Entries without a
+    /// recorded repository cannot be re-fetched.
+    pub async fn refetch_entry(&self, entry: &CacheEntry) -> Result<(), CoreError> {
+        let repository = entry.repository.as_deref().ok_or_else(|| {
+            CoreError::NotFound(format!("No repository recorded for {}", entry.digest))
+        })?;
+        debug!("Re-fetching {}@{}", repository, entry.digest);
+
+        if entry.entry_type == EntryType::Manifest {
+            self.get_manifest(repository, &entry.digest).await?;
+            return Ok(());
+        }
+
+        let upstreams = self.get_upstreams(repository);
+        if upstreams.is_empty() {
+            return Err(CoreError::NotFound("No upstream configured".to_string()));
+        }
+        let (stream, size) = self
+            .with_failover(&upstreams, |upstream| {
+                upstream.client.get_blob_stream(repository, &entry.digest)
+            })
+            .await?;
+
+        let target = self.cache_target(routed(&upstreams)).await?;
+        target
+            .cache
+            .put_stream(
+                EntryType::Blob,
+                Some(repository.to_string()),
+                None,
+                &entry.digest,
+                &entry.content_type,
+                storage_stream(stream),
+                Some(size),
+                target.upstream_id,
+            )
+            .await?;
+        Ok(())
+    }
+
+    // ==================== Delete Operations ====================
+
+    /// Delete a manifest by tag or digest.
+    ///
+    /// The delete is forwarded to the routed upstream first. The local copy
+    /// is invalidated once the upstream deleted the manifest or reports it
+    /// missing: deleting a tag drops it from the tag index, deleting a
+    /// digest also drops the cached manifest and every tag pointing to it.
+    pub async fn delete_manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<(), CoreError> {
+        // Validate reference format at service boundary to prevent path traversal
+        validate_reference(reference)?;
+        debug!("Deleting manifest: {}:{}", repository, reference);
+
+        let upstream = self
+            .get_upstream(repository)
+            .ok_or_else(|| CoreError::NotFound("No upstream configured".to_string()))?;
+        let result = upstream.client.delete_manifest(repository, reference).await;
+        self.record_upstream_result(&upstream, &result);
+        let result = result.map_err(delete_error);
+        if let Err(e) = &result
+            && !matches!(e, CoreError::NotFound(_))
+        {
+            return result;
+        }
+
+        if is_digest_reference(reference) {
+            self.db
+                .delete_manifest_tags_for_digest(repository, reference)
+                .await?;
+            let target = self.cache_target(Some(&upstream)).await?;
+            target.cache.delete(reference).await?;
+        } else {
+            self.db.delete_manifest_tag(repository, reference).await?;
+        }
+
+        info!("Deleted manifest: {}:{}", repository, reference);
+        result
+    }
+
+    /// Delete a blob, forwarding the delete to the routed upstream and
+    /// invalidating the cached copy
+    pub async fn delete_blob(&self, repository: &str, digest: &str) -> Result<(), CoreError> {
+        // Validate digest format at service boundary to prevent path traversal
+        harbor_storage::backend::validate_digest(digest)?;
+        debug!("Deleting blob: {}@{}", repository, digest);
+
+        let upstream = self
+            .get_upstream(repository)
+            .ok_or_else(|| CoreError::NotFound("No upstream configured".to_string()))?;
+        let result = upstream.client.delete_blob(repository, digest).await;
+        self.record_upstream_result(&upstream, &result);
+        let result = result.map_err(delete_error);
+        if let Err(e) = &result
+            && !matches!(e, CoreError::NotFound(_))
+        {
+            return result;
+        }
+
+        let target = self.cache_target(Some(&upstream)).await?;
+        target.cache.delete(digest).await?;
+
+        info!("Deleted blob: {}@{}", repository, digest);
+        result
+    }
+
     // ==================== Upload Operations ====================
 
     /// Validate session ID format to prevent path traversal attacks.
@@ -543,15 +1597,67 @@ impl RegistryService {
         Ok(self.db.get_upload_session(session_id).await?)
     }
 
-    /// Append data to an upload session
-    pub async fn append_upload(&self, session_id: &str, data: Bytes) -> Result<i64, CoreError> {
+    /// Append data to an upload session.
+    ///
+    /// When the client sent a `Content-Range`, the chunk must start at the
+    /// number of bytes received so far and match the body length; retried
+    /// or out-of-order chunks are rejected instead of corrupting the blob.
+    pub async fn append_upload(
+        &self,
+        session_id: &str,
+        data: Bytes,
+        range: Option<ChunkRange>,
+    ) -> Result<i64, CoreError> {
         // Validate session ID format to prevent path traversal
         Self::validate_session_id(session_id)?;
         debug!("Appending {} bytes to upload: {}", data.len(), session_id);
 
-        let new_size = self.storage.append_chunk(session_id, data).await?;
+        let session = self
+            .db
+            .get_upload_session(session_id)
+            .await?
+            .ok_or_else(|| CoreError::NotFound(format!("Upload session: {}", session_id)))?;
+        if let Some(range) = range {
+            let offset = session.bytes_received as u64;
+            if range.start != offset {
+                return Err(CoreError::UploadRangeInvalid { offset });
+            }
+            if range.length() != data.len() as u64 {
+                return Err(CoreError::BadRequest(format!(
+                    "Content-Range {}-{} does not match chunk length {}",
+                    range.start,
+                    range.end,
+                    data.len()
+                )));
+            }
+        }
+        let mut state = upload_state(&self.db, &session).await?;
+        let known_parts = state.parts.len();
+
+        let new_size = self
+            .storage
+            .append_chunk(session_id, &mut state, data)
+            .await?;
+
+        let new_parts: Vec<UploadSessionPart> = state
+            .parts
+            .iter()
+            .enumerate()
+            .skip(known_parts)
+            .map(|(number, part)| UploadSessionPart {
+                session_id: session_id.to_string(),
+                part_number: number as i64,
+                etag: part.etag.clone(),
+                size: part.size as i64,
+            })
+            .collect();
         self.db
-            .update_upload_session(session_id, new_size as i64)
+            .update_upload_session_multipart(
+                session_id,
+                new_size as i64,
+                state.multipart_upload_id.as_deref(),
+                &new_parts,
+            )
             .await?;
 
         Ok(new_size as i64)
@@ -571,16 +1677,17 @@ impl RegistryService {
         debug!("Completing upload: {} -> {}", session_id, digest);
 
         // Get session info
-        let _session = self
+        let session = self
             .db
             .get_upload_session(session_id)
             .await?
             .ok_or_else(|| CoreError::NotFound(format!("Upload session: {}", session_id)))?;
+        let state = upload_state(&self.db, &session).await?;
 
         // Complete the chunked upload (validates digest)
         let storage_path = self
             .storage
-            .complete_chunked_upload(session_id, digest)
+            .complete_chunked_upload(session_id, &state, digest)
             .await?;
 
         // Get the size
@@ -603,22 +1710,51 @@ impl RegistryService {
 
         // Push to upstream with streaming
         upstream
+            .client
             .push_blob_stream(repository, digest, proxy_stream, size)
             .await?;
 
         // Create cache entry
-        self.db
-            .insert_cache_entry(harbor_db::NewCacheEntry {
-                entry_type: EntryType::Blob,
-                repository: Some(repository.to_string()),
-                reference: None,
-                digest: digest.to_string(),
-                content_type: "application/octet-stream".to_string(),
-                size: size as i64,
-                storage_path,
-                upstream_id: None,
-            })
-            .await?;
+        let target = self.cache_target(Some(&upstream)).await?;
+        if target.cache.namespace() == SHARED_NAMESPACE {
+            self.db
+                .insert_cache_entry(harbor_db::NewCacheEntry {
+                    entry_type: EntryType::Blob,
+                    repository: Some(repository.to_string()),
+                    reference: None,
+                    digest: digest.to_string(),
+                    content_type: "application/octet-stream".to_string(),
+                    size: size as i64,
+                    storage_path,
+                    upstream_id: target.upstream_id,
+                    namespace: SHARED_NAMESPACE,
+                })
+                .await?;
+        } else {
+            // The upload was assembled in shared storage: move it into the
+            // isolated cache of the upstream
+            target
+                .cache
+                .put_stream(
+                    EntryType::Blob,
+                    Some(repository.to_string()),
+                    None,
+                    digest,
+                    "application/octet-stream",
+                    self.storage.stream(digest).await?,
+                    Some(size),
+                    target.upstream_id,
+                )
+                .await?;
+            if self
+                .db
+                .get_cache_entry_by_digest(digest, SHARED_NAMESPACE)
+                .await?
+                .is_none()
+            {
+                self.storage.delete(digest).await?;
+            }
+        }
 
         // Delete upload session
         self.db.delete_upload_session(session_id).await?;
@@ -633,7 +1769,14 @@ impl RegistryService {
         Self::validate_session_id(session_id)?;
         debug!("Canceling upload: {}", session_id);
 
-        self.storage.cancel_chunked_upload(session_id).await?;
+        let state = match self.db.get_upload_session(session_id).await? {
+            Some(session) => upload_state(&self.db, &session).await?,
+            None => ChunkedUploadState::default(),
+        };
+
+        self.storage
+            .cancel_chunked_upload(session_id, &state)
+            .await?;
         self.db.delete_upload_session(session_id).await?;
 
         Ok(())
@@ -654,7 +1797,10 @@ impl RegistryService {
         );
 
         // Check if blob exists in cache
-        if self.cache.exists(digest).await? {
+        let target = self
+            .cache_target(self.get_upstream(repository).as_ref())
+            .await?;
+        if target.cache.exists(digest).await? {
             info!("Blob {} found in cache, mount successful", digest);
             return Ok(true);
         }
@@ -666,9 +1812,9 @@ impl RegistryService {
         };
 
         // Check if it exists in upstream (from source)
-        if upstream.blob_exists(from, digest).await? {
+        if upstream.client.blob_exists(from, digest).await? {
             // Fetch from source and cache with streaming
-            let (proxy_stream, size) = upstream.get_blob_stream(from, digest).await?;
+            let (proxy_stream, size) = upstream.client.get_blob_stream(from, digest).await?;
 
             // Convert ProxyError stream to StorageError stream for caching
             use futures::StreamExt;
@@ -679,7 +1825,8 @@ impl RegistryService {
                     })
                 }));
 
-            self.cache
+            target
+                .cache
                 .put_stream(
                     EntryType::Blob,
                     Some(repository.to_string()),
@@ -688,6 +1835,7 @@ impl RegistryService {
                     "application/octet-stream",
                     storage_stream,
                     Some(size),
+                    target.upstream_id,
                 )
                 .await?;
 
@@ -698,3 +1846,586 @@ impl RegistryService {
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_paginate_tags() {
+        let all = tags(&["1.0", "1.1", "2.0", "latest"]);
+
+        let page = paginate_tags(all.clone(), Some(2), None);
+        assert_eq!(page.tags, tags(&["1.0", "1.1"]));
+        assert_eq!(page.next_last.as_deref(), Some("1.1"));
+
+        let page = paginate_tags(all.clone(), Some(2), Some("1.1"));
+        assert_eq!(page.tags, tags(&["2.0", "latest"]));
+        assert_eq!(page.next_last, None);
+
+        let page = paginate_tags(all, None, Some("1.0"));
+        assert_eq!(page.tags, tags(&["1.1", "2.0", "latest"]));
+        assert_eq!(page.next_last, None);
+    }
+
+    #[test]
+    fn test_merge_catalog_pages() {
+        let upstream = vec![
+            RepositoryList {
+                repositories: tags(&["alpine", "nginx"]),
+                next_last: None,
+            },
+            RepositoryList {
+                repositories: tags(&["busybox", "debian"]),
+                next_last: Some("debian".to_string()),
+            },
+        ];
+
+        // Entries past an incomplete upstream page wait for the next page
+        let page = merge_catalog_pages(tags(&["alpine", "zookeeper"]), true, upstream, Some(10));
+        assert_eq!(page.repositories, tags(&["alpine", "busybox", "debian"]));
+        assert_eq!(page.next_last.as_deref(), Some("debian"));
+
+        let page = merge_catalog_pages(tags(&["a", "b", "c"]), true, Vec::new(), Some(2));
+        assert_eq!(page.repositories, tags(&["a", "b"]));
+        assert_eq!(page.next_last.as_deref(), Some("b"));
+
+        let page = merge_catalog_pages(tags(&["a", "b"]), true, Vec::new(), None);
+        assert_eq!(page.repositories, tags(&["a", "b"]));
+        assert_eq!(page.next_last, None);
+    }
+
+    #[test]
+    fn test_delete_error_mapping() {
+        use harbor_proxy::ProxyError;
+
+        assert!(matches!(
+            delete_error(ProxyError::NotFound("app:1".to_string())),
+            CoreError::NotFound(_)
+        ));
+        assert!(matches!(
+            delete_error(ProxyError::UpstreamError {
+                status: 405,
+                message: String::new()
+            }),
+            CoreError::Unsupported(_)
+        ));
+        assert!(matches!(
+            delete_error(ProxyError::UpstreamError {
+                status: 500,
+                message: String::new()
+            }),
+            CoreError::Proxy(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_upstream_delete_keeps_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = service(&dir).await;
+        let data = Bytes::from_static(b"{}");
+        let digest = harbor_storage::backend::compute_sha256(&data);
+        registry
+            .cache
+            .put(
+                EntryType::Manifest,
+                Some("library/app".to_string()),
+                Some("v1".to_string()),
+                &digest,
+                "application/vnd.oci.image.manifest.v1+json",
+                data,
+                None,
+            )
+            .await
+            .unwrap();
+        registry
+            .db
+            .upsert_manifest_tag("library/app", "v1", &digest)
+            .await
+            .unwrap();
+
+        // The test upstream is unreachable, so nothing is invalidated
+        assert!(registry.delete_manifest("library/app", "v1").await.is_err());
+        assert!(
+            registry
+                .delete_manifest("library/app", &digest)
+                .await
+                .is_err()
+        );
+        assert!(registry.cache.exists(&digest).await.unwrap());
+        assert!(
+            registry
+                .db
+                .get_manifest_tag("library/app", "v1")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_upstream_fails_over_to_mirrors() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = |name: &str, mirrors: &[&str]| crate::UpstreamConfig {
+            is_default: name == "main",
+            mirrors: mirrors.iter().map(|m| m.to_string()).collect(),
+            ..upstream_config(name, &[])
+        };
+        let (registry, manager) =
+            routed_service(&dir, vec![upstream("main", &["dr"]), upstream("dr", &[])]).await;
+
+        // Every member of the group is unreachable: each is tried once
+        assert!(registry.get_manifest("library/app", "v1").await.is_err());
+        for name in ["main", "dr"] {
+            let health = manager.get_upstream_health(name).unwrap();
+            assert!(!health.healthy);
+            assert_eq!(health.consecutive_failures, 1);
+        }
+    }
+
+    /// Configuration of an unreachable upstream serving the given routes
+    fn upstream_config(name: &str, routes: &[&str]) -> crate::UpstreamConfig {
+        crate::UpstreamConfig {
+            name: name.to_string(),
+            display_name: None,
+            url: "http://127.0.0.1:1".to_string(),
+            registry: "library".to_string(),
+            projects: vec![],
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            priority: 100,
+            enabled: true,
+            cache_isolation: "shared".to_string(),
+            cache_max_size: None,
+            is_default: false,
+            routes: routes
+                .iter()
+                .map(|pattern| crate::UpstreamRouteConfig {
+                    pattern: pattern.to_string(),
+                    priority: 100,
+                    tag_ttl_secs: None,
+                })
+                .collect(),
+            tag_ttl_secs: None,
+            mirrors: vec![],
+            retry: Default::default(),
+        }
+    }
+
+    /// Registry service routing through an upstream manager
+    async fn routed_service(
+        dir: &tempfile::TempDir,
+        upstreams: Vec<crate::UpstreamConfig>,
+    ) -> (RegistryService, Arc<UpstreamManager>) {
+        routed_service_with(dir, upstreams, crate::CacheConfig::default()).await
+    }
+
+    async fn routed_service_with(
+        dir: &tempfile::TempDir,
+        upstreams: Vec<crate::UpstreamConfig>,
+        config: crate::CacheConfig,
+    ) -> (RegistryService, Arc<UpstreamManager>) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(harbor_storage::LocalStorage::new(dir.path()).await.unwrap());
+        let cache = Arc::new(CacheManager::new(db.clone(), storage.clone(), config));
+        let provider = crate::config::InMemoryConfigProvider::new(upstreams);
+        let manager = Arc::new(UpstreamManager::new(Arc::new(provider)).unwrap());
+        let registry = RegistryService::with_upstream_manager(cache, manager.clone(), db, storage);
+        (registry, manager)
+    }
+
+    #[tokio::test]
+    async fn test_routes_repositories_to_upstreams() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = crate::UpstreamConfig {
+            is_default: true,
+            ..upstream_config("main", &[])
+        };
+        let (registry, _) =
+            routed_service(&dir, vec![main, upstream_config("team-a", &["team-a/*"])]).await;
+
+        let selected = registry.get_upstream("team-a/app").unwrap();
+        assert_eq!(selected.name.as_deref(), Some("team-a"));
+        assert!(!selected.mirror);
+
+        let selected = registry.get_upstream("library/nginx").unwrap();
+        assert_eq!(selected.name.as_deref(), Some("main"));
+    }
+
+    #[tokio::test]
+    async fn test_proxied_results_update_upstream_health() {
+        let dir = tempfile::tempdir().unwrap();
+        let (registry, manager) =
+            routed_service(&dir, vec![upstream_config("team-a", &["team-a/*"])]).await;
+        let upstream = registry.get_upstream("team-a/app").unwrap();
+        let unavailable = || -> Result<(), harbor_proxy::ProxyError> {
+            Err(harbor_proxy::ProxyError::UpstreamError {
+                status: 503,
+                message: "Service Unavailable".to_string(),
+            })
+        };
+
+        registry.record_upstream_result(&upstream, &unavailable());
+        let health = manager.get_upstream_health("team-a").unwrap();
+        assert!(!health.healthy);
+        assert_eq!(health.consecutive_failures, 1);
+
+        // Definitive answers say nothing about the upstream's health
+        registry.record_upstream_result::<()>(
+            &upstream,
+            &Err(harbor_proxy::ProxyError::NotFound("app".to_string())),
+        );
+        let health = manager.get_upstream_health("team-a").unwrap();
+        assert_eq!(health.consecutive_failures, 1);
+
+        registry.record_upstream_result(&upstream, &Ok(()));
+        let health = manager.get_upstream_health("team-a").unwrap();
+        assert!(health.healthy);
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    async fn service(dir: &tempfile::TempDir) -> RegistryService {
+        service_with(dir, crate::CacheConfig::default()).await
+    }
+
+    /// Registry service with a single unreachable upstream
+    async fn service_with(dir: &tempfile::TempDir, config: crate::CacheConfig) -> RegistryService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(harbor_storage::LocalStorage::new(dir.path()).await.unwrap());
+        let cache = Arc::new(CacheManager::new(db.clone(), storage.clone(), config));
+        let client = HarborClient::new(harbor_proxy::HarborClientConfig {
+            url: "http://127.0.0.1:1".to_string(),
+            registry: "library".to_string(),
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            retry: Default::default(),
+        })
+        .unwrap();
+        RegistryService::new(cache, Arc::new(client), db, storage)
+    }
+
+    /// Cache a manifest and record it as the target of a tag
+    async fn cache_tagged_manifest(
+        registry: &RegistryService,
+        repository: &str,
+        tag: &str,
+    ) -> String {
+        let data = Bytes::from_static(b"{}");
+        let digest = harbor_storage::backend::compute_sha256(&data);
+        registry
+            .cache
+            .put(
+                EntryType::Manifest,
+                Some(repository.to_string()),
+                Some(tag.to_string()),
+                &digest,
+                "application/vnd.oci.image.manifest.v1+json",
+                data,
+                None,
+            )
+            .await
+            .unwrap();
+        registry
+            .db
+            .upsert_manifest_tag(repository, tag, &digest)
+            .await
+            .unwrap();
+        digest
+    }
+
+    #[tokio::test]
+    async fn test_cached_tag_served_while_upstream_unreachable() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = service(&dir).await;
+        let digest = cache_tagged_manifest(&registry, "library/app", "v1").await;
+
+        let (data, _, served) = registry.get_manifest("library/app", "v1").await.unwrap();
+        assert_eq!(served, digest);
+        assert_eq!(data, Bytes::from_static(b"{}"));
+
+        // Tags never pulled before have nothing to fall back to
+        assert!(registry.get_manifest("library/app", "v2").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cached_tag_not_served_when_stale_serving_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::CacheConfig {
+            serve_stale_on_error: false,
+            ..crate::CacheConfig::default()
+        };
+        let registry = service_with(&dir, config).await;
+        cache_tagged_manifest(&registry, "library/app", "v1").await;
+
+        assert!(matches!(
+            registry.get_manifest("library/app", "v1").await,
+            Err(CoreError::Proxy(_))
+        ));
+    }
+
+    /// Serve canned HTTP responses on a local port, one connection each,
+    /// and return the base URL and the request lines received
+    async fn serve(responses: Vec<String>) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                let head = String::from_utf8_lossy(&head);
+                received
+                    .lock()
+                    .unwrap()
+                    .push(head.lines().next().unwrap_or_default().to_string());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Upstream response to a manifest request
+    fn manifest_response(digest: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/vnd.oci.image.manifest.v1+json\r\n\
+             Docker-Content-Digest: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            digest,
+            body.len(),
+            body
+        )
+    }
+
+    /// Registry service routing `library/*` to an upstream at `url`
+    async fn revalidating_service(
+        dir: &tempfile::TempDir,
+        url: &str,
+        tag_ttl_secs: Option<u64>,
+        config: crate::CacheConfig,
+    ) -> (RegistryService, Arc<UpstreamManager>) {
+        let upstream = crate::UpstreamConfig {
+            url: url.to_string(),
+            tag_ttl_secs,
+            retry: crate::UpstreamRetryConfig {
+                max_attempts: 1,
+                ..Default::default()
+            },
+            ..upstream_config("main", &["library/*"])
+        };
+        routed_service_with(dir, vec![upstream], config).await
+    }
+
+    #[tokio::test]
+    async fn test_tag_within_ttl_served_without_upstream() {
+        let dir = tempfile::tempdir().unwrap();
+        let (registry, manager) = revalidating_service(
+            &dir,
+            "http://127.0.0.1:1",
+            Some(300),
+            crate::CacheConfig::default(),
+        )
+        .await;
+        let digest = cache_tagged_manifest(&registry, "library/app", "v1").await;
+
+        let (_, _, served) = registry.get_manifest("library/app", "v1").await.unwrap();
+        assert_eq!(served, digest);
+        // The unreachable upstream was never contacted
+        let health = manager.get_upstream_health("main").unwrap();
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stale_tag_revalidated_with_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let digest = harbor_storage::backend::compute_sha256(b"{}");
+        let (url, requests) = serve(vec![manifest_response(&digest, "")]).await;
+        let (registry, _) =
+            revalidating_service(&dir, &url, None, crate::CacheConfig::default()).await;
+        cache_tagged_manifest(&registry, "library/app", "v1").await;
+
+        let (data, _, served) = registry.get_manifest("library/app", "v1").await.unwrap();
+        assert_eq!(served, digest);
+        assert_eq!(data, Bytes::from_static(b"{}"));
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["HEAD /v2/library/app/manifests/v1 HTTP/1.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_moved_tag_fetched_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = r#"{"schemaVersion":2}"#;
+        let moved = harbor_storage::backend::compute_sha256(body.as_bytes());
+        let (url, requests) = serve(vec![
+            manifest_response(&moved, ""),
+            manifest_response(&moved, body),
+        ])
+        .await;
+        let (registry, _) =
+            revalidating_service(&dir, &url, None, crate::CacheConfig::default()).await;
+        cache_tagged_manifest(&registry, "library/app", "v1").await;
+
+        let (data, _, served) = registry.get_manifest("library/app", "v1").await.unwrap();
+        assert_eq!(served, moved);
+        assert_eq!(data, Bytes::from(body));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        let tag = registry
+            .db
+            .get_manifest_tag("library/app", "v1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tag.digest, moved);
+    }
+
+    #[tokio::test]
+    async fn test_failed_revalidation_falls_through_to_fetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = r#"{"schemaVersion":2}"#;
+        let moved = harbor_storage::backend::compute_sha256(body.as_bytes());
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            manifest_response(&moved, body),
+        ])
+        .await;
+        let config = crate::CacheConfig {
+            serve_stale_on_error: false,
+            ..crate::CacheConfig::default()
+        };
+        let (registry, _) = revalidating_service(&dir, &url, None, config).await;
+        cache_tagged_manifest(&registry, "library/app", "v1").await;
+
+        let (_, _, served) = registry.get_manifest("library/app", "v1").await.unwrap();
+        assert_eq!(served, moved);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "HEAD /v2/library/app/manifests/v1 HTTP/1.1",
+                "GET /v2/library/app/manifests/v1 HTTP/1.1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_state_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = service(&dir).await;
+
+        let session_id = registry.start_upload("library/app").await.unwrap();
+        registry
+            .append_upload(&session_id, Bytes::from_static(b"hello "), None)
+            .await
+            .unwrap();
+        let size = registry
+            .append_upload(&session_id, Bytes::from_static(b"world"), None)
+            .await
+            .unwrap();
+        assert_eq!(size, 11);
+
+        let session = registry
+            .db
+            .get_upload_session(&session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.bytes_received, 11);
+        assert_eq!(
+            upload_state(&registry.db, &session).await.unwrap(),
+            ChunkedUploadState::default()
+        );
+
+        // Parts recorded by a multipart backend are restored in order
+        let parts: Vec<_> = ["etag-0", "etag-1"]
+            .iter()
+            .enumerate()
+            .map(|(number, etag)| UploadSessionPart {
+                session_id: session_id.clone(),
+                part_number: number as i64,
+                etag: etag.to_string(),
+                size: 5,
+            })
+            .collect();
+        registry
+            .db
+            .update_upload_session_multipart(&session_id, 10, Some("upload-1"), &parts)
+            .await
+            .unwrap();
+
+        let session = registry
+            .db
+            .get_upload_session(&session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let state = upload_state(&registry.db, &session).await.unwrap();
+        assert_eq!(state.multipart_upload_id.as_deref(), Some("upload-1"));
+        assert_eq!(state.parts.len(), 2);
+        assert_eq!(state.parts[1].etag, "etag-1");
+        assert_eq!(state.parts_size(), 10);
+
+        registry.cancel_upload(&session_id).await.unwrap();
+        assert!(
+            registry
+                .db
+                .get_upload_session_parts(&session_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_upload_validates_chunk_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = service(&dir).await;
+        let session_id = registry.start_upload("library/app").await.unwrap();
+        let chunk = |start, end| Some(ChunkRange { start, end });
+
+        let size = registry
+            .append_upload(&session_id, Bytes::from_static(b"hello "), chunk(0, 5))
+            .await
+            .unwrap();
+        assert_eq!(size, 6);
+
+        // A retried chunk is rejected with the current offset
+        let err = registry
+            .append_upload(&session_id, Bytes::from_static(b"hello "), chunk(0, 5))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::UploadRangeInvalid { offset: 6 }));
+
+        // So is a range that does not match the body
+        let err = registry
+            .append_upload(&session_id, Bytes::from_static(b"world"), chunk(6, 20))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::BadRequest(_)));
+
+        let size = registry
+            .append_upload(&session_id, Bytes::from_static(b"world"), chunk(6, 10))
+            .await
+            .unwrap();
+        assert_eq!(size, 11);
+    }
+}
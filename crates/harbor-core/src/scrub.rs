@@ -0,0 +1,437 @@
+//! Background blob scrubber
+//!
+//! Digests are verified only when content is written, so bit rot on disk or
+//! a truncated object would be served as-is. The scrubber re-hashes all
+//! cached content on a schedule at a limited read rate, drops corrupt
+//! entries and re-fetches them from upstream. Progress is stored in the
+//! database so an interrupted pass resumes where it stopped. Isolated
+//! upstream caches are scrubbed after the shared cache, one namespace at a
+//! time.
+
+use chrono::Utc;
+use harbor_db::{CacheEntry, Database, SHARED_NAMESPACE, ScrubState};
+use harbor_storage::StorageError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::cache::CacheManager;
+use crate::error::CoreError;
+use crate::fsck::{replace_entry, stored_digest};
+use crate::registry::RegistryService;
+
+/// Cache entries read from the database at a time
+const BATCH_SIZE: i64 = 100;
+
+/// Delay before retrying a pass that failed
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Scrubber configuration
+#[derive(Debug, Clone)]
+pub struct ScrubConfig {
+    /// Start a new pass this long after the previous one completed
+    pub interval_secs: u64,
+    /// Maximum average read rate while hashing (0 = unlimited)
+    pub bytes_per_sec: u64,
+}
+
+impl Default for ScrubConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 7 * 24 * 3600,
+            bytes_per_sec: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// Paces reads to a maximum average rate
+pub(crate) struct Throttle {
+    bytes_per_sec: u64,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub(crate) fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// Account for bytes read, sleeping while ahead of the allowed rate
+    pub(crate) async fn consume(&mut self, bytes: u64) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+        self.bytes += bytes;
+        let due = Duration::from_secs_f64(self.bytes as f64 / self.bytes_per_sec as f64);
+        if let Some(ahead) = due.checked_sub(self.started.elapsed()) {
+            tokio::time::sleep(ahead).await;
+        }
+    }
+}
+
+/// Re-verifies the digests of stored content
+pub struct Scrubber {
+    db: Database,
+    cache: Arc<CacheManager>,
+    registry: Arc<RegistryService>,
+    config: ScrubConfig,
+}
+
+impl Scrubber {
+    /// Create a new scrubber
+    pub fn new(
+        db: Database,
+        cache: Arc<CacheManager>,
+        registry: Arc<RegistryService>,
+        config: ScrubConfig,
+    ) -> Self {
+        Self {
+            db,
+            cache,
+            registry,
+            config,
+        }
+    }
+
+    /// Time until the next pass is due (zero while a pass is in progress)
+    fn next_pass_in(&self, state: &ScrubState) -> Duration {
+        if state.pass_started_at.is_some() {
+            return Duration::ZERO;
+        }
+        let Some(last_completed_at) = state.last_completed_at else {
+            return Duration::ZERO;
+        };
+        let due = last_completed_at + chrono::Duration::seconds(self.config.interval_secs as i64);
+        (due - Utc::now()).to_std().unwrap_or(Duration::ZERO)
+    }
+
+    /// Run a pass over all cache entries in namespace and digest order,
+    /// resuming the pass in progress if there is one. Progress is saved
+    /// after every batch.
+    pub async fn run_pass(&self) -> Result<ScrubState, CoreError> {
+        let mut state = self.db.get_scrub_state().await?;
+        match &state.cursor {
+            Some(cursor) if state.pass_started_at.is_some() => {
+                info!(
+                    "Resuming scrub pass after {} in namespace {}",
+                    cursor, state.cursor_namespace
+                );
+            }
+            _ => {
+                info!("Starting scrub pass");
+                state = ScrubState {
+                    pass_started_at: Some(Utc::now()),
+                    last_completed_at: state.last_completed_at,
+                    ..Default::default()
+                };
+                self.db.save_scrub_state(&state).await?;
+            }
+        }
+
+        let mut throttle = Throttle::new(self.config.bytes_per_sec);
+        for cache in self.cache.with_isolated().await {
+            // Scrubbed earlier in this pass
+            if cache.namespace() < state.cursor_namespace {
+                continue;
+            }
+            if cache.namespace() > state.cursor_namespace {
+                state.cursor_namespace = cache.namespace();
+                state.cursor = None;
+            }
+
+            loop {
+                let entries = self
+                    .db
+                    .list_cache_entries_after(
+                        cache.namespace(),
+                        state.cursor.as_deref(),
+                        BATCH_SIZE,
+                    )
+                    .await?;
+                if entries.is_empty() {
+                    break;
+                }
+
+                for entry in entries {
+                    self.scrub_entry(&cache, &entry, &mut throttle, &mut state)
+                        .await?;
+                    state.cursor = Some(entry.digest);
+                }
+                self.db.save_scrub_state(&state).await?;
+            }
+        }
+
+        state.pass_started_at = None;
+        state.cursor = None;
+        state.cursor_namespace = SHARED_NAMESPACE;
+        state.last_completed_at = Some(Utc::now());
+        self.db.save_scrub_state(&state).await?;
+
+        info!(
+            "Scrub pass completed: {} blobs ({} bytes) verified, {} corrupt",
+            state.verified_blobs, state.verified_bytes, state.corrupt_blobs
+        );
+        Ok(state)
+    }
+
+    /// Verify one entry, replacing it if its content is corrupt or gone
+    async fn scrub_entry(
+        &self,
+        cache: &CacheManager,
+        entry: &CacheEntry,
+        throttle: &mut Throttle,
+        state: &mut ScrubState,
+    ) -> Result<(), CoreError> {
+        let problem =
+            match stored_digest(cache.storage().as_ref(), &entry.digest, Some(throttle)).await {
+                Ok(actual) if actual == entry.digest => {
+                    state.verified_blobs += 1;
+                    state.verified_bytes += entry.size;
+                    metrics::counter!("harbor_cache_scrub_verified_blobs_total").increment(1);
+                    metrics::counter!("harbor_cache_scrub_verified_bytes_total")
+                        .increment(entry.size.max(0) as u64);
+                    return Ok(());
+                }
+                Ok(actual) => format!("content hashes to {}", actual),
+                Err(CoreError::Storage(StorageError::NotFound(_))) => {
+                    // Evicted since the batch was read
+                    if cache.get_metadata(&entry.digest).await?.is_none() {
+                        return Ok(());
+                    }
+                    "content is missing".to_string()
+                }
+                Err(e) => {
+                    warn!("Failed to scrub {}: {}", entry.digest, e);
+                    return Ok(());
+                }
+            };
+
+        let repair = replace_entry(cache, &self.registry, entry).await;
+        warn!(
+            "Scrub found corrupt {} {}: {} (repair: {:?})",
+            entry.entry_type.as_str(),
+            entry.digest,
+            problem,
+            repair
+        );
+        state.corrupt_blobs += 1;
+        metrics::counter!("harbor_cache_scrub_corrupt_blobs_total").increment(1);
+        Ok(())
+    }
+}
+
+/// Spawn a background task that runs scrub passes on schedule
+pub fn spawn_scrubber(scrubber: Arc<Scrubber>) -> tokio::task::JoinHandle<()> {
+    info!(
+        "Starting blob scrubber (interval: {}s, rate limit: {} bytes/s)",
+        scrubber.config.interval_secs, scrubber.config.bytes_per_sec
+    );
+
+    tokio::spawn(async move {
+        loop {
+            let wait = match scrubber.db.get_scrub_state().await {
+                Ok(state) => scrubber.next_pass_in(&state),
+                Err(e) => {
+                    warn!("Failed to load scrub state: {}", e);
+                    RETRY_DELAY
+                }
+            };
+            debug!("Next scrub pass in {}s", wait.as_secs());
+            tokio::time::sleep(wait).await;
+
+            if let Err(e) = scrubber.run_pass().await {
+                warn!("Error during scrub pass: {}", e);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use harbor_db::{CacheIsolation, EntryType, NewUpstream};
+    use harbor_proxy::{HarborClient, HarborClientConfig};
+    use harbor_storage::LocalStorage;
+    use sha2::{Digest, Sha256};
+
+    async fn scrubber(dir: &tempfile::TempDir) -> (Scrubber, Database, Arc<CacheManager>) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage: Arc<dyn harbor_storage::StorageBackend> =
+            Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+        let cache = Arc::new(CacheManager::new(
+            db.clone(),
+            storage.clone(),
+            crate::CacheConfig::default(),
+        ));
+        let client = HarborClient::new(HarborClientConfig {
+            url: "http://127.0.0.1:1".to_string(),
+            registry: "library".to_string(),
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            retry: Default::default(),
+        })
+        .unwrap();
+        let registry = Arc::new(RegistryService::new(
+            cache.clone(),
+            Arc::new(client),
+            db.clone(),
+            storage.clone(),
+        ));
+        let config = ScrubConfig {
+            bytes_per_sec: 0,
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(db.clone(), cache.clone(), registry, config);
+        (scrubber, db, cache)
+    }
+
+    async fn put(cache: &CacheManager, data: &'static [u8]) -> String {
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(data)));
+        cache
+            .put(
+                EntryType::Blob,
+                None,
+                None,
+                &digest,
+                "application/octet-stream",
+                Bytes::from_static(data),
+                None,
+            )
+            .await
+            .unwrap();
+        digest
+    }
+
+    #[tokio::test]
+    async fn test_scrub_drops_corrupt_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (scrubber, db, cache) = scrubber(&dir).await;
+
+        let intact = put(&cache, b"intact").await;
+        let corrupt = put(&cache, b"corrupt").await;
+        let hash = corrupt.strip_prefix("sha256:").unwrap();
+        let path = dir.path().join("blobs/sha256").join(&hash[..2]).join(hash);
+        std::fs::write(path, b"CORRUPT").unwrap();
+
+        let state = scrubber.run_pass().await.unwrap();
+        assert_eq!(state.verified_blobs, 1);
+        assert_eq!(state.verified_bytes, 6);
+        assert_eq!(state.corrupt_blobs, 1);
+        assert!(state.pass_started_at.is_none());
+        assert!(state.cursor.is_none());
+        assert!(state.last_completed_at.is_some());
+
+        assert!(
+            db.get_cache_entry_by_digest(&intact, SHARED_NAMESPACE)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            db.get_cache_entry_by_digest(&corrupt, SHARED_NAMESPACE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(!cache.exists(&corrupt).await.unwrap());
+
+        // The next pass is due after the interval
+        let wait = scrubber.next_pass_in(&db.get_scrub_state().await.unwrap());
+        assert!(wait > Duration::from_secs(7 * 24 * 3600 - 60));
+    }
+
+    #[tokio::test]
+    async fn test_scrub_resumes_interrupted_pass() {
+        let dir = tempfile::tempdir().unwrap();
+        let (scrubber, db, cache) = scrubber(&dir).await;
+
+        let mut digests = [
+            put(&cache, b"one").await,
+            put(&cache, b"two").await,
+            put(&cache, b"three").await,
+        ];
+        digests.sort();
+
+        // Interrupted after verifying the first digest
+        let interrupted = ScrubState {
+            pass_started_at: Some(Utc::now()),
+            cursor: Some(digests[0].clone()),
+            verified_blobs: 1,
+            ..Default::default()
+        };
+        db.save_scrub_state(&interrupted).await.unwrap();
+        assert_eq!(scrubber.next_pass_in(&interrupted), Duration::ZERO);
+
+        let state = scrubber.run_pass().await.unwrap();
+        assert_eq!(state.verified_blobs, 3);
+        assert_eq!(state.corrupt_blobs, 0);
+
+        // A new pass starts from scratch
+        let state = scrubber.run_pass().await.unwrap();
+        assert_eq!(state.verified_blobs, 3);
+    }
+
+    #[tokio::test]
+    async fn test_scrub_covers_isolated_namespaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let (scrubber, db, cache) = scrubber(&dir).await;
+        let upstream_id = db
+            .register_upstream(NewUpstream {
+                name: "team-a".to_string(),
+                display_name: "Team A".to_string(),
+                url: "https://harbor.example.com".to_string(),
+                registry: "library".to_string(),
+                username: None,
+                password: None,
+                skip_tls_verify: false,
+                priority: 100,
+                enabled: true,
+                cache_isolation: CacheIsolation::Isolated,
+                is_default: false,
+            })
+            .await
+            .unwrap();
+        let isolated = cache.isolated(upstream_id, "team-a", 1024).await.unwrap();
+
+        let shared = put(&cache, b"shared").await;
+        let corrupt = put(&isolated, b"corrupt").await;
+        let hash = corrupt.strip_prefix("sha256:").unwrap();
+        let path = dir
+            .path()
+            .join("namespaces")
+            .join(upstream_id.to_string())
+            .join("blobs/sha256")
+            .join(&hash[..2])
+            .join(hash);
+        std::fs::write(path, b"CORRUPT").unwrap();
+
+        let state = scrubber.run_pass().await.unwrap();
+        assert_eq!(state.verified_blobs, 1);
+        assert_eq!(state.corrupt_blobs, 1);
+        assert_eq!(state.cursor_namespace, SHARED_NAMESPACE);
+        assert!(cache.exists(&shared).await.unwrap());
+        assert!(!isolated.exists(&corrupt).await.unwrap());
+
+        // Interrupted in the isolated namespace: the shared one is done
+        let mut digests = [put(&isolated, b"one").await, put(&isolated, b"two").await];
+        digests.sort();
+        let interrupted = ScrubState {
+            pass_started_at: Some(Utc::now()),
+            cursor: Some(digests[0].clone()),
+            cursor_namespace: upstream_id,
+            ..Default::default()
+        };
+        db.save_scrub_state(&interrupted).await.unwrap();
+
+        let state = scrubber.run_pass().await.unwrap();
+        assert_eq!(state.verified_blobs, 1);
+        assert_eq!(state.corrupt_blobs, 0);
+    }
+}
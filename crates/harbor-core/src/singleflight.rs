@@ -0,0 +1,574 @@
+//! Coalescing of concurrent upstream fetches
+//!
+//! When many clients pull the same uncached content at once, only the first
+//! request goes upstream and the others wait for its result. Blob downloads
+//! are shared through a broadcast of the stream being cached, so a single
+//! upstream download feeds every client.
+
+use bytes::Bytes;
+use futures::StreamExt;
+use harbor_storage::StorageError;
+use harbor_storage::backend::ByteStream;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tracing::debug;
+
+use crate::cache::CacheManager;
+use crate::error::CoreError;
+use crate::range::ByteRange;
+
+/// Chunks buffered for the clients of a shared blob download. Clients that
+/// fall further behind continue from the cache once the blob is written.
+const BROADCAST_CAPACITY: usize = 1024;
+
+/// Copy an error for the requests that waited on the failed one
+fn shared_error(e: &CoreError) -> CoreError {
+    match e {
+        CoreError::NotFound(what) => CoreError::NotFound(what.clone()),
+        CoreError::RangeNotSatisfiable { size } => CoreError::RangeNotSatisfiable { size: *size },
+        e => CoreError::Coalesced(e.to_string()),
+    }
+}
+
+fn stream_error(message: impl Into<String>) -> StorageError {
+    StorageError::Io(std::io::Error::other(message.into()))
+}
+
+// ==================== Generic Calls ====================
+
+/// Result of a call, published to the requests waiting on it
+type Outcome<T> = Option<Result<T, CoreError>>;
+
+type Calls<T> = Mutex<HashMap<String, watch::Receiver<Outcome<T>>>>;
+
+/// Deduplicates concurrent calls with the same key
+pub(crate) struct SingleFlight<T> {
+    calls: Calls<T>,
+    /// Kind of content fetched, the `kind` label of the coalescing metric
+    kind: &'static str,
+}
+
+impl<T> SingleFlight<T> {
+    pub(crate) fn new(kind: &'static str) -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+            kind,
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// Run `fetch`, or wait for the result of the call with the same key
+    /// that is already in progress. If that call is cancelled before it
+    /// finishes, one of the waiting requests takes over.
+    pub(crate) async fn run<F, Fut>(&self, key: &str, fetch: F) -> Result<T, CoreError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, CoreError>>,
+    {
+        loop {
+            let (leader, mut outcome) = {
+                let mut calls = self.calls.lock().unwrap();
+                match calls.get(key) {
+                    Some(outcome) => (None, outcome.clone()),
+                    None => {
+                        let (tx, rx) = watch::channel(None);
+                        calls.insert(key.to_string(), rx.clone());
+                        (Some(tx), rx)
+                    }
+                }
+            };
+
+            let Some(tx) = leader else {
+                match outcome.wait_for(Option::is_some).await {
+                    Ok(outcome) => {
+                        metrics::counter!("harbor_cache_coalesced_fetches_total", "kind" => self.kind)
+                            .increment(1);
+                        return match outcome.as_ref() {
+                            Some(Ok(value)) => Ok(value.clone()),
+                            Some(Err(e)) => Err(shared_error(e)),
+                            None => continue,
+                        };
+                    }
+                    // The call was cancelled without a result
+                    Err(_) => continue,
+                }
+            };
+
+            let _call = CallGuard {
+                calls: &self.calls,
+                key,
+            };
+            let result = fetch().await;
+            tx.send_replace(Some(match &result {
+                Ok(value) => Ok(value.clone()),
+                Err(e) => Err(shared_error(e)),
+            }));
+            return result;
+        }
+    }
+}
+
+/// Unregisters a call when it finishes or is cancelled
+struct CallGuard<'a, T> {
+    calls: &'a Calls<T>,
+    key: &'a str,
+}
+
+impl<T> Drop for CallGuard<'_, T> {
+    fn drop(&mut self) {
+        self.calls.lock().unwrap().remove(self.key);
+    }
+}
+
+// ==================== Blob Downloads ====================
+
+/// Progress of the upstream request of a shared blob download
+enum Start {
+    Pending,
+    /// Streaming a blob of the given size
+    Started(u64),
+    Failed(CoreError),
+    /// The leading request was cancelled before the download started
+    Abandoned,
+}
+
+/// Broadcast side of a shared blob download
+struct Broadcast {
+    /// Dropped when the upstream stream ends
+    tx: Option<broadcast::Sender<Result<Bytes, String>>>,
+    /// Bytes broadcast so far
+    sent: u64,
+}
+
+/// A blob download shared by all concurrent requests for the digest
+pub(crate) struct BlobFlight {
+    /// Namespace of the cache the blob is downloaded into
+    namespace: i64,
+    digest: String,
+    start: watch::Sender<Start>,
+    chunks: Mutex<Broadcast>,
+    /// Set once the download is over and the cache write has settled
+    finished: watch::Sender<Option<Result<(), String>>>,
+}
+
+impl BlobFlight {
+    /// Subscribe to the broadcast. Returns None once chunks have been sent,
+    /// in which case the blob is read from the cache after the download.
+    pub(crate) fn subscribe(&self) -> Option<broadcast::Receiver<Result<Bytes, String>>> {
+        let chunks = self.chunks.lock().unwrap();
+        match &chunks.tx {
+            Some(tx) if chunks.sent == 0 => Some(tx.subscribe()),
+            _ => None,
+        }
+    }
+
+    /// Wait for the upstream request. Returns None if the leading request
+    /// gave up, in which case the caller should try again.
+    pub(crate) async fn started(&self) -> Option<Result<u64, CoreError>> {
+        let mut start = self.start.subscribe();
+        let start = start
+            .wait_for(|start| !matches!(start, Start::Pending))
+            .await
+            .ok()?;
+        match &*start {
+            Start::Started(size) => Some(Ok(*size)),
+            Start::Failed(e) => Some(Err(shared_error(e))),
+            Start::Pending | Start::Abandoned => None,
+        }
+    }
+
+    /// Stream the blob to one client from a subscription
+    pub(crate) fn reader(
+        self: Arc<Self>,
+        receiver: Option<broadcast::Receiver<Result<Bytes, String>>>,
+        cache: Arc<CacheManager>,
+        size: u64,
+    ) -> ByteStream {
+        let reader = BlobReader {
+            flight: self,
+            cache,
+            size,
+            delivered: 0,
+            feed: match receiver {
+                Some(rx) => Feed::Live(rx),
+                None => Feed::CatchUp,
+            },
+        };
+        Box::pin(futures::stream::unfold(reader, |mut reader| async move {
+            reader.next().await.map(|item| (item, reader))
+        }))
+    }
+
+    fn send(&self, item: Result<Bytes, String>) {
+        let mut chunks = self.chunks.lock().unwrap();
+        if let Ok(chunk) = &item {
+            chunks.sent += chunk.len() as u64;
+        }
+        if let Some(tx) = &chunks.tx {
+            // Fails only while no client is subscribed
+            let _ = tx.send(item);
+        }
+    }
+
+    fn close(&self) {
+        self.chunks.lock().unwrap().tx = None;
+    }
+}
+
+/// In-progress blob downloads by cache namespace and digest
+#[derive(Default)]
+pub(crate) struct BlobFlights {
+    flights: Mutex<HashMap<(i64, String), Arc<BlobFlight>>>,
+}
+
+impl BlobFlights {
+    /// Join the download of a digest into a cache namespace, or register a
+    /// new one. The lead is returned to the request that must start the
+    /// download.
+    pub(crate) fn join(
+        self: &Arc<Self>,
+        namespace: i64,
+        digest: &str,
+    ) -> (Arc<BlobFlight>, Option<BlobLead>) {
+        let key = (namespace, digest.to_string());
+        let mut flights = self.flights.lock().unwrap();
+        if let Some(flight) = flights.get(&key) {
+            metrics::counter!("harbor_cache_coalesced_fetches_total", "kind" => "blob")
+                .increment(1);
+            return (flight.clone(), None);
+        }
+
+        let (tx, _) = broadcast::channel(BROADCAST_CAPACITY);
+        let flight = Arc::new(BlobFlight {
+            namespace,
+            digest: digest.to_string(),
+            start: watch::Sender::new(Start::Pending),
+            chunks: Mutex::new(Broadcast {
+                tx: Some(tx),
+                sent: 0,
+            }),
+            finished: watch::Sender::new(None),
+        });
+        flights.insert(key, flight.clone());
+        let lead = BlobLead {
+            flights: self.clone(),
+            flight: flight.clone(),
+        };
+        (flight, Some(lead))
+    }
+}
+
+/// Drives a shared blob download. The download is unregistered when the
+/// lead is dropped, whether it completed or not.
+pub(crate) struct BlobLead {
+    flights: Arc<BlobFlights>,
+    flight: Arc<BlobFlight>,
+}
+
+impl BlobLead {
+    /// Report that the upstream request failed
+    pub(crate) fn fail(self, e: &CoreError) {
+        self.flight
+            .start
+            .send_replace(Start::Failed(shared_error(e)));
+    }
+
+    /// Broadcast the tee'd upstream stream in the background. `cache_write`
+    /// resolves once the blob has been written to the cache.
+    pub(crate) fn broadcast(
+        self,
+        size: u64,
+        mut stream: ByteStream,
+        cache_write: impl Future<Output = Result<(), String>> + Send + 'static,
+    ) {
+        self.flight.start.send_replace(Start::Started(size));
+        tokio::spawn(async move {
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(chunk) => self.flight.send(Ok(chunk)),
+                    Err(e) => {
+                        self.flight.send(Err(e.to_string()));
+                        break;
+                    }
+                }
+            }
+            self.flight.close();
+
+            let outcome = cache_write.await;
+            self.flight.finished.send_replace(Some(outcome));
+        });
+    }
+}
+
+impl Drop for BlobLead {
+    fn drop(&mut self) {
+        let flight = &self.flight;
+        flight.start.send_if_modified(|start| {
+            let pending = matches!(start, Start::Pending);
+            if pending {
+                *start = Start::Abandoned;
+            }
+            pending
+        });
+        flight.close();
+        flight.finished.send_if_modified(|finished| {
+            let unset = finished.is_none();
+            if unset {
+                *finished = Some(Err("Shared download was abandoned".to_string()));
+            }
+            unset
+        });
+        self.flights
+            .flights
+            .lock()
+            .unwrap()
+            .remove(&(flight.namespace, flight.digest.clone()));
+    }
+}
+
+/// Where a client reads a shared blob download from
+enum Feed {
+    /// Chunks as they are broadcast
+    Live(broadcast::Receiver<Result<Bytes, String>>),
+    /// Missed chunks: wait for the cache to serve the rest
+    CatchUp,
+    /// The rest of the blob from the cache
+    Cached(ByteStream),
+    Done,
+}
+
+/// One client's view of a shared blob download
+struct BlobReader {
+    flight: Arc<BlobFlight>,
+    cache: Arc<CacheManager>,
+    size: u64,
+    /// Bytes passed to the client so far
+    delivered: u64,
+    feed: Feed,
+}
+
+impl BlobReader {
+    async fn next(&mut self) -> Option<Result<Bytes, StorageError>> {
+        loop {
+            match &mut self.feed {
+                Feed::Live(rx) => match rx.recv().await {
+                    Ok(Ok(chunk)) => {
+                        self.delivered += chunk.len() as u64;
+                        return Some(Ok(chunk));
+                    }
+                    Ok(Err(e)) => {
+                        self.feed = Feed::Done;
+                        return Some(Err(stream_error(e)));
+                    }
+                    Err(RecvError::Lagged(_)) => {
+                        debug!(
+                            "Client fell behind the download of {}, continuing from cache",
+                            self.flight.digest
+                        );
+                        self.feed = Feed::CatchUp;
+                    }
+                    Err(RecvError::Closed) => self.feed = Feed::CatchUp,
+                },
+                Feed::CatchUp => {
+                    if self.delivered >= self.size {
+                        self.feed = Feed::Done;
+                        continue;
+                    }
+                    match resume(&self.flight, &self.cache, self.delivered).await {
+                        Ok(stream) => self.feed = Feed::Cached(stream),
+                        Err(e) => {
+                            self.feed = Feed::Done;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                Feed::Cached(stream) => {
+                    let item = stream.next().await;
+                    if item.is_none() {
+                        self.feed = Feed::Done;
+                    }
+                    return item;
+                }
+                Feed::Done => return None,
+            }
+        }
+    }
+}
+
+/// Open the cached blob after the bytes already delivered, once the
+/// download has been written to the cache
+async fn resume(
+    flight: &BlobFlight,
+    cache: &CacheManager,
+    delivered: u64,
+) -> Result<ByteStream, StorageError> {
+    let mut finished = flight.finished.subscribe();
+    let outcome = finished
+        .wait_for(Option::is_some)
+        .await
+        .map_err(|_| stream_error("Shared download was abandoned"))?
+        .clone();
+    if let Some(Err(e)) = outcome {
+        return Err(stream_error(e));
+    }
+
+    let range = ByteRange::FromTo {
+        start: delivered,
+        end: None,
+    };
+    match cache.get_range_stream(&flight.digest, &range).await {
+        Ok(Some((stream, _))) => Ok(stream),
+        Ok(None) => Err(stream_error(format!(
+            "{} is no longer cached",
+            flight.digest
+        ))),
+        Err(e) => Err(stream_error(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harbor_db::{Database, EntryType};
+    use harbor_storage::LocalStorage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_are_coalesced() {
+        let flights = Arc::new(SingleFlight::<u32>::new("test"));
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let flights = flights.clone();
+                let fetches = fetches.clone();
+                tokio::spawn(async move {
+                    flights
+                        .run("library/app:latest", || async {
+                            fetches.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(42)
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), 42);
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Errors are shared, finished calls are not
+        let result = flights
+            .run("library/app:latest", || async {
+                Err(CoreError::NotFound("library/app:latest".to_string()))
+            })
+            .await;
+        assert!(matches!(result, Err(CoreError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_call_is_taken_over() {
+        let flights = Arc::new(SingleFlight::<u32>::new("test"));
+
+        let leader = {
+            let flights = flights.clone();
+            tokio::spawn(async move {
+                flights
+                    .run("key", std::future::pending::<Result<u32, CoreError>>)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let follower = {
+            let flights = flights.clone();
+            tokio::spawn(async move { flights.run("key", || async { Ok(7) }).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+
+        assert_eq!(follower.await.unwrap().unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_blob_download_is_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let storage = Arc::new(LocalStorage::new(dir.path()).await.unwrap());
+        let cache = Arc::new(CacheManager::new(
+            db,
+            storage,
+            crate::CacheConfig::default(),
+        ));
+
+        let data = Bytes::from_static(b"shared layer content");
+        let digest = harbor_storage::backend::compute_sha256(&data);
+        let flights = Arc::new(BlobFlights::default());
+
+        let (flight, lead) = flights.join(cache.namespace(), &digest);
+        let lead = lead.unwrap();
+        let (joined, second_lead) = flights.join(cache.namespace(), &digest);
+        assert!(second_lead.is_none());
+        // Another cache namespace downloads the blob separately
+        assert!(flights.join(cache.namespace() + 1, &digest).1.is_some());
+        let first = flight.subscribe();
+        let second = joined.subscribe();
+
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        let stream: ByteStream = Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx));
+        let (written_tx, written_rx) = tokio::sync::oneshot::channel::<()>();
+        lead.broadcast(data.len() as u64, stream, async move {
+            written_rx.await.map_err(|e| e.to_string())
+        });
+        assert_eq!(joined.started().await.unwrap().unwrap(), data.len() as u64);
+
+        let read = |flight: &Arc<BlobFlight>, receiver| {
+            let stream = flight
+                .clone()
+                .reader(receiver, cache.clone(), data.len() as u64);
+            tokio::spawn(async move {
+                let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+                chunks.concat()
+            })
+        };
+        let first = read(&flight, first);
+        let second = read(&joined, second);
+
+        tx.send(Ok(data.slice(..6))).await.unwrap();
+        // Joins after the first chunk and has to wait for the cache
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let late = flight.subscribe();
+        assert!(late.is_none());
+        let late = read(&flight, late);
+
+        tx.send(Ok(data.slice(6..))).await.unwrap();
+        drop(tx);
+        assert_eq!(first.await.unwrap(), data);
+        assert_eq!(second.await.unwrap(), data);
+
+        cache
+            .put(
+                EntryType::Blob,
+                None,
+                None,
+                &digest,
+                "application/octet-stream",
+                data.clone(),
+                None,
+            )
+            .await
+            .unwrap();
+        written_tx.send(()).unwrap();
+        assert_eq!(late.await.unwrap(), data);
+
+        // The download is unregistered once it is over
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(flights.join(cache.namespace(), &digest).1.is_some());
+    }
+}
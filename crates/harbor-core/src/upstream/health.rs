@@ -0,0 +1,199 @@
+//! Upstream health probing and circuit breaking
+//!
+//! Every upstream has a circuit breaker fed by both proxied traffic and a
+//! background prober that pings each upstream on a fixed interval:
+//!
+//! - **Closed**: the upstream serves requests. After `failure_threshold`
+//!   consecutive failures the circuit opens.
+//! - **Open**: the upstream is skipped by routing and only tried as a last
+//!   resort in its failover group. Once `open_secs` have passed, the next
+//!   success moves it to half-open.
+//! - **Half-open**: the upstream serves requests again on trial. After
+//!   `success_threshold` consecutive successes the circuit closes; any
+//!   failure opens it again.
+//!
+//! State transitions are logged to `activity_logs` and exported to Prometheus.
+
+use chrono::{DateTime, Utc};
+use harbor_db::{Database, NewActivityLog};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use super::manager::UpstreamManager;
+
+/// Health probing and circuit breaker configuration
+#[derive(Debug, Clone)]
+pub struct HealthCheckConfig {
+    /// Seconds between probes of each upstream
+    pub interval_secs: u64,
+    /// Seconds after which a probe counts as failed
+    pub timeout_secs: u64,
+    /// Consecutive failures that open a closed circuit
+    pub failure_threshold: u32,
+    /// Consecutive successes that close a half-open circuit
+    pub success_threshold: u32,
+    /// Seconds an open circuit waits before a success may half-open it
+    pub open_secs: u64,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            timeout_secs: 5,
+            failure_threshold: 3,
+            success_threshold: 2,
+            open_secs: 60,
+        }
+    }
+}
+
+/// Circuit breaker state of an upstream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Serving requests
+    #[default]
+    Closed,
+    /// Skipped after repeated failures
+    Open,
+    /// Serving requests on trial after the open period
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+
+    /// Value of the `harbor_cache_upstream_circuit_state` gauge
+    pub(crate) fn gauge_value(&self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A change of an upstream's circuit state
+#[derive(Debug, Clone)]
+pub struct CircuitTransition {
+    pub upstream: String,
+    pub from: CircuitState,
+    pub to: CircuitState,
+    /// The error that caused the transition, if any
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+impl CircuitTransition {
+    /// Activity log entry recording the transition
+    fn activity_log(&self) -> NewActivityLog {
+        let mut details = format!("Circuit {} -> {}", self.from, self.to);
+        if let Some(error) = &self.error {
+            details.push_str(": ");
+            details.push_str(error);
+        }
+        NewActivityLog {
+            action: format!("circuit_{}", self.to),
+            resource_type: "upstream".to_string(),
+            resource_id: Some(self.upstream.clone()),
+            user_id: None,
+            username: None,
+            details: Some(details),
+            ip_address: None,
+        }
+    }
+}
+
+/// Spawn a background task that probes every upstream on the configured
+/// interval
+pub fn spawn_health_prober(manager: Arc<UpstreamManager>) -> tokio::task::JoinHandle<()> {
+    let interval = Duration::from_secs(manager.health_check_config().interval_secs.max(1));
+    info!(
+        "Starting upstream health prober (interval: {}s, timeout: {}s)",
+        interval.as_secs(),
+        manager.health_check_config().timeout_secs
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let results = manager.check_all_health().await;
+            debug!(
+                "Probed {} upstreams ({} healthy)",
+                results.len(),
+                results.iter().filter(|h| h.healthy).count()
+            );
+        }
+    })
+}
+
+/// Spawn a background task that records circuit state transitions in the
+/// activity log
+pub fn spawn_circuit_logger(
+    manager: &UpstreamManager,
+    db: Database,
+) -> tokio::task::JoinHandle<()> {
+    let mut transitions = manager.subscribe_transitions();
+
+    tokio::spawn(async move {
+        loop {
+            match transitions.recv().await {
+                Ok(transition) => {
+                    if let Err(e) = db.insert_activity_log(transition.activity_log()).await {
+                        warn!(
+                            "Failed to record circuit transition of upstream {}: {}",
+                            transition.upstream, e
+                        );
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Skipped logging {} circuit transitions", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition_activity_log() {
+        let transition = CircuitTransition {
+            upstream: "main".to_string(),
+            from: CircuitState::Closed,
+            to: CircuitState::Open,
+            error: Some("connection refused".to_string()),
+            at: Utc::now(),
+        };
+
+        let log = transition.activity_log();
+        assert_eq!(log.action, "circuit_open");
+        assert_eq!(log.resource_type, "upstream");
+        assert_eq!(log.resource_id.as_deref(), Some("main"));
+        assert_eq!(
+            log.details.as_deref(),
+            Some("Circuit closed -> open: connection refused")
+        );
+    }
+}
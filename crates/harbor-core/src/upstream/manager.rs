@@ -3,22 +3,28 @@
 //! The UpstreamManager is responsible for:
 //! - Creating and managing HarborClient instances for each upstream
 //! - Route-based upstream selection
-//! - Health monitoring
+//! - Health monitoring and circuit breaking
 //! - Dynamic upstream configuration updates
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use harbor_proxy::{HarborClient, HarborClientConfig};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
+use super::health::{CircuitState, CircuitTransition, HealthCheckConfig};
 use super::router::RouteMatcher;
 use crate::config::{UpstreamConfig, UpstreamConfigProvider, UpstreamRouteConfig};
 use crate::error::CoreError;
 
+/// Circuit transitions buffered for subscribers that fall behind
+const TRANSITION_CHANNEL_CAPACITY: usize = 64;
+
 /// Health status for an upstream
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpstreamHealth {
@@ -28,6 +34,82 @@ pub struct UpstreamHealth {
     pub last_check: DateTime<Utc>,
     pub last_error: Option<String>,
     pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    pub circuit_state: CircuitState,
+    /// When the circuit last opened
+    pub opened_at: Option<DateTime<Utc>>,
+}
+
+impl UpstreamHealth {
+    fn new(config: &UpstreamConfig) -> Self {
+        Self {
+            upstream_name: config.name.clone(),
+            name: config.display_name().to_string(),
+            healthy: true, // Assume healthy until proven otherwise
+            last_check: Utc::now(),
+            last_error: None,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            circuit_state: CircuitState::Closed,
+            opened_at: None,
+        }
+    }
+
+    /// Apply the outcome of a request or probe to the circuit breaker.
+    /// Returns the previous state if the circuit changed state.
+    fn record(
+        &mut self,
+        outcome: Result<(), &str>,
+        config: &HealthCheckConfig,
+    ) -> Option<CircuitState> {
+        let now = Utc::now();
+        let previous = self.circuit_state;
+        self.last_check = now;
+
+        match outcome {
+            Ok(()) => {
+                self.consecutive_failures = 0;
+                self.consecutive_successes += 1;
+
+                if self.circuit_state == CircuitState::Open {
+                    let open_for = self.opened_at.map(|at| now - at).unwrap_or_default();
+                    if open_for < chrono::Duration::seconds(config.open_secs as i64) {
+                        // Still cooling down: the success does not count yet
+                        self.consecutive_successes = 0;
+                        return None;
+                    }
+                    self.circuit_state = CircuitState::HalfOpen;
+                    self.consecutive_successes = 1;
+                }
+                if self.circuit_state == CircuitState::HalfOpen
+                    && self.consecutive_successes >= config.success_threshold
+                {
+                    self.circuit_state = CircuitState::Closed;
+                    self.opened_at = None;
+                }
+                self.healthy = true;
+                self.last_error = None;
+            }
+            Err(error) => {
+                self.healthy = false;
+                self.last_error = Some(error.to_string());
+                self.consecutive_failures += 1;
+                self.consecutive_successes = 0;
+
+                let trips = match self.circuit_state {
+                    CircuitState::Closed => self.consecutive_failures >= config.failure_threshold,
+                    CircuitState::HalfOpen => true,
+                    CircuitState::Open => false,
+                };
+                if trips {
+                    self.circuit_state = CircuitState::Open;
+                    self.opened_at = Some(now);
+                }
+            }
+        }
+
+        (self.circuit_state != previous).then_some(previous)
+    }
 }
 
 /// Information about a resolved upstream
@@ -43,6 +125,24 @@ pub struct UpstreamInfo {
     pub project: String,
 }
 
+impl UpstreamInfo {
+    /// Tag freshness TTL in seconds for this selection.
+    /// A matched route's TTL takes precedence over the upstream-level TTL.
+    pub fn tag_ttl_secs(&self) -> Option<u64> {
+        if let MatchReason::RouteMatch { pattern, .. } = &self.match_reason
+            && let Some(ttl) = self
+                .config
+                .routes
+                .iter()
+                .find(|r| &r.pattern == pattern)
+                .and_then(|r| r.tag_ttl_secs)
+        {
+            return Some(ttl);
+        }
+        self.config.tag_ttl_secs
+    }
+}
+
 impl std::fmt::Debug for UpstreamInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("UpstreamInfo")
@@ -68,6 +168,8 @@ pub enum MatchReason {
     DefaultFallback,
     /// Explicitly specified by name
     ExplicitName(String),
+    /// Listed as a mirror of the named upstream
+    Mirror(String),
 }
 
 /// Internal state for each upstream
@@ -81,6 +183,13 @@ struct UpstreamState {
     health: UpstreamHealth,
 }
 
+impl UpstreamState {
+    /// Whether the upstream's circuit allows new requests
+    fn is_available(&self) -> bool {
+        self.health.circuit_state != CircuitState::Open
+    }
+}
+
 /// Manages multiple upstream Harbor registries
 pub struct UpstreamManager {
     config_provider: Arc<dyn UpstreamConfigProvider>,
@@ -90,16 +199,30 @@ pub struct UpstreamManager {
     route_matcher: RwLock<RouteMatcher>,
     /// Default upstream name (if any)
     default_upstream_name: RwLock<Option<String>>,
+    /// Health probing and circuit breaker settings
+    health_config: HealthCheckConfig,
+    /// Circuit state changes, for the activity log
+    transitions: broadcast::Sender<CircuitTransition>,
 }
 
 impl UpstreamManager {
     /// Create a new UpstreamManager with a config provider
     pub fn new(config_provider: Arc<dyn UpstreamConfigProvider>) -> Result<Self, CoreError> {
+        Self::with_health_check(config_provider, HealthCheckConfig::default())
+    }
+
+    /// Create a new UpstreamManager with custom circuit breaker settings
+    pub fn with_health_check(
+        config_provider: Arc<dyn UpstreamConfigProvider>,
+        health_config: HealthCheckConfig,
+    ) -> Result<Self, CoreError> {
         let manager = Self {
             config_provider,
             upstreams: RwLock::new(HashMap::new()),
             route_matcher: RwLock::new(RouteMatcher::new(vec![])),
             default_upstream_name: RwLock::new(None),
+            health_config,
+            transitions: broadcast::channel(TRANSITION_CHANNEL_CAPACITY).0,
         };
 
         // Load initial configuration
@@ -138,14 +261,7 @@ impl UpstreamManager {
             let default_project = upstream_config.get_default_project().to_string();
             match Self::create_client_for_project(&upstream_config, &default_project) {
                 Ok(default_client) => {
-                    let health = UpstreamHealth {
-                        upstream_name: upstream_config.name.clone(),
-                        name: upstream_config.display_name().to_string(),
-                        healthy: true, // Assume healthy until proven otherwise
-                        last_check: Utc::now(),
-                        last_error: None,
-                        consecutive_failures: 0,
-                    };
+                    let health = UpstreamHealth::new(&upstream_config);
 
                     if upstream_config.is_default {
                         default_name = Some(upstream_config.name.clone());
@@ -221,9 +337,19 @@ impl UpstreamManager {
             })
             .collect();
 
-        // Update state
+        // Update state, keeping the circuit state of upstreams that remain
         {
             let mut upstreams_guard = self.upstreams.write();
+            for (name, state) in new_upstreams.iter_mut() {
+                if let Some(existing) = upstreams_guard.get(name) {
+                    state.health = UpstreamHealth {
+                        name: state.health.name.clone(),
+                        ..existing.health.clone()
+                    };
+                }
+                metrics::gauge!("harbor_cache_upstream_circuit_state", "upstream" => name.clone())
+                    .set(state.health.circuit_state.gauge_value());
+            }
             *upstreams_guard = new_upstreams;
         }
 
@@ -252,6 +378,7 @@ impl UpstreamManager {
             username: config.username.clone(),
             password: config.password.clone(),
             skip_tls_verify: config.skip_tls_verify,
+            retry: config.retry.policy(),
         };
 
         HarborClient::new(client_config).map_err(CoreError::Proxy)
@@ -266,6 +393,12 @@ impl UpstreamManager {
 
     /// Find the appropriate upstream for a repository path
     pub fn find_upstream(&self, repository: &str) -> Option<UpstreamInfo> {
+        self.match_upstream(repository, true)
+    }
+
+    /// Select the upstream for a repository path by route, project and
+    /// default, skipping upstreams whose circuit is open if `skip_open`
+    fn match_upstream(&self, repository: &str, skip_open: bool) -> Option<UpstreamInfo> {
         let upstreams = self.upstreams.read();
 
         // First, try route matching (upstream-level routes)
@@ -280,7 +413,7 @@ impl UpstreamManager {
                         .routes
                         .iter()
                         .any(|r| r.pattern == route_match.pattern)
-                        && (state.health.healthy || state.health.consecutive_failures < 3)
+                        && (!skip_open || state.is_available())
                 })
                 .collect();
 
@@ -311,7 +444,7 @@ impl UpstreamManager {
         // Sort upstreams by priority, then by project priority
         let mut upstream_matches: Vec<_> = upstreams
             .values()
-            .filter(|state| state.health.healthy || state.health.consecutive_failures < 3)
+            .filter(|state| !skip_open || state.is_available())
             .filter_map(|state| {
                 if state.config.uses_multi_project() {
                     // Find matching project for this upstream
@@ -369,7 +502,7 @@ impl UpstreamManager {
         // If no default, try first available healthy upstream (sorted for determinism)
         let mut available: Vec<_> = upstreams
             .values()
-            .filter(|state| state.health.healthy || state.health.consecutive_failures < 3)
+            .filter(|state| !skip_open || state.is_available())
             .collect();
 
         // Sort by priority, then name for deterministic fallback behavior
@@ -393,6 +526,62 @@ impl UpstreamManager {
         None
     }
 
+    /// Find the failover group for a repository path: the upstream the
+    /// repository routes to followed by its mirrors, in configured order.
+    /// Members whose circuit is open are moved to the end so they are only
+    /// tried once every other member has failed.
+    ///
+    /// An upstream with mirrors heads the group even while its circuit is
+    /// open, so its repositories fail over to its mirrors rather than to an
+    /// unrelated upstream. Without mirrors the selection of
+    /// [`find_upstream`](Self::find_upstream) applies.
+    pub fn find_upstreams(&self, repository: &str) -> Vec<UpstreamInfo> {
+        let primary = match self.match_upstream(repository, false) {
+            Some(routed) if !routed.config.mirrors.is_empty() => routed,
+            _ => match self.find_upstream(repository) {
+                Some(primary) => primary,
+                None => return Vec::new(),
+            },
+        };
+
+        let upstreams = self.upstreams.read();
+        let mut available = Vec::new();
+        let mut open = Vec::new();
+        let primary_open = upstreams
+            .get(&primary.config.name)
+            .is_some_and(|state| !state.is_available());
+
+        for name in &primary.config.mirrors {
+            let Some(state) = upstreams.get(name) else {
+                debug!(
+                    "Mirror {} of upstream {} is not loaded, skipping",
+                    name, primary.config.name
+                );
+                continue;
+            };
+            let (client, project) = self.get_client_and_project(state, repository);
+            let info = UpstreamInfo {
+                config: state.config.clone(),
+                client,
+                match_reason: MatchReason::Mirror(primary.config.name.clone()),
+                project,
+            };
+            if state.is_available() {
+                available.push(info);
+            } else {
+                open.push(info);
+            }
+        }
+
+        if primary_open {
+            open.insert(0, primary);
+        } else {
+            available.insert(0, primary);
+        }
+        available.extend(open);
+        available
+    }
+
     /// Get the appropriate client and project for a given upstream state and repository
     fn get_client_and_project(
         &self,
@@ -497,6 +686,23 @@ impl UpstreamManager {
         upstreams.values().map(|s| s.config.clone()).collect()
     }
 
+    /// List the clients of all upstreams, including per-project clients
+    pub fn list_clients(&self) -> Vec<Arc<HarborClient>> {
+        let upstreams = self.upstreams.read();
+        upstreams
+            .values()
+            .flat_map(|s| {
+                let default_project = s.config.get_default_project();
+                std::iter::once(s.default_client.clone()).chain(
+                    s.project_clients
+                        .iter()
+                        .filter(move |(project, _)| project.as_str() != default_project)
+                        .map(|(_, client)| client.clone()),
+                )
+            })
+            .collect()
+    }
+
     /// Get health status for all upstreams
     pub fn get_health_status(&self) -> Vec<UpstreamHealth> {
         let upstreams = self.upstreams.read();
@@ -518,31 +724,19 @@ impl UpstreamManager {
 
         let client = client.ok_or_else(|| CoreError::NotFound(format!("Upstream {}", name)))?;
 
-        let (healthy, error) = match client.ping().await {
-            Ok(true) => (true, None),
-            Ok(false) => (false, Some("Ping returned false".to_string())),
-            Err(e) => (false, Some(e.to_string())),
+        let timeout = Duration::from_secs(self.health_config.timeout_secs);
+        let error = match tokio::time::timeout(timeout, client.ping()).await {
+            Ok(Ok(true)) => None,
+            Ok(Ok(false)) => Some("Ping returned false".to_string()),
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some(format!(
+                "Health check timed out after {}s",
+                timeout.as_secs()
+            )),
         };
 
-        let now = Utc::now();
-
-        // Update health status
-        let mut upstreams = self.upstreams.write();
-        if let Some(state) = upstreams.get_mut(name) {
-            state.health.healthy = healthy;
-            state.health.last_check = now;
-            state.health.last_error = error.clone();
-
-            if healthy {
-                state.health.consecutive_failures = 0;
-            } else {
-                state.health.consecutive_failures += 1;
-            }
-
-            Ok(state.health.clone())
-        } else {
-            Err(CoreError::NotFound(format!("Upstream {}", name)))
-        }
+        self.record_outcome(name, error.as_deref().map_or(Ok(()), Err))
+            .ok_or_else(|| CoreError::NotFound(format!("Upstream {}", name)))
     }
 
     /// Check health of all upstreams
@@ -552,9 +746,16 @@ impl UpstreamManager {
             upstreams.keys().cloned().collect()
         };
 
+        // Probe concurrently so one slow upstream does not delay the others
+        let checks = upstream_names
+            .iter()
+            .map(|name| self.check_upstream_health(name));
         let mut results = Vec::new();
-        for name in upstream_names {
-            match self.check_upstream_health(&name).await {
+        for (name, result) in upstream_names
+            .iter()
+            .zip(futures::future::join_all(checks).await)
+        {
+            match result {
                 Ok(health) => results.push(health),
                 Err(e) => {
                     warn!("Failed to check health for upstream {}: {}", name, e);
@@ -567,29 +768,67 @@ impl UpstreamManager {
 
     /// Mark an upstream as unhealthy after a failure
     pub fn mark_unhealthy(&self, name: &str, error: &str) {
-        let mut upstreams = self.upstreams.write();
-        if let Some(state) = upstreams.get_mut(name) {
-            state.health.healthy = false;
-            state.health.last_error = Some(error.to_string());
-            state.health.consecutive_failures += 1;
+        if let Some(health) = self.record_outcome(name, Err(error)) {
             debug!(
                 "Marked upstream {} as unhealthy: {} (failures: {})",
-                name, error, state.health.consecutive_failures
+                name, error, health.consecutive_failures
             );
         }
     }
 
     /// Mark an upstream as healthy after a successful operation
     pub fn mark_healthy(&self, name: &str) {
+        self.record_outcome(name, Ok(()));
+    }
+
+    /// Feed the outcome of a request or probe to an upstream's circuit
+    /// breaker, publishing any state transition. Returns the updated health,
+    /// or None if the upstream is unknown.
+    fn record_outcome(&self, name: &str, outcome: Result<(), &str>) -> Option<UpstreamHealth> {
         let mut upstreams = self.upstreams.write();
-        if let Some(state) = upstreams.get_mut(name) {
-            if !state.health.healthy {
-                info!("Upstream {} recovered", name);
+        let state = upstreams.get_mut(name)?;
+        let was_healthy = state.health.healthy;
+
+        if let Some(from) = state.health.record(outcome, &self.health_config) {
+            let to = state.health.circuit_state;
+            match to {
+                CircuitState::Open => warn!(
+                    "Circuit for upstream {} opened after {} consecutive failures",
+                    name, state.health.consecutive_failures
+                ),
+                _ => info!("Circuit for upstream {} is now {}", name, to),
             }
-            state.health.healthy = true;
-            state.health.last_error = None;
-            state.health.consecutive_failures = 0;
+            metrics::counter!(
+                "harbor_cache_upstream_circuit_transitions_total",
+                "upstream" => name.to_string(),
+                "state" => to.as_str()
+            )
+            .increment(1);
+            metrics::gauge!("harbor_cache_upstream_circuit_state", "upstream" => name.to_string())
+                .set(to.gauge_value());
+            // No receiver is fine: transitions are only logged when subscribed
+            let _ = self.transitions.send(CircuitTransition {
+                upstream: name.to_string(),
+                from,
+                to,
+                error: outcome.err().map(str::to_string),
+                at: Utc::now(),
+            });
+        } else if !was_healthy && outcome.is_ok() {
+            info!("Upstream {} recovered", name);
         }
+
+        Some(state.health.clone())
+    }
+
+    /// Subscribe to circuit state transitions of all upstreams
+    pub fn subscribe_transitions(&self) -> broadcast::Receiver<CircuitTransition> {
+        self.transitions.subscribe()
+    }
+
+    /// Get the health probing and circuit breaker settings
+    pub fn health_check_config(&self) -> &HealthCheckConfig {
+        &self.health_config
     }
 
     /// Get the number of configured upstreams
@@ -620,3 +859,216 @@ impl UpstreamManager {
         &self.config_provider
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::InMemoryConfigProvider;
+
+    fn upstream(name: &str, routes: &[&str], mirrors: &[&str]) -> UpstreamConfig {
+        UpstreamConfig {
+            name: name.to_string(),
+            display_name: None,
+            url: format!("http://{}.example.com", name),
+            registry: "library".to_string(),
+            projects: vec![],
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            priority: 100,
+            enabled: true,
+            cache_isolation: "shared".to_string(),
+            cache_max_size: None,
+            is_default: false,
+            routes: routes
+                .iter()
+                .map(|pattern| UpstreamRouteConfig {
+                    pattern: pattern.to_string(),
+                    priority: 100,
+                    tag_ttl_secs: None,
+                })
+                .collect(),
+            tag_ttl_secs: None,
+            mirrors: mirrors.iter().map(|m| m.to_string()).collect(),
+            retry: Default::default(),
+        }
+    }
+
+    fn group_names(manager: &UpstreamManager, repository: &str) -> Vec<String> {
+        manager
+            .find_upstreams(repository)
+            .into_iter()
+            .map(|info| info.config.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_find_upstreams_orders_group_by_circuit_state() {
+        let mut primary = upstream("main", &["library/*"], &["dr", "edge", "missing"]);
+        primary.is_default = true;
+        let provider = InMemoryConfigProvider::new(vec![
+            primary,
+            upstream("dr", &[], &[]),
+            upstream("edge", &[], &[]),
+        ]);
+        let config = HealthCheckConfig {
+            success_threshold: 1,
+            open_secs: 0,
+            ..HealthCheckConfig::default()
+        };
+        let manager = UpstreamManager::with_health_check(Arc::new(provider), config).unwrap();
+
+        // Unknown mirrors are skipped
+        assert_eq!(
+            group_names(&manager, "library/nginx"),
+            ["main", "dr", "edge"]
+        );
+
+        // A member with an open circuit is only tried last
+        for _ in 0..3 {
+            manager.mark_unhealthy("dr", "connection refused");
+        }
+        assert_eq!(
+            group_names(&manager, "library/nginx"),
+            ["main", "edge", "dr"]
+        );
+
+        // Failures below the threshold keep the circuit closed
+        manager.mark_unhealthy("main", "timeout");
+        assert_eq!(
+            group_names(&manager, "library/nginx"),
+            ["main", "edge", "dr"]
+        );
+
+        for _ in 1..3 {
+            manager.mark_unhealthy("main", "timeout");
+        }
+        assert_eq!(
+            group_names(&manager, "library/nginx"),
+            ["edge", "main", "dr"]
+        );
+
+        // Once the open period has passed, a success closes the circuit again
+        manager.mark_healthy("main");
+        assert_eq!(
+            group_names(&manager, "library/nginx"),
+            ["main", "edge", "dr"]
+        );
+    }
+
+    #[test]
+    fn test_open_routed_upstream_fails_over_to_its_mirrors() {
+        let mut fallback = upstream("fallback", &[], &[]);
+        fallback.is_default = true;
+        let provider = InMemoryConfigProvider::new(vec![
+            upstream("main", &["team-a/*"], &["dr"]),
+            upstream("dr", &[], &[]),
+            fallback,
+        ]);
+        let manager = UpstreamManager::new(Arc::new(provider)).unwrap();
+        assert_eq!(group_names(&manager, "team-a/app"), ["main", "dr"]);
+
+        // The open primary keeps its group instead of routing to the default
+        for _ in 0..3 {
+            manager.mark_unhealthy("main", "connection refused");
+        }
+        assert_eq!(group_names(&manager, "team-a/app"), ["dr", "main"]);
+        assert_eq!(group_names(&manager, "library/nginx"), ["fallback"]);
+    }
+
+    #[test]
+    fn test_find_upstreams_without_mirrors() {
+        let provider = InMemoryConfigProvider::new(vec![upstream("main", &["library/*"], &[])]);
+        let manager = UpstreamManager::new(Arc::new(provider)).unwrap();
+
+        assert_eq!(group_names(&manager, "library/nginx"), ["main"]);
+    }
+
+    #[test]
+    fn test_circuit_breaker_state_machine() {
+        let config = HealthCheckConfig {
+            failure_threshold: 2,
+            success_threshold: 2,
+            open_secs: 60,
+            ..HealthCheckConfig::default()
+        };
+        let mut health = UpstreamHealth::new(&upstream("main", &[], &[]));
+
+        assert_eq!(health.record(Err("timeout"), &config), None);
+        assert_eq!(
+            health.record(Err("timeout"), &config),
+            Some(CircuitState::Closed)
+        );
+        assert_eq!(health.circuit_state, CircuitState::Open);
+
+        // Successes during the open period are ignored
+        assert_eq!(health.record(Ok(()), &config), None);
+        assert_eq!(health.circuit_state, CircuitState::Open);
+
+        // After the open period the circuit half-opens, and a failure reopens it
+        health.opened_at = Some(Utc::now() - chrono::Duration::seconds(61));
+        assert_eq!(health.record(Ok(()), &config), Some(CircuitState::Open));
+        assert_eq!(health.circuit_state, CircuitState::HalfOpen);
+        assert_eq!(
+            health.record(Err("503"), &config),
+            Some(CircuitState::HalfOpen)
+        );
+        assert_eq!(health.circuit_state, CircuitState::Open);
+
+        // Enough consecutive successes in half-open close it
+        health.opened_at = Some(Utc::now() - chrono::Duration::seconds(61));
+        health.record(Ok(()), &config);
+        assert_eq!(health.circuit_state, CircuitState::HalfOpen);
+        assert_eq!(health.record(Ok(()), &config), Some(CircuitState::HalfOpen));
+        assert_eq!(health.circuit_state, CircuitState::Closed);
+        assert_eq!(health.opened_at, None);
+    }
+
+    #[test]
+    fn test_success_during_cooldown_keeps_upstream_unhealthy() {
+        let config = HealthCheckConfig {
+            failure_threshold: 1,
+            open_secs: 60,
+            ..HealthCheckConfig::default()
+        };
+        let mut health = UpstreamHealth::new(&upstream("main", &[], &[]));
+
+        health.record(Err("timeout"), &config);
+        assert_eq!(health.circuit_state, CircuitState::Open);
+
+        assert_eq!(health.record(Ok(()), &config), None);
+        assert_eq!(health.circuit_state, CircuitState::Open);
+        assert!(!health.healthy);
+        assert_eq!(health.last_error.as_deref(), Some("timeout"));
+
+        // Once the circuit half-opens the success counts
+        health.opened_at = Some(Utc::now() - chrono::Duration::seconds(61));
+        health.record(Ok(()), &config);
+        assert_eq!(health.circuit_state, CircuitState::HalfOpen);
+        assert!(health.healthy);
+        assert_eq!(health.last_error, None);
+    }
+
+    #[test]
+    fn test_circuit_transitions_are_published() {
+        let provider = InMemoryConfigProvider::new(vec![upstream("main", &["library/*"], &[])]);
+        let manager = UpstreamManager::new(Arc::new(provider)).unwrap();
+        let mut transitions = manager.subscribe_transitions();
+
+        for _ in 0..3 {
+            manager.mark_unhealthy("main", "connection refused");
+        }
+
+        let transition = transitions.try_recv().unwrap();
+        assert_eq!(transition.upstream, "main");
+        assert_eq!(transition.from, CircuitState::Closed);
+        assert_eq!(transition.to, CircuitState::Open);
+        assert_eq!(transition.error.as_deref(), Some("connection refused"));
+        assert!(transitions.try_recv().is_err());
+
+        // The circuit state survives a reload
+        manager.reload().unwrap();
+        let health = manager.get_upstream_health("main").unwrap();
+        assert_eq!(health.circuit_state, CircuitState::Open);
+    }
+}
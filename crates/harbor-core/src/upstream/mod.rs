@@ -3,11 +3,15 @@
 //! This module provides the UpstreamManager which handles:
 //! - Managing multiple HarborClient instances
 //! - Routing requests to appropriate upstreams based on patterns
-//! - Health monitoring per upstream
+//! - Health probing and circuit breaking per upstream
 //! - Dynamic upstream configuration
 
+mod health;
 mod manager;
 mod router;
 
-pub use manager::{UpstreamHealth, UpstreamInfo, UpstreamManager};
-pub use router::RouteMatch;
+pub use health::{
+    CircuitState, CircuitTransition, HealthCheckConfig, spawn_circuit_logger, spawn_health_prober,
+};
+pub use manager::{MatchReason, UpstreamHealth, UpstreamInfo, UpstreamManager};
+pub use router::{RouteMatch, RouteMatcher};
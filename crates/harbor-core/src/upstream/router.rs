@@ -116,6 +116,14 @@ impl RouteMatcher {
         None
     }
 
+    /// Check if a single glob pattern matches a repository path.
+    ///
+    /// Uses the same semantics as route matching: `*` matches within one
+    /// path segment and `**` matches any number of segments.
+    pub fn pattern_matches(pattern: &str, repository: &str) -> bool {
+        Self::matches_pattern(&Self::compile_pattern(pattern), repository)
+    }
+
     /// Check if a pattern matches a repository path
     fn matches_pattern(parts: &[PatternPart], path: &str) -> bool {
         let mut iterations = 0;
@@ -254,6 +262,14 @@ mod tests {
         assert!(matcher.find_match("team-b/image").is_none());
     }
 
+    #[test]
+    fn test_pattern_matches() {
+        assert!(RouteMatcher::pattern_matches("team-a/**", "team-a/app/web"));
+        assert!(RouteMatcher::pattern_matches("library/*", "library/alpine"));
+        assert!(!RouteMatcher::pattern_matches("library/*", "library/a/b"));
+        assert!(!RouteMatcher::pattern_matches("team-a/**", "team-b/app"));
+    }
+
     #[test]
     fn test_priority_ordering() {
         let matcher = RouteMatcher::new(vec![
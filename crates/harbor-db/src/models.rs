@@ -54,6 +54,9 @@ impl FromStr for EntryType {
     }
 }
 
+/// Namespace of the cache shared by all upstreams without isolation
+pub const SHARED_NAMESPACE: i64 = 0;
+
 /// Cache entry model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
@@ -68,9 +71,12 @@ pub struct CacheEntry {
     pub last_accessed_at: DateTime<Utc>,
     pub access_count: i64,
     pub storage_path: String,
-    /// Optional upstream ID for cache isolation
+    /// Upstream the content was fetched from (None if unknown)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub upstream_id: Option<i64>,
+    /// Cache namespace: [`SHARED_NAMESPACE`] or the ID of the isolated
+    /// upstream owning the entry
+    pub namespace: i64,
 }
 
 /// User role
@@ -125,6 +131,36 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Repository permission grant for a user or a group.
+/// Exactly one of `user_id` and `group_name` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryPermission {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub group_name: Option<String>,
+    /// Repository glob pattern (`*` matches one segment, `**` any number)
+    pub pattern: String,
+    /// Granted actions (`pull`, `push`, `delete` or `*`)
+    pub actions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New repository permission (for insertion)
+#[derive(Debug, Clone)]
+pub struct NewRepositoryPermission {
+    pub user_id: Option<i64>,
+    pub group_name: Option<String>,
+    pub pattern: String,
+    pub actions: Vec<String>,
+}
+
+/// Repository permission update
+#[derive(Debug, Clone, Default)]
+pub struct UpdateRepositoryPermission {
+    pub pattern: Option<String>,
+    pub actions: Option<Vec<String>>,
+}
+
 /// Configuration entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigEntry {
@@ -142,6 +178,79 @@ pub struct UploadSession {
     pub last_chunk_at: DateTime<Utc>,
     pub bytes_received: i64,
     pub temp_path: String,
+    /// Multipart upload id on object storage (None until the first part)
+    pub multipart_upload_id: Option<String>,
+}
+
+/// Progress of the background scrubber, persisted so that a pass resumes
+/// after a restart
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScrubState {
+    /// Start of the pass in progress (None between passes)
+    pub pass_started_at: Option<DateTime<Utc>>,
+    /// Last digest verified by the pass in progress
+    pub cursor: Option<String>,
+    /// Cache namespace the pass in progress is in
+    pub cursor_namespace: i64,
+    pub last_completed_at: Option<DateTime<Utc>>,
+    /// Counters of the pass in progress, or of the last pass
+    pub verified_blobs: i64,
+    pub verified_bytes: i64,
+    pub corrupt_blobs: i64,
+}
+
+/// An uploaded part of a multipart upload session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSessionPart {
+    pub session_id: String,
+    /// Position of the part, starting at 0
+    pub part_number: i64,
+    pub etag: String,
+    pub size: i64,
+}
+
+/// Tag to digest mapping for a cached manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestTag {
+    pub repository: String,
+    pub tag: String,
+    pub digest: String,
+    /// When the mapping was last confirmed by the upstream
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Edge in the manifest reference graph: a cached manifest pointing to a
+/// blob (config/layer) or a child manifest (index entry)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestReference {
+    pub manifest_digest: String,
+    pub child_digest: String,
+    pub child_type: EntryType,
+}
+
+/// A manifest that refers to a subject manifest (OCI referrers API)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestReferrer {
+    pub repository: String,
+    pub subject_digest: String,
+    /// Digest of the referring manifest
+    pub digest: String,
+    pub media_type: String,
+    pub artifact_type: Option<String>,
+    pub size: i64,
+    /// Annotations of the referring manifest, as a JSON object
+    pub annotations: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New referrer (for insertion)
+#[derive(Debug, Clone)]
+pub struct NewManifestReferrer {
+    pub digest: String,
+    pub media_type: String,
+    pub artifact_type: Option<String>,
+    pub size: i64,
+    pub annotations: Option<String>,
 }
 
 /// New cache entry (for insertion)
@@ -154,8 +263,10 @@ pub struct NewCacheEntry {
     pub content_type: String,
     pub size: i64,
     pub storage_path: String,
-    /// Optional upstream ID for cache isolation
+    /// Upstream the content was fetched from
     pub upstream_id: Option<i64>,
+    /// Cache namespace: [`SHARED_NAMESPACE`] or the ID of an isolated upstream
+    pub namespace: i64,
 }
 
 /// New user (for insertion)
@@ -336,6 +447,7 @@ impl TryFrom<&sqlx::sqlite::SqliteRow> for CacheEntry {
             access_count: row.try_get("access_count")?,
             storage_path: row.try_get("storage_path")?,
             upstream_id: row.try_get("upstream_id").ok(),
+            namespace: row.try_get("namespace")?,
         })
     }
 }
@@ -356,6 +468,26 @@ impl TryFrom<&sqlx::sqlite::SqliteRow> for User {
     }
 }
 
+impl TryFrom<&sqlx::sqlite::SqliteRow> for RepositoryPermission {
+    type Error = sqlx::Error;
+
+    fn try_from(row: &sqlx::sqlite::SqliteRow) -> Result<Self, Self::Error> {
+        let actions: String = row.try_get("actions")?;
+        Ok(RepositoryPermission {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            group_name: row.try_get("group_name")?,
+            pattern: row.try_get("pattern")?,
+            actions: actions
+                .split(',')
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .collect(),
+            created_at: parse_datetime_or_now(&row.try_get::<String, _>("created_at")?),
+        })
+    }
+}
+
 impl TryFrom<&sqlx::sqlite::SqliteRow> for UploadSession {
     type Error = sqlx::Error;
 
@@ -367,6 +499,84 @@ impl TryFrom<&sqlx::sqlite::SqliteRow> for UploadSession {
             last_chunk_at: parse_datetime_or_now(&row.try_get::<String, _>("last_chunk_at")?),
             bytes_received: row.try_get("bytes_received")?,
             temp_path: row.try_get("temp_path")?,
+            multipart_upload_id: row.try_get("multipart_upload_id")?,
+        })
+    }
+}
+
+impl TryFrom<&sqlx::sqlite::SqliteRow> for ScrubState {
+    type Error = sqlx::Error;
+
+    fn try_from(row: &sqlx::sqlite::SqliteRow) -> Result<Self, Self::Error> {
+        let datetime = |column: &str| -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+            Ok(row
+                .try_get::<Option<String>, _>(column)?
+                .map(|s| parse_datetime_or_now(&s)))
+        };
+        Ok(ScrubState {
+            pass_started_at: datetime("pass_started_at")?,
+            cursor: row.try_get("cursor")?,
+            cursor_namespace: row.try_get("cursor_namespace")?,
+            last_completed_at: datetime("last_completed_at")?,
+            verified_blobs: row.try_get("verified_blobs")?,
+            verified_bytes: row.try_get("verified_bytes")?,
+            corrupt_blobs: row.try_get("corrupt_blobs")?,
+        })
+    }
+}
+
+impl TryFrom<&sqlx::sqlite::SqliteRow> for UploadSessionPart {
+    type Error = sqlx::Error;
+
+    fn try_from(row: &sqlx::sqlite::SqliteRow) -> Result<Self, Self::Error> {
+        Ok(UploadSessionPart {
+            session_id: row.try_get("session_id")?,
+            part_number: row.try_get("part_number")?,
+            etag: row.try_get("etag")?,
+            size: row.try_get("size")?,
+        })
+    }
+}
+
+impl TryFrom<&sqlx::sqlite::SqliteRow> for ManifestTag {
+    type Error = sqlx::Error;
+
+    fn try_from(row: &sqlx::sqlite::SqliteRow) -> Result<Self, Self::Error> {
+        Ok(ManifestTag {
+            repository: row.try_get("repository")?,
+            tag: row.try_get("tag")?,
+            digest: row.try_get("digest")?,
+            updated_at: parse_datetime_or_now(&row.try_get::<String, _>("updated_at")?),
+        })
+    }
+}
+
+impl TryFrom<&sqlx::sqlite::SqliteRow> for ManifestReferrer {
+    type Error = sqlx::Error;
+
+    fn try_from(row: &sqlx::sqlite::SqliteRow) -> Result<Self, Self::Error> {
+        Ok(ManifestReferrer {
+            repository: row.try_get("repository")?,
+            subject_digest: row.try_get("subject_digest")?,
+            digest: row.try_get("digest")?,
+            media_type: row.try_get("media_type")?,
+            artifact_type: row.try_get("artifact_type")?,
+            size: row.try_get("size")?,
+            annotations: row.try_get("annotations")?,
+            updated_at: parse_datetime_or_now(&row.try_get::<String, _>("updated_at")?),
+        })
+    }
+}
+
+impl TryFrom<&sqlx::sqlite::SqliteRow> for ManifestReference {
+    type Error = sqlx::Error;
+
+    fn try_from(row: &sqlx::sqlite::SqliteRow) -> Result<Self, Self::Error> {
+        let child_type_str: String = row.try_get("child_type")?;
+        Ok(ManifestReference {
+            manifest_digest: row.try_get("manifest_digest")?,
+            child_digest: row.try_get("child_digest")?,
+            child_type: EntryType::from_str(&child_type_str).unwrap_or(EntryType::Blob),
         })
     }
 }
@@ -15,8 +15,8 @@ impl Database {
         let now = Utc::now();
         let result = sqlx::query(
             r#"
-            INSERT INTO cache_entries (entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)
+            INSERT INTO cache_entries (entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?, ?)
             RETURNING id
             "#,
         )
@@ -30,6 +30,7 @@ impl Database {
         .bind(now.to_rfc3339())
         .bind(&entry.storage_path)
         .bind(entry.upstream_id)
+        .bind(entry.namespace)
         .fetch_one(&self.pool)
         .await?;
 
@@ -48,22 +49,25 @@ impl Database {
             access_count: 1,
             storage_path: entry.storage_path,
             upstream_id: entry.upstream_id,
+            namespace: entry.namespace,
         })
     }
 
-    /// Get a cache entry by digest
+    /// Get the cache entry of a digest in a namespace
     pub async fn get_cache_entry_by_digest(
         &self,
         digest: &str,
+        namespace: i64,
     ) -> Result<Option<CacheEntry>, DbError> {
         let result = sqlx::query(
             r#"
-            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
             FROM cache_entries
-            WHERE digest = ?
+            WHERE digest = ? AND namespace = ?
             "#,
         )
         .bind(digest)
+        .bind(namespace)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -72,78 +76,175 @@ impl Database {
             .transpose()
     }
 
-    /// Get a cache entry by digest and upstream ID (for isolated caching)
-    pub async fn get_cache_entry_by_digest_and_upstream(
-        &self,
-        digest: &str,
-        upstream_id: Option<i64>,
-    ) -> Result<Option<CacheEntry>, DbError> {
-        let result = if let Some(uid) = upstream_id {
-            sqlx::query(
-                r#"
-                SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id
-                FROM cache_entries
-                WHERE digest = ? AND upstream_id = ?
-                "#,
-            )
-            .bind(digest)
-            .bind(uid)
-            .fetch_optional(&self.pool)
-            .await?
-        } else {
-            sqlx::query(
-                r#"
-                SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id
-                FROM cache_entries
-                WHERE digest = ? AND upstream_id IS NULL
-                "#,
-            )
-            .bind(digest)
-            .fetch_optional(&self.pool)
-            .await?
-        };
-
-        result
-            .map(|row| CacheEntry::try_from(&row).map_err(DbError::from))
-            .transpose()
+    /// Check whether a digest is cached in any namespace
+    pub async fn is_digest_cached(&self, digest: &str) -> Result<bool, DbError> {
+        let row =
+            sqlx::query("SELECT EXISTS(SELECT 1 FROM cache_entries WHERE digest = ?) as cached")
+                .bind(digest)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(row.get::<i64, _>("cached") != 0)
     }
 
     /// Update last accessed time and increment access count
-    pub async fn touch_cache_entry(&self, digest: &str) -> Result<(), DbError> {
+    pub async fn touch_cache_entry(&self, digest: &str, namespace: i64) -> Result<(), DbError> {
         let now = Utc::now();
         sqlx::query(
             r#"
             UPDATE cache_entries
             SET last_accessed_at = ?, access_count = access_count + 1
-            WHERE digest = ?
+            WHERE digest = ? AND namespace = ?
             "#,
         )
         .bind(now.to_rfc3339())
         .bind(digest)
+        .bind(namespace)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    /// Delete a cache entry by digest
-    pub async fn delete_cache_entry(&self, digest: &str) -> Result<bool, DbError> {
-        let result = sqlx::query("DELETE FROM cache_entries WHERE digest = ?")
+    /// Correct the recorded size of a cache entry
+    pub async fn update_cache_entry_size(
+        &self,
+        digest: &str,
+        namespace: i64,
+        size: i64,
+    ) -> Result<bool, DbError> {
+        let result =
+            sqlx::query("UPDATE cache_entries SET size = ? WHERE digest = ? AND namespace = ?")
+                .bind(size)
+                .bind(digest)
+                .bind(namespace)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete the cache entry of a digest in a namespace
+    pub async fn delete_cache_entry(&self, digest: &str, namespace: i64) -> Result<bool, DbError> {
+        let result = sqlx::query("DELETE FROM cache_entries WHERE digest = ? AND namespace = ?")
             .bind(digest)
+            .bind(namespace)
             .execute(&self.pool)
             .await?;
         Ok(result.rows_affected() > 0)
     }
 
-    /// Get all cache entries sorted by last accessed time (oldest first) for LRU eviction
-    pub async fn get_cache_entries_lru(&self, limit: i64) -> Result<Vec<CacheEntry>, DbError> {
+    /// Get all cache entries of a namespace
+    pub async fn list_all_cache_entries(&self, namespace: i64) -> Result<Vec<CacheEntry>, DbError> {
         let rows = sqlx::query(
             r#"
-            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
             FROM cache_entries
+            WHERE namespace = ?
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(namespace)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| CacheEntry::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Get cache entries of a namespace with a digest after `after`, in
+    /// digest order
+    pub async fn list_cache_entries_after(
+        &self,
+        namespace: i64,
+        after: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<CacheEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
+            FROM cache_entries
+            WHERE namespace = ? AND digest > ?
+            ORDER BY digest ASC, id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(namespace)
+        .bind(after.unwrap_or(""))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| CacheEntry::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Get the entries of a namespace sorted by last accessed time (oldest first) for LRU eviction
+    pub async fn get_cache_entries_lru(
+        &self,
+        namespace: i64,
+        limit: i64,
+    ) -> Result<Vec<CacheEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
+            FROM cache_entries
+            WHERE namespace = ?
             ORDER BY last_accessed_at ASC
             LIMIT ?
             "#,
         )
+        .bind(namespace)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| CacheEntry::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Get the entries of a namespace sorted by access count (least used first) for LFU eviction.
+    /// Ties are broken by last accessed time.
+    pub async fn get_cache_entries_lfu(
+        &self,
+        namespace: i64,
+        limit: i64,
+    ) -> Result<Vec<CacheEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
+            FROM cache_entries
+            WHERE namespace = ?
+            ORDER BY access_count ASC, last_accessed_at ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(namespace)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| CacheEntry::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Get the entries of a namespace sorted by creation time (oldest first) for FIFO eviction
+    pub async fn get_cache_entries_fifo(
+        &self,
+        namespace: i64,
+        limit: i64,
+    ) -> Result<Vec<CacheEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
+            FROM cache_entries
+            WHERE namespace = ?
+            ORDER BY created_at ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(namespace)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
@@ -161,6 +262,17 @@ impl Database {
         Ok(result.get("total"))
     }
 
+    /// Get the size of the entries in a namespace
+    pub async fn get_namespace_cache_size(&self, namespace: i64) -> Result<i64, DbError> {
+        let result = sqlx::query(
+            "SELECT COALESCE(SUM(size), 0) as total FROM cache_entries WHERE namespace = ?",
+        )
+        .bind(namespace)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result.get("total"))
+    }
+
     /// Get cache entry count
     pub async fn get_cache_entry_count(&self) -> Result<i64, DbError> {
         let result = sqlx::query("SELECT COUNT(*) as count FROM cache_entries")
@@ -197,7 +309,8 @@ impl Database {
         })
     }
 
-    /// Get cache statistics for a specific upstream
+    /// Get cache statistics for the entries fetched from an upstream, in
+    /// any namespace
     pub async fn get_cache_stats_by_upstream(
         &self,
         upstream_id: i64,
@@ -379,7 +492,7 @@ impl Database {
         let sql = format!(
             r#"
             SELECT id, entry_type, repository, reference, digest, content_type, size,
-                   created_at, last_accessed_at, access_count, storage_path, upstream_id
+                   created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
             FROM cache_entries
             {}
             ORDER BY {} {}
@@ -408,7 +521,7 @@ impl Database {
         let rows = sqlx::query(
             r#"
             SELECT id, entry_type, repository, reference, digest, content_type, size,
-                   created_at, last_accessed_at, access_count, storage_path, upstream_id
+                   created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
             FROM cache_entries
             ORDER BY access_count DESC
             LIMIT ?
@@ -441,4 +554,27 @@ impl Database {
 
         Ok(rows.iter().map(|row| row.get("repository")).collect())
     }
+
+    /// Get cached repository names sorted after `last`, at most `limit`
+    pub async fn get_cached_repositories_after(
+        &self,
+        last: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<String>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT repository
+            FROM cache_entries
+            WHERE repository IS NOT NULL AND (?1 IS NULL OR repository > ?1)
+            ORDER BY repository
+            LIMIT ?2
+            "#,
+        )
+        .bind(last)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("repository")).collect())
+    }
 }
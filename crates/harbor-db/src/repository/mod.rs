@@ -9,7 +9,11 @@ use crate::error::DbError;
 mod activity_logs;
 mod cache;
 mod config;
+mod permissions;
+mod references;
+mod scrub;
 mod sessions;
+mod tags;
 mod upstreams;
 mod users;
 
@@ -80,6 +84,22 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_cache_entries_access_count ON cache_entries(access_count, last_accessed_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_cache_entries_created_at ON cache_entries(created_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS users (
@@ -268,6 +288,304 @@ impl Database {
             .await?;
         }
 
+        // Cache namespaces: isolated upstreams cache the same digest separately,
+        // so uniqueness moves from the digest to (digest, namespace). SQLite
+        // cannot drop the inline UNIQUE constraint, so the table is rebuilt.
+        let column_exists: bool = sqlx::query(
+            "SELECT COUNT(*) as count FROM pragma_table_info('cache_entries') WHERE name = 'namespace'"
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get::<i64, _>("count") > 0)
+        .unwrap_or(false);
+
+        if !column_exists {
+            let mut tx = self.pool.begin().await?;
+
+            sqlx::query(
+                r#"
+                CREATE TABLE cache_entries_namespaced (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    entry_type TEXT NOT NULL,
+                    repository TEXT,
+                    reference TEXT,
+                    digest TEXT NOT NULL,
+                    content_type TEXT NOT NULL,
+                    size INTEGER NOT NULL,
+                    created_at TEXT NOT NULL,
+                    last_accessed_at TEXT NOT NULL,
+                    access_count INTEGER DEFAULT 1,
+                    storage_path TEXT NOT NULL,
+                    upstream_id INTEGER,
+                    namespace INTEGER NOT NULL DEFAULT 0,
+                    UNIQUE (digest, namespace)
+                )
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO cache_entries_namespaced (id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace)
+                SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, 0
+                FROM cache_entries
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query("DROP TABLE cache_entries")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("ALTER TABLE cache_entries_namespaced RENAME TO cache_entries")
+                .execute(&mut *tx)
+                .await?;
+
+            for index in [
+                "CREATE INDEX idx_cache_entries_digest ON cache_entries(digest)",
+                "CREATE INDEX idx_cache_entries_last_accessed ON cache_entries(last_accessed_at)",
+                "CREATE INDEX idx_cache_entries_access_count ON cache_entries(access_count, last_accessed_at)",
+                "CREATE INDEX idx_cache_entries_created_at ON cache_entries(created_at)",
+                "CREATE INDEX idx_cache_entries_upstream_id ON cache_entries(upstream_id)",
+                "CREATE INDEX idx_cache_entries_namespace ON cache_entries(namespace)",
+            ] {
+                sqlx::query(index).execute(&mut *tx).await?;
+            }
+
+            tx.commit().await?;
+        }
+
+        // Multipart upload state for chunked uploads on object storage
+        let column_exists: bool = sqlx::query(
+            "SELECT COUNT(*) as count FROM pragma_table_info('upload_sessions') WHERE name = 'multipart_upload_id'"
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get::<i64, _>("count") > 0)
+        .unwrap_or(false);
+
+        if !column_exists {
+            sqlx::query("ALTER TABLE upload_sessions ADD COLUMN multipart_upload_id TEXT")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS upload_session_parts (
+                session_id TEXT NOT NULL,
+                part_number INTEGER NOT NULL,
+                etag TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                PRIMARY KEY (session_id, part_number)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Tag -> digest index for serving tag pulls from cache
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS manifest_tags (
+                repository TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                digest TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (repository, tag)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_manifest_tags_digest ON manifest_tags(digest)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Backfill the tag index from manifests cached by tag
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO manifest_tags (repository, tag, digest, updated_at)
+            SELECT repository, reference, digest, created_at
+            FROM cache_entries
+            WHERE entry_type = 'manifest'
+              AND repository IS NOT NULL
+              AND reference IS NOT NULL
+              AND reference NOT LIKE 'sha256:%'
+              AND reference NOT LIKE 'sha512:%'
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Manifest -> blob/child manifest reference graph used by eviction
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS manifest_references (
+                manifest_digest TEXT NOT NULL,
+                child_digest TEXT NOT NULL,
+                child_type TEXT NOT NULL,
+                PRIMARY KEY (manifest_digest, child_digest)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_manifest_references_child ON manifest_references(child_digest)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Subject -> referrer index for the OCI referrers API
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS manifest_referrers (
+                repository TEXT NOT NULL,
+                subject_digest TEXT NOT NULL,
+                digest TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                artifact_type TEXT,
+                size INTEGER NOT NULL,
+                annotations TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (repository, subject_digest, digest)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_manifest_referrers_digest ON manifest_referrers(digest)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Per-repository access control
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS repository_permissions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER,
+                group_name TEXT,
+                pattern TEXT NOT NULL,
+                actions TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                CHECK ((user_id IS NULL) != (group_name IS NULL))
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_repository_permissions_user_id ON repository_permissions(user_id)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_repository_permissions_group_name ON repository_permissions(group_name)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_groups (
+                user_id INTEGER NOT NULL,
+                group_name TEXT NOT NULL,
+                PRIMARY KEY (user_id, group_name)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Users limited to their repository grants. A user is added when
+        // given a grant or a group and stays restricted when the grants are
+        // removed, so revoking the last grant does not widen access.
+        let table_exists: bool = sqlx::query(
+            "SELECT COUNT(*) as count FROM sqlite_master WHERE type = 'table' AND name = 'restricted_users'"
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get::<i64, _>("count") > 0)
+        .unwrap_or(false);
+
+        if !table_exists {
+            sqlx::query(
+                r#"
+                CREATE TABLE restricted_users (
+                    user_id INTEGER PRIMARY KEY
+                )
+                "#,
+            )
+            .execute(&self.pool)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO restricted_users (user_id)
+                SELECT user_id FROM repository_permissions WHERE user_id IS NOT NULL
+                UNION
+                SELECT user_id FROM user_groups
+                "#,
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+
+        // Progress of the background scrubber, a single row
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS scrub_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                pass_started_at TEXT,
+                cursor TEXT,
+                last_completed_at TEXT,
+                verified_blobs INTEGER NOT NULL DEFAULT 0,
+                verified_bytes INTEGER NOT NULL DEFAULT 0,
+                corrupt_blobs INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Scrub passes also cover isolated cache namespaces
+        let column_exists: bool = sqlx::query(
+            "SELECT COUNT(*) as count FROM pragma_table_info('scrub_state') WHERE name = 'cursor_namespace'"
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get::<i64, _>("count") > 0)
+        .unwrap_or(false);
+
+        if !column_exists {
+            sqlx::query(
+                "ALTER TABLE scrub_state ADD COLUMN cursor_namespace INTEGER NOT NULL DEFAULT 0",
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+
         info!("Database migrations completed");
         Ok(())
     }
@@ -0,0 +1,226 @@
+//! Repository permission and user group operations
+
+use chrono::Utc;
+use sqlx::Row;
+
+use crate::error::DbError;
+use crate::models::{NewRepositoryPermission, RepositoryPermission, UpdateRepositoryPermission};
+use crate::repository::Database;
+
+impl Database {
+    // ==================== Repository Permission Operations ====================
+
+    /// Insert a new repository permission. A user given a grant becomes
+    /// restricted to their grants.
+    pub async fn insert_repository_permission(
+        &self,
+        permission: NewRepositoryPermission,
+    ) -> Result<RepositoryPermission, DbError> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(user_id) = permission.user_id {
+            sqlx::query("INSERT OR IGNORE INTO restricted_users (user_id) VALUES (?)")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO repository_permissions (user_id, group_name, pattern, actions, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING id
+            "#,
+        )
+        .bind(permission.user_id)
+        .bind(&permission.group_name)
+        .bind(&permission.pattern)
+        .bind(permission.actions.join(","))
+        .bind(now.to_rfc3339())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(RepositoryPermission {
+            id: result.get("id"),
+            user_id: permission.user_id,
+            group_name: permission.group_name,
+            pattern: permission.pattern,
+            actions: permission.actions,
+            created_at: now,
+        })
+    }
+
+    /// Get a repository permission by ID
+    pub async fn get_repository_permission(
+        &self,
+        id: i64,
+    ) -> Result<Option<RepositoryPermission>, DbError> {
+        let result = sqlx::query(
+            r#"
+            SELECT id, user_id, group_name, pattern, actions, created_at
+            FROM repository_permissions
+            WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        result
+            .map(|row| RepositoryPermission::try_from(&row).map_err(DbError::from))
+            .transpose()
+    }
+
+    /// List all repository permissions
+    pub async fn list_repository_permissions(&self) -> Result<Vec<RepositoryPermission>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, group_name, pattern, actions, created_at
+            FROM repository_permissions
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| RepositoryPermission::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Get the permissions that apply to a user, directly or through its groups
+    pub async fn get_permissions_for_user(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<RepositoryPermission>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, group_name, pattern, actions, created_at
+            FROM repository_permissions
+            WHERE user_id = ?
+               OR group_name IN (SELECT group_name FROM user_groups WHERE user_id = ?)
+            ORDER BY id
+            "#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| RepositoryPermission::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Update a repository permission
+    pub async fn update_repository_permission(
+        &self,
+        id: i64,
+        update: UpdateRepositoryPermission,
+    ) -> Result<Option<RepositoryPermission>, DbError> {
+        let Some(existing) = self.get_repository_permission(id).await? else {
+            return Ok(None);
+        };
+
+        let pattern = update.pattern.unwrap_or(existing.pattern);
+        let actions = update.actions.unwrap_or(existing.actions);
+
+        sqlx::query(
+            r#"
+            UPDATE repository_permissions
+            SET pattern = ?, actions = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&pattern)
+        .bind(actions.join(","))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_repository_permission(id).await
+    }
+
+    /// Delete a repository permission
+    pub async fn delete_repository_permission(&self, id: i64) -> Result<bool, DbError> {
+        let result = sqlx::query("DELETE FROM repository_permissions WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ==================== Restriction Operations ====================
+
+    /// Check if a user is limited to their repository grants
+    pub async fn is_user_restricted(&self, user_id: i64) -> Result<bool, DbError> {
+        let result =
+            sqlx::query("SELECT COUNT(*) as count FROM restricted_users WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await?;
+        let count: i64 = result.get("count");
+        Ok(count > 0)
+    }
+
+    /// Set whether a user is limited to their repository grants
+    pub async fn set_user_restricted(&self, user_id: i64, restricted: bool) -> Result<(), DbError> {
+        let query = if restricted {
+            "INSERT OR IGNORE INTO restricted_users (user_id) VALUES (?)"
+        } else {
+            "DELETE FROM restricted_users WHERE user_id = ?"
+        };
+        sqlx::query(query).bind(user_id).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    // ==================== User Group Operations ====================
+
+    /// Get the groups a user belongs to
+    pub async fn get_user_groups(&self, user_id: i64) -> Result<Vec<String>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT group_name
+            FROM user_groups
+            WHERE user_id = ?
+            ORDER BY group_name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("group_name")).collect())
+    }
+
+    /// Replace the groups a user belongs to. A user given a group becomes
+    /// restricted to their grants.
+    pub async fn set_user_groups(&self, user_id: i64, groups: &[String]) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+
+        if !groups.is_empty() {
+            sqlx::query("INSERT OR IGNORE INTO restricted_users (user_id) VALUES (?)")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query("DELETE FROM user_groups WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for group in groups {
+            sqlx::query("INSERT OR IGNORE INTO user_groups (user_id, group_name) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(group)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
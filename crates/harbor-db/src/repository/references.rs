@@ -0,0 +1,230 @@
+//! Manifest reference graph operations
+
+use chrono::Utc;
+use sqlx::Row;
+
+use crate::error::DbError;
+use crate::models::{
+    CacheEntry, EntryType, ManifestReference, ManifestReferrer, NewManifestReferrer,
+};
+use crate::repository::Database;
+
+impl Database {
+    // ==================== Manifest Reference Operations ====================
+
+    /// Replace the recorded children of a manifest
+    pub async fn set_manifest_references(
+        &self,
+        manifest_digest: &str,
+        children: &[(String, EntryType)],
+    ) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM manifest_references WHERE manifest_digest = ?")
+            .bind(manifest_digest)
+            .execute(&mut *tx)
+            .await?;
+
+        for (child_digest, child_type) in children {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO manifest_references (manifest_digest, child_digest, child_type)
+                VALUES (?, ?, ?)
+                "#,
+            )
+            .bind(manifest_digest)
+            .bind(child_digest)
+            .bind(child_type.as_str())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the children recorded for a manifest
+    pub async fn get_manifest_references(
+        &self,
+        manifest_digest: &str,
+    ) -> Result<Vec<ManifestReference>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT manifest_digest, child_digest, child_type
+            FROM manifest_references
+            WHERE manifest_digest = ?
+            "#,
+        )
+        .bind(manifest_digest)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| ManifestReference::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Check whether a digest is referenced by any manifest that is still
+    /// cached in a namespace
+    pub async fn is_referenced_by_cached_manifest(
+        &self,
+        digest: &str,
+        namespace: i64,
+    ) -> Result<bool, DbError> {
+        let row = sqlx::query(
+            r#"
+            SELECT EXISTS(
+                SELECT 1
+                FROM manifest_references r
+                JOIN cache_entries c ON c.digest = r.manifest_digest
+                WHERE r.child_digest = ? AND c.namespace = ?
+            ) as referenced
+            "#,
+        )
+        .bind(digest)
+        .bind(namespace)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get::<i64, _>("referenced") != 0)
+    }
+
+    /// Delete the recorded children of a manifest
+    pub async fn delete_manifest_references(&self, manifest_digest: &str) -> Result<u64, DbError> {
+        let result = sqlx::query("DELETE FROM manifest_references WHERE manifest_digest = ?")
+            .bind(manifest_digest)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the manifests cached in a namespace that have no recorded
+    /// references yet
+    pub async fn get_unindexed_manifests(
+        &self,
+        namespace: i64,
+    ) -> Result<Vec<CacheEntry>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, entry_type, repository, reference, digest, content_type, size, created_at, last_accessed_at, access_count, storage_path, upstream_id, namespace
+            FROM cache_entries c
+            WHERE entry_type = 'manifest'
+              AND namespace = ?
+              AND NOT EXISTS (
+                  SELECT 1 FROM manifest_references r WHERE r.manifest_digest = c.digest
+              )
+            "#,
+        )
+        .bind(namespace)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| CacheEntry::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    // ==================== Referrer Operations ====================
+
+    /// Record a manifest referring to a subject, replacing any previous record
+    pub async fn upsert_manifest_referrer(
+        &self,
+        repository: &str,
+        subject_digest: &str,
+        referrer: &NewManifestReferrer,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO manifest_referrers
+                (repository, subject_digest, digest, media_type, artifact_type, size, annotations, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(repository)
+        .bind(subject_digest)
+        .bind(&referrer.digest)
+        .bind(&referrer.media_type)
+        .bind(&referrer.artifact_type)
+        .bind(referrer.size)
+        .bind(&referrer.annotations)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Replace the referrers recorded for a subject
+    pub async fn set_manifest_referrers(
+        &self,
+        repository: &str,
+        subject_digest: &str,
+        referrers: &[NewManifestReferrer],
+    ) -> Result<(), DbError> {
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM manifest_referrers WHERE repository = ? AND subject_digest = ?")
+            .bind(repository)
+            .bind(subject_digest)
+            .execute(&mut *tx)
+            .await?;
+
+        for referrer in referrers {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO manifest_referrers
+                    (repository, subject_digest, digest, media_type, artifact_type, size, annotations, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(repository)
+            .bind(subject_digest)
+            .bind(&referrer.digest)
+            .bind(&referrer.media_type)
+            .bind(&referrer.artifact_type)
+            .bind(referrer.size)
+            .bind(&referrer.annotations)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// List the referrers of a subject, optionally filtered by artifact type
+    pub async fn list_manifest_referrers(
+        &self,
+        repository: &str,
+        subject_digest: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<ManifestReferrer>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT repository, subject_digest, digest, media_type, artifact_type, size, annotations, updated_at
+            FROM manifest_referrers
+            WHERE repository = ?1 AND subject_digest = ?2
+              AND (?3 IS NULL OR artifact_type = ?3)
+            ORDER BY digest
+            "#,
+        )
+        .bind(repository)
+        .bind(subject_digest)
+        .bind(artifact_type)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| ManifestReferrer::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Delete a referring manifest from every subject it was recorded for
+    pub async fn delete_manifest_referrer(&self, digest: &str) -> Result<u64, DbError> {
+        let result = sqlx::query("DELETE FROM manifest_referrers WHERE digest = ?")
+            .bind(digest)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
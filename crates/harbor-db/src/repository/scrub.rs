@@ -0,0 +1,55 @@
+//! Scrubber progress operations
+
+use crate::error::DbError;
+use crate::models::ScrubState;
+use crate::repository::Database;
+
+impl Database {
+    // ==================== Scrub State Operations ====================
+
+    /// Get the scrubber progress (default state if no pass ever ran)
+    pub async fn get_scrub_state(&self) -> Result<ScrubState, DbError> {
+        let result = sqlx::query(
+            r#"
+            SELECT pass_started_at, cursor, cursor_namespace, last_completed_at, verified_blobs, verified_bytes, corrupt_blobs
+            FROM scrub_state
+            WHERE id = 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result
+            .map(|row| ScrubState::try_from(&row))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Save the scrubber progress
+    pub async fn save_scrub_state(&self, state: &ScrubState) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO scrub_state (id, pass_started_at, cursor, cursor_namespace, last_completed_at, verified_blobs, verified_bytes, corrupt_blobs)
+            VALUES (1, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                pass_started_at = excluded.pass_started_at,
+                cursor = excluded.cursor,
+                cursor_namespace = excluded.cursor_namespace,
+                last_completed_at = excluded.last_completed_at,
+                verified_blobs = excluded.verified_blobs,
+                verified_bytes = excluded.verified_bytes,
+                corrupt_blobs = excluded.corrupt_blobs
+            "#,
+        )
+        .bind(state.pass_started_at.map(|t| t.to_rfc3339()))
+        .bind(&state.cursor)
+        .bind(state.cursor_namespace)
+        .bind(state.last_completed_at.map(|t| t.to_rfc3339()))
+        .bind(state.verified_blobs)
+        .bind(state.verified_bytes)
+        .bind(state.corrupt_blobs)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
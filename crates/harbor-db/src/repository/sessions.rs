@@ -1,9 +1,9 @@
 //! Upload session operations
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
 use crate::error::DbError;
-use crate::models::{NewUploadSession, UploadSession};
+use crate::models::{NewUploadSession, UploadSession, UploadSessionPart};
 use crate::repository::Database;
 
 impl Database {
@@ -36,6 +36,7 @@ impl Database {
             last_chunk_at: now,
             bytes_received: 0,
             temp_path: session.temp_path,
+            multipart_upload_id: None,
         })
     }
 
@@ -43,7 +44,7 @@ impl Database {
     pub async fn get_upload_session(&self, id: &str) -> Result<Option<UploadSession>, DbError> {
         let result = sqlx::query(
             r#"
-            SELECT id, repository, started_at, last_chunk_at, bytes_received, temp_path
+            SELECT id, repository, started_at, last_chunk_at, bytes_received, temp_path, multipart_upload_id
             FROM upload_sessions
             WHERE id = ?
             "#,
@@ -57,6 +58,28 @@ impl Database {
             .transpose()
     }
 
+    /// List upload sessions that have not received a chunk since `cutoff`
+    pub async fn list_idle_upload_sessions(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<UploadSession>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, repository, started_at, last_chunk_at, bytes_received, temp_path, multipart_upload_id
+            FROM upload_sessions
+            WHERE last_chunk_at < ?
+            ORDER BY last_chunk_at
+            "#,
+        )
+        .bind(cutoff.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| UploadSession::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
     /// Update upload session bytes received
     pub async fn update_upload_session(
         &self,
@@ -79,8 +102,80 @@ impl Database {
         Ok(result.rows_affected() > 0)
     }
 
-    /// Delete an upload session
+    /// Record a chunk appended through a multipart upload: updates the
+    /// bytes received and upload id, and stores newly uploaded parts
+    pub async fn update_upload_session_multipart(
+        &self,
+        id: &str,
+        bytes_received: i64,
+        multipart_upload_id: Option<&str>,
+        new_parts: &[UploadSessionPart],
+    ) -> Result<bool, DbError> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE upload_sessions
+            SET bytes_received = ?, last_chunk_at = ?, multipart_upload_id = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(bytes_received)
+        .bind(now.to_rfc3339())
+        .bind(multipart_upload_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        for part in new_parts {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO upload_session_parts (session_id, part_number, etag, size)
+                VALUES (?, ?, ?, ?)
+                "#,
+            )
+            .bind(id)
+            .bind(part.part_number)
+            .bind(&part.etag)
+            .bind(part.size)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the uploaded parts of an upload session, in order
+    pub async fn get_upload_session_parts(
+        &self,
+        id: &str,
+    ) -> Result<Vec<UploadSessionPart>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT session_id, part_number, etag, size
+            FROM upload_session_parts
+            WHERE session_id = ?
+            ORDER BY part_number
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| UploadSessionPart::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Delete an upload session and its parts
     pub async fn delete_upload_session(&self, id: &str) -> Result<bool, DbError> {
+        sqlx::query("DELETE FROM upload_session_parts WHERE session_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
         let result = sqlx::query("DELETE FROM upload_sessions WHERE id = ?")
             .bind(id)
             .execute(&self.pool)
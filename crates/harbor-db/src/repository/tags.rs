@@ -0,0 +1,102 @@
+//! Manifest tag index operations
+
+use chrono::Utc;
+
+use crate::error::DbError;
+use crate::models::ManifestTag;
+use crate::repository::Database;
+
+impl Database {
+    // ==================== Manifest Tag Operations ====================
+
+    /// Record (or refresh) the digest a tag currently points to
+    pub async fn upsert_manifest_tag(
+        &self,
+        repository: &str,
+        tag: &str,
+        digest: &str,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO manifest_tags (repository, tag, digest, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(repository, tag) DO UPDATE SET
+                digest = excluded.digest,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(repository)
+        .bind(tag)
+        .bind(digest)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get the digest a tag points to
+    pub async fn get_manifest_tag(
+        &self,
+        repository: &str,
+        tag: &str,
+    ) -> Result<Option<ManifestTag>, DbError> {
+        let result = sqlx::query(
+            r#"
+            SELECT repository, tag, digest, updated_at
+            FROM manifest_tags
+            WHERE repository = ? AND tag = ?
+            "#,
+        )
+        .bind(repository)
+        .bind(tag)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        result
+            .map(|row| ManifestTag::try_from(&row).map_err(DbError::from))
+            .transpose()
+    }
+
+    /// List the known tags of a repository in lexical order
+    pub async fn list_manifest_tags(&self, repository: &str) -> Result<Vec<ManifestTag>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT repository, tag, digest, updated_at
+            FROM manifest_tags
+            WHERE repository = ?
+            ORDER BY tag
+            "#,
+        )
+        .bind(repository)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| ManifestTag::try_from(row).map_err(DbError::from))
+            .collect()
+    }
+
+    /// Remove a tag from the index
+    pub async fn delete_manifest_tag(&self, repository: &str, tag: &str) -> Result<bool, DbError> {
+        let result = sqlx::query("DELETE FROM manifest_tags WHERE repository = ? AND tag = ?")
+            .bind(repository)
+            .bind(tag)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove every tag of a repository that points to a digest
+    pub async fn delete_manifest_tags_for_digest(
+        &self,
+        repository: &str,
+        digest: &str,
+    ) -> Result<u64, DbError> {
+        let result = sqlx::query("DELETE FROM manifest_tags WHERE repository = ? AND digest = ?")
+            .bind(repository)
+            .bind(digest)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -66,6 +66,59 @@ impl Database {
         })
     }
 
+    /// Insert an upstream, or update the one with the same name, and return
+    /// its ID. Used to give upstreams defined outside the database a stable
+    /// ID for cache entries.
+    pub async fn register_upstream(&self, upstream: NewUpstream) -> Result<i64, DbError> {
+        let now = Utc::now();
+
+        if upstream.is_default {
+            sqlx::query("UPDATE upstreams SET is_default = 0 WHERE is_default = 1 AND name != ?")
+                .bind(&upstream.name)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO upstreams (name, display_name, url, registry, username, password,
+                                   skip_tls_verify, priority, enabled, cache_isolation,
+                                   is_default, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                display_name = excluded.display_name,
+                url = excluded.url,
+                registry = excluded.registry,
+                username = excluded.username,
+                password = excluded.password,
+                skip_tls_verify = excluded.skip_tls_verify,
+                priority = excluded.priority,
+                enabled = excluded.enabled,
+                cache_isolation = excluded.cache_isolation,
+                is_default = excluded.is_default,
+                updated_at = excluded.updated_at
+            RETURNING id
+            "#,
+        )
+        .bind(&upstream.name)
+        .bind(&upstream.display_name)
+        .bind(&upstream.url)
+        .bind(&upstream.registry)
+        .bind(&upstream.username)
+        .bind(&upstream.password)
+        .bind(upstream.skip_tls_verify)
+        .bind(upstream.priority)
+        .bind(upstream.enabled)
+        .bind(upstream.cache_isolation.as_str())
+        .bind(upstream.is_default)
+        .bind(now.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.get("id"))
+    }
+
     /// Get an upstream by ID
     pub async fn get_upstream(&self, id: i64) -> Result<Option<Upstream>, DbError> {
         let result = sqlx::query(
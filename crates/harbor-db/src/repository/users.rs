@@ -149,6 +149,21 @@ impl Database {
             .bind(id)
             .execute(&self.pool)
             .await?;
+
+        // Drop the user's grants and group memberships with it
+        sqlx::query("DELETE FROM repository_permissions WHERE user_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM user_groups WHERE user_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM restricted_users WHERE user_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
         Ok(result.rows_affected() > 0)
     }
 
@@ -1,17 +1,35 @@
 //! Harbor upstream client
 
 use bytes::Bytes;
-use futures::Stream;
-use reqwest::{Client, Response, StatusCode};
+use futures::{Stream, StreamExt};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::pin::Pin;
-use tracing::{debug, info};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
 use crate::error::ProxyError;
+use crate::retry::{RetryPolicy, is_idempotent, is_retryable_error};
 
 /// Type alias for a boxed stream of bytes
 pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, ProxyError>> + Send>>;
 
+/// Accept header sent for manifest requests
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.oci.image.index.v1+json, \
+     application/vnd.docker.distribution.manifest.v2+json, \
+     application/vnd.docker.distribution.manifest.list.v2+json, \
+     application/vnd.docker.distribution.manifest.v1+prettyjws";
+
+/// Token lifetime assumed when the token service does not report one
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Cached tokens are dropped this long before they expire, so a token is
+/// not sent just as it runs out
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(10);
+
 /// Harbor client configuration
 #[derive(Clone, Debug)]
 pub struct HarborClientConfig {
@@ -25,6 +43,124 @@ pub struct HarborClientConfig {
     pub password: Option<String>,
     /// Skip TLS certificate verification
     pub skip_tls_verify: bool,
+    /// Retry policy for idempotent requests
+    pub retry: RetryPolicy,
+}
+
+/// A page of tags from the tag listing endpoint
+#[derive(Debug, Clone, Default)]
+pub struct TagList {
+    pub tags: Vec<String>,
+    /// `last` parameter for the next page, if there is one
+    pub next_last: Option<String>,
+}
+
+/// Tag listing response body
+#[derive(Debug, Deserialize)]
+struct TagListResponse {
+    /// Registries return `null` for repositories without tags
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+/// Byte range of a partial (206) blob response, as in `Content-Range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    /// First byte position (inclusive)
+    pub start: u64,
+    /// Last byte position (inclusive)
+    pub end: u64,
+    /// Full blob size, if known
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    /// Parse a `Content-Range` value such as `bytes 0-99/1000` or `bytes 0-99/*`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+        let (start, end) = range.split_once('-')?;
+        let start = start.trim().parse().ok()?;
+        let end = end.trim().parse().ok()?;
+        let total = match total.trim() {
+            "*" => None,
+            total => Some(total.parse().ok()?),
+        };
+        (start <= end).then_some(Self { start, end, total })
+    }
+
+    /// Number of bytes in the range
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Format as a `Content-Range` value
+    pub fn header_value(&self) -> String {
+        match self.total {
+            Some(total) => format!("bytes {}-{}/{}", self.start, self.end, total),
+            None => format!("bytes {}-{}/*", self.start, self.end),
+        }
+    }
+}
+
+/// A page of repositories from the catalog endpoint
+#[derive(Debug, Clone, Default)]
+pub struct RepositoryList {
+    /// Repository names with the registry prefix stripped
+    pub repositories: Vec<String>,
+    /// `last` parameter for the next page, if there is one
+    pub next_last: Option<String>,
+}
+
+/// Catalog response body
+#[derive(Debug, Deserialize)]
+struct CatalogResponse {
+    #[serde(default)]
+    repositories: Option<Vec<String>>,
+}
+
+/// Extract the `last` parameter of the `rel="next"` target of a `Link` header
+fn next_last_from_link(link: &str) -> Option<String> {
+    link.split(',')
+        .find(|part| part.contains("rel=\"next\""))
+        .and_then(|part| {
+            let start = part.find('<')? + 1;
+            let end = part.find('>')?;
+            part.get(start..end)
+        })
+        .and_then(|target| target.split_once('?'))
+        .and_then(|(_, query)| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("last="))
+                .map(str::to_string)
+        })
+}
+
+/// Token scope a registry request is expected to be challenged for, as sent
+/// by the token service (`repository:<name>:<actions>`). Empty for requests
+/// outside a repository, such as the `/v2/` ping.
+fn request_scope(method: &str, url: &str) -> String {
+    let path = url.split('?').next().unwrap_or(url);
+    let Some((_, path)) = path.split_once("/v2/") else {
+        return String::new();
+    };
+    if path.starts_with("_catalog") {
+        return "registry:catalog:*".to_string();
+    }
+
+    let Some(end) = ["/manifests/", "/blobs/", "/tags/", "/referrers/"]
+        .iter()
+        .filter_map(|marker| path.find(marker))
+        .min()
+    else {
+        return String::new();
+    };
+    let actions = match method {
+        "GET" | "HEAD" => "pull",
+        "DELETE" => "delete",
+        _ => "pull,push",
+    };
+    format!("repository:{}:{}", &path[..end], actions)
 }
 
 /// Token response from Harbor
@@ -32,14 +168,113 @@ pub struct HarborClientConfig {
 struct TokenResponse {
     token: String,
     #[serde(default)]
-    #[allow(dead_code)]
     expires_in: Option<u64>,
 }
 
+/// A bearer token cached for a scope
+struct CachedToken {
+    /// Authorization header value
+    header: String,
+    expires_at: Instant,
+}
+
+/// Response body of an upstream request
+type BodyStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+/// A blob download that resumes with a range request when the connection
+/// drops after part of the blob was received
+struct ResumableDownload {
+    client: Client,
+    url: String,
+    /// Authorization header of the original request
+    authorization: Option<String>,
+    policy: RetryPolicy,
+    /// Blob size (0 if unknown, which disables resuming)
+    size: u64,
+    received: u64,
+    resumes: u32,
+    body: BodyStream,
+}
+
+impl ResumableDownload {
+    /// Next chunk of the blob, resuming the download if it was interrupted
+    async fn next(&mut self) -> Option<Result<Bytes, ProxyError>> {
+        loop {
+            let mut error = match self.body.next().await? {
+                Ok(chunk) => {
+                    self.received += chunk.len() as u64;
+                    return Some(Ok(chunk));
+                }
+                Err(e) => ProxyError::Http(e),
+            };
+
+            loop {
+                if self.size == 0
+                    || self.received >= self.size
+                    || self.resumes + 1 >= self.policy.max_attempts
+                {
+                    return Some(Err(error));
+                }
+
+                self.resumes += 1;
+                let delay = self.policy.backoff(self.resumes);
+                warn!(
+                    "Download of {} interrupted after {} of {} bytes ({}), resuming in {:?}",
+                    self.url, self.received, self.size, error, delay
+                );
+                metrics::counter!("harbor_cache_upstream_retries_total").increment(1);
+                tokio::time::sleep(delay).await;
+
+                match Self::resume(self.resume_request(), self.received).await {
+                    Ok(body) => {
+                        self.body = body;
+                        break;
+                    }
+                    Err(e) => error = e,
+                }
+            }
+        }
+    }
+
+    /// Request for the rest of the blob from the first byte not yet received
+    fn resume_request(&self) -> RequestBuilder {
+        let request = self
+            .client
+            .get(&self.url)
+            .header("Range", format!("bytes={}-", self.received));
+        match self.authorization {
+            Some(ref authorization) => request.header("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    /// Send a resume request and check that the upstream continues at `offset`
+    async fn resume(request: RequestBuilder, offset: u64) -> Result<BodyStream, ProxyError> {
+        let response = request.send().await?;
+        let status = response.status();
+        let range = response
+            .headers()
+            .get("content-range")
+            .and_then(|h| h.to_str().ok())
+            .and_then(ContentRange::parse);
+        if status != StatusCode::PARTIAL_CONTENT || range.map(|r| r.start) != Some(offset) {
+            return Err(ProxyError::InvalidResponse(format!(
+                "Upstream did not resume the download at byte {} (HTTP {})",
+                offset,
+                status.as_u16()
+            )));
+        }
+
+        Ok(Box::pin(response.bytes_stream()))
+    }
+}
+
 /// Harbor API client
 pub struct HarborClient {
     config: HarborClientConfig,
     client: Client,
+    /// Bearer tokens by the scope they were issued for
+    tokens: Mutex<HashMap<String, CachedToken>>,
 }
 
 impl HarborClient {
@@ -57,10 +292,133 @@ impl HarborClient {
 
         info!("Created Harbor client for {}", config.url);
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Start a request with the given method
+    fn request(&self, method: &str, url: &str) -> RequestBuilder {
+        match method {
+            "GET" => self.client.get(url),
+            "HEAD" => self.client.head(url),
+            "PUT" => self.client.put(url),
+            "POST" => self.client.post(url),
+            "DELETE" => self.client.delete(url),
+            _ => self.client.get(url),
+        }
+    }
+
+    /// Build a request with its headers, body and authorization
+    fn build_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<&Bytes>,
+        token: Option<&str>,
+    ) -> RequestBuilder {
+        let mut request = self.request(method, url);
+
+        if let Some(token) = token {
+            request = request.header("Authorization", token);
+        }
+
+        for (key, value) in headers {
+            request = request.header(*key, *value);
+        }
+
+        if let Some(data) = body {
+            request = request.body(data.clone());
+        }
+
+        request
     }
 
-    /// Parse WWW-Authenticate header and fetch token with proper scope
+    /// Send a request built by `request`, retrying idempotent requests that
+    /// fail temporarily according to the retry policy
+    async fn send(
+        &self,
+        method: &str,
+        url: &str,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, ProxyError> {
+        let policy = &self.config.retry;
+        let mut attempt = 1;
+
+        loop {
+            let result = request().send().await;
+            if !is_idempotent(method) || attempt >= policy.max_attempts {
+                return Ok(result?);
+            }
+
+            let delay = match &result {
+                Ok(response) => policy.response_delay(response, attempt),
+                Err(e) if is_retryable_error(e) => Some(policy.backoff(attempt)),
+                Err(_) => None,
+            };
+            let Some(delay) = delay else {
+                return Ok(result?);
+            };
+
+            let reason = match result {
+                Ok(response) => format!("HTTP {}", response.status().as_u16()),
+                Err(e) => e.to_string(),
+            };
+            attempt += 1;
+            warn!(
+                "{} {} failed ({}), retrying in {:?} (attempt {}/{})",
+                method, url, reason, delay, attempt, policy.max_attempts
+            );
+            metrics::counter!("harbor_cache_upstream_retries_total").increment(1);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Get the cached token for a scope, if it has not expired
+    fn cached_token(&self, scope: &str) -> Option<String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        match tokens.get(scope) {
+            Some(token) if token.expires_at > Instant::now() => Some(token.header.clone()),
+            Some(_) => {
+                tokens.remove(scope);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a token for its scope until shortly before it expires
+    fn store_token(&self, scope: String, header: String, expires_in: Option<u64>) {
+        let lifetime = expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME)
+            .saturating_sub(TOKEN_EXPIRY_MARGIN);
+        if lifetime.is_zero() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, token| token.expires_at > now);
+        tokens.insert(
+            scope,
+            CachedToken {
+                header,
+                expires_at: now + lifetime,
+            },
+        );
+    }
+
+    /// Drop the cached token for a scope
+    fn forget_token(&self, scope: &str) {
+        self.tokens.lock().unwrap().remove(scope);
+    }
+
+    /// Parse WWW-Authenticate header and fetch token with proper scope.
+    /// The token is cached for the challenged scope.
     async fn fetch_token_for_scope(&self, www_auth: &str) -> Result<String, ProxyError> {
         // Parse: Bearer realm="https://...",service="harbor-registry",scope="..."
         if !www_auth.starts_with("Bearer ") {
@@ -135,7 +493,7 @@ impl HarborClient {
         if let Some(svc) = service {
             params.push(format!("service={}", svc));
         }
-        if let Some(scp) = scope {
+        if let Some(ref scp) = scope {
             params.push(format!("scope={}", scp));
         }
 
@@ -145,14 +503,19 @@ impl HarborClient {
 
         debug!("Fetching token from: {}", url);
 
-        let mut request = self.client.get(&url);
-
-        // Add basic auth if credentials are provided
-        if let (Some(username), Some(password)) = (&self.config.username, &self.config.password) {
-            request = request.basic_auth(username, Some(password));
-        }
+        let response = self
+            .send("GET", &url, || {
+                let request = self.client.get(&url);
 
-        let response = request.send().await?;
+                // Add basic auth if credentials are provided
+                match (&self.config.username, &self.config.password) {
+                    (Some(username), Some(password)) => {
+                        request.basic_auth(username, Some(password))
+                    }
+                    _ => request,
+                }
+            })
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -165,10 +528,19 @@ impl HarborClient {
 
         let token_response: TokenResponse = response.json().await?;
 
-        Ok(format!("Bearer {}", token_response.token))
+        let header = format!("Bearer {}", token_response.token);
+        self.store_token(
+            scope.unwrap_or_default(),
+            header.clone(),
+            token_response.expires_in,
+        );
+        Ok(header)
     }
 
-    /// Make an authenticated request, handling 401 by getting a properly scoped token
+    /// Make an authenticated request, handling 401 by getting a properly scoped token.
+    ///
+    /// A cached token for the scope of the request is sent up front; a 401
+    /// then means it was not accepted, and a new token is fetched.
     async fn authenticated_request(
         &self,
         method: &str,
@@ -176,24 +548,14 @@ impl HarborClient {
         headers: Vec<(&str, &str)>,
         body: Option<Bytes>,
     ) -> Result<Response, ProxyError> {
-        // First attempt without token
-        let mut request = match method {
-            "GET" => self.client.get(url),
-            "HEAD" => self.client.head(url),
-            "PUT" => self.client.put(url),
-            "POST" => self.client.post(url),
-            _ => self.client.get(url),
-        };
-
-        for (key, value) in &headers {
-            request = request.header(*key, *value);
-        }
-
-        if let Some(ref data) = body {
-            request = request.body(data.clone());
-        }
-
-        let response = request.send().await?;
+        // First attempt with the cached token, if any
+        let scope = request_scope(method, url);
+        let cached = self.cached_token(&scope);
+        let response = self
+            .send(method, url, || {
+                self.build_request(method, url, &headers, body.as_ref(), cached.as_deref())
+            })
+            .await?;
 
         // If unauthorized, get a token with the proper scope and retry
         if response.status() == StatusCode::UNAUTHORIZED {
@@ -203,30 +565,20 @@ impl HarborClient {
                 .and_then(|h| h.to_str().ok())
                 .ok_or(ProxyError::Unauthorized)?;
 
+            if cached.is_some() {
+                debug!("Cached token for scope '{}' was rejected", scope);
+                self.forget_token(&scope);
+            }
             debug!("Got 401, fetching token with scope from: {}", www_auth);
 
             let token = self.fetch_token_for_scope(www_auth).await?;
 
             // Retry with token
-            let mut request = match method {
-                "GET" => self.client.get(url),
-                "HEAD" => self.client.head(url),
-                "PUT" => self.client.put(url),
-                "POST" => self.client.post(url),
-                _ => self.client.get(url),
-            };
-
-            request = request.header("Authorization", &token);
-
-            for (key, value) in &headers {
-                request = request.header(*key, *value);
-            }
-
-            if let Some(data) = body {
-                request = request.body(data);
-            }
-
-            return Ok(request.send().await?);
+            return self
+                .send(method, url, || {
+                    self.build_request(method, url, &headers, body.as_ref(), Some(&token))
+                })
+                .await;
         }
 
         Ok(response)
@@ -240,34 +592,33 @@ impl HarborClient {
         headers: Vec<(&str, &str)>,
         body: Option<reqwest::Body>,
     ) -> Result<Response, ProxyError> {
-        // For streaming requests, we need to handle authentication differently
-        // We'll do a HEAD request first to get the token if needed
-        let head_url = url.split('?').next().unwrap_or(url);
-        let probe_response = self
-            .authenticated_request("HEAD", head_url, vec![], None)
-            .await?;
-
-        // Extract token from successful probe or try to get it from 401
-        let token = if probe_response.status() == StatusCode::UNAUTHORIZED {
-            let www_auth = probe_response
-                .headers()
-                .get("www-authenticate")
-                .and_then(|h| h.to_str().ok())
-                .ok_or(ProxyError::Unauthorized)?;
-
-            Some(self.fetch_token_for_scope(www_auth).await?)
-        } else {
-            None
+        // A streaming body cannot be replayed after a 401, so the token must
+        // be known up front: use the cached one, or probe with a HEAD request
+        // to get the token challenge
+        let token = match self.cached_token(&request_scope(method, url)) {
+            Some(token) => Some(token),
+            None => {
+                let head_url = url.split('?').next().unwrap_or(url);
+                let probe_response = self
+                    .send("HEAD", head_url, || self.client.head(head_url))
+                    .await?;
+
+                if probe_response.status() == StatusCode::UNAUTHORIZED {
+                    let www_auth = probe_response
+                        .headers()
+                        .get("www-authenticate")
+                        .and_then(|h| h.to_str().ok())
+                        .ok_or(ProxyError::Unauthorized)?;
+
+                    Some(self.fetch_token_for_scope(www_auth).await?)
+                } else {
+                    None
+                }
+            }
         };
 
         // Build the actual streaming request
-        let mut request = match method {
-            "GET" => self.client.get(url),
-            "HEAD" => self.client.head(url),
-            "PUT" => self.client.put(url),
-            "POST" => self.client.post(url),
-            _ => self.client.get(url),
-        };
+        let mut request = self.request(method, url);
 
         if let Some(token) = token {
             request = request.header("Authorization", &token);
@@ -319,14 +670,7 @@ impl HarborClient {
 
         debug!("Fetching manifest: {}", url);
 
-        let headers = vec![(
-            "Accept",
-            "application/vnd.oci.image.manifest.v1+json, \
-             application/vnd.oci.image.index.v1+json, \
-             application/vnd.docker.distribution.manifest.v2+json, \
-             application/vnd.docker.distribution.manifest.list.v2+json, \
-             application/vnd.docker.distribution.manifest.v1+prettyjws",
-        )];
+        let headers = vec![("Accept", MANIFEST_ACCEPT)];
 
         let response = self
             .authenticated_request("GET", &url, headers, None)
@@ -367,6 +711,202 @@ impl HarborClient {
         Ok((body, content_type, digest))
     }
 
+    /// Resolve a manifest reference without downloading it (HEAD request).
+    /// Returns the `Docker-Content-Digest` (empty if the upstream omits it),
+    /// the content type and the manifest size.
+    pub async fn head_manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<(String, String, u64), ProxyError> {
+        let full_repo = self.full_repository(repository);
+        let url = format!(
+            "{}/v2/{}/manifests/{}",
+            self.config.url, full_repo, reference
+        );
+
+        debug!("Checking manifest: {}", url);
+
+        let headers = vec![("Accept", MANIFEST_ACCEPT)];
+
+        let response = self
+            .authenticated_request("HEAD", &url, headers, None)
+            .await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProxyError::NotFound(format!(
+                "{}:{}",
+                repository, reference
+            )));
+        }
+
+        if !status.is_success() {
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                message: "HEAD request failed".to_string(),
+            });
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(str::to_string)
+        };
+
+        let digest = header("docker-content-digest").unwrap_or_default();
+        let content_type = header("content-type")
+            .unwrap_or_else(|| "application/vnd.oci.image.manifest.v1+json".to_string());
+        let size = header("content-length")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        Ok((digest, content_type, size))
+    }
+
+    /// List the tags of a repository, paginated with `n` and `last`
+    pub async fn list_tags(
+        &self,
+        repository: &str,
+        n: Option<usize>,
+        last: Option<&str>,
+    ) -> Result<TagList, ProxyError> {
+        let full_repo = self.full_repository(repository);
+        let mut params = Vec::new();
+        if let Some(n) = n {
+            params.push(format!("n={}", n));
+        }
+        if let Some(last) = last {
+            params.push(format!("last={}", last));
+        }
+        let mut url = format!("{}/v2/{}/tags/list", self.config.url, full_repo);
+        if !params.is_empty() {
+            url = format!("{}?{}", url, params.join("&"));
+        }
+
+        debug!("Listing tags: {}", url);
+
+        let response = self
+            .authenticated_request("GET", &url, vec![], None)
+            .await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProxyError::NotFound(repository.to_string()));
+        }
+
+        if !status.is_success() {
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        let next_last = response
+            .headers()
+            .get("link")
+            .and_then(|h| h.to_str().ok())
+            .and_then(next_last_from_link);
+
+        let body: TagListResponse = response.json().await?;
+
+        Ok(TagList {
+            tags: body.tags.unwrap_or_default(),
+            next_last,
+        })
+    }
+
+    /// List the repositories under the registry prefix, paginated with `n`
+    /// and `last` (both relative to the prefix).
+    ///
+    /// The upstream catalog is sorted, so the listing starts right after the
+    /// prefix and ends at the first repository outside it.
+    pub async fn catalog(
+        &self,
+        n: Option<usize>,
+        last: Option<&str>,
+    ) -> Result<RepositoryList, ProxyError> {
+        let prefix = format!("{}/", self.config.registry);
+        let mut params = vec![format!("last={}{}", prefix, last.unwrap_or(""))];
+        if let Some(n) = n {
+            params.push(format!("n={}", n));
+        }
+        let url = format!("{}/v2/_catalog?{}", self.config.url, params.join("&"));
+
+        debug!("Listing catalog: {}", url);
+
+        let response = self
+            .authenticated_request("GET", &url, vec![], None)
+            .await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        let has_next = response
+            .headers()
+            .get("link")
+            .and_then(|h| h.to_str().ok())
+            .and_then(next_last_from_link)
+            .is_some();
+
+        let body: CatalogResponse = response.json().await?;
+        let entries = body.repositories.unwrap_or_default();
+        let total = entries.len();
+
+        let repositories: Vec<String> = entries
+            .iter()
+            .map_while(|name| name.strip_prefix(&prefix))
+            .map(str::to_string)
+            .collect();
+
+        // Past the end of the prefix there is nothing more for this registry
+        let next_last = if has_next && repositories.len() == total {
+            repositories.last().cloned()
+        } else {
+            None
+        };
+
+        Ok(RepositoryList {
+            repositories,
+            next_last,
+        })
+    }
+
+    /// Get the referrers of a manifest (OCI 1.1 referrers API) as an image index
+    pub async fn get_referrers(&self, repository: &str, digest: &str) -> Result<Bytes, ProxyError> {
+        let full_repo = self.full_repository(repository);
+        let url = format!("{}/v2/{}/referrers/{}", self.config.url, full_repo, digest);
+
+        debug!("Fetching referrers: {}", url);
+
+        let headers = vec![("Accept", "application/vnd.oci.image.index.v1+json")];
+        let response = self
+            .authenticated_request("GET", &url, headers, None)
+            .await?;
+        let status = response.status();
+
+        // Registries without referrers support answer 404
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProxyError::NotFound(format!("{}@{}", repository, digest)));
+        }
+
+        if !status.is_success() {
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        Ok(response.bytes().await?)
+    }
+
     /// Get a blob from upstream
     #[deprecated(note = "Use get_blob_stream() to avoid buffering entire blob in memory")]
     pub async fn get_blob(
@@ -449,13 +989,94 @@ impl HarborClient {
             .and_then(|s| s.parse().ok())
             .unwrap_or(0);
 
-        // Convert reqwest's BytesStream to our ByteStream type
+        // Resume with the token the download was authorized with
+        let download = ResumableDownload {
+            client: self.client.clone(),
+            authorization: self.cached_token(&request_scope("GET", &url)),
+            url,
+            policy: self.config.retry.clone(),
+            size,
+            received: 0,
+            resumes: 0,
+            body: Box::pin(response.bytes_stream()),
+        };
+        let byte_stream: ByteStream = Box::pin(futures::stream::unfold(
+            download,
+            |mut download| async move { download.next().await.map(|item| (item, download)) },
+        ));
+
+        Ok((byte_stream, size))
+    }
+
+    /// Get a byte range of a blob as a stream, forwarding the `Range` header.
+    ///
+    /// Returns the stream, its length and the served range. Upstreams that
+    /// ignore the range answer with the full blob and no range.
+    pub async fn get_blob_range_stream(
+        &self,
+        repository: &str,
+        digest: &str,
+        range: &str,
+    ) -> Result<(ByteStream, u64, Option<ContentRange>), ProxyError> {
+        let full_repo = self.full_repository(repository);
+        let url = format!("{}/v2/{}/blobs/{}", self.config.url, full_repo, digest);
+
+        debug!("Fetching blob range {}: {}", range, url);
+
+        let response = self
+            .authenticated_request("GET", &url, vec![("Range", range)], None)
+            .await?;
+        let status = response.status();
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(str::to_string)
+        };
+
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProxyError::NotFound(digest.to_string()));
+        }
+
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            let size = header("content-range")
+                .and_then(|v| v.strip_prefix("bytes */").and_then(|s| s.parse().ok()));
+            return Err(ProxyError::RangeNotSatisfiable { size });
+        }
+
+        if !status.is_success() {
+            tracing::error!(
+                "Upstream error for blob range (status {}): {}",
+                status.as_u16(),
+                response.text().await.unwrap_or_default()
+            );
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                message: format!("Upstream returned HTTP {}", status.as_u16()),
+            });
+        }
+
+        let content_range = if status == StatusCode::PARTIAL_CONTENT {
+            let value = header("content-range").unwrap_or_default();
+            Some(ContentRange::parse(&value).ok_or_else(|| {
+                ProxyError::InvalidResponse(format!("Invalid Content-Range: {}", value))
+            })?)
+        } else {
+            None
+        };
+
+        let size = header("content-length")
+            .and_then(|s| s.parse().ok())
+            .or(content_range.map(|r| r.length()))
+            .unwrap_or(0);
+
         use futures::StreamExt;
         let stream = response.bytes_stream();
         let byte_stream: ByteStream =
             Box::pin(stream.map(|result| result.map_err(ProxyError::Http)));
 
-        Ok((byte_stream, size))
+        Ok((byte_stream, size, content_range))
     }
 
     /// Check if a blob exists
@@ -706,4 +1327,236 @@ impl HarborClient {
 
         Ok(digest)
     }
+
+    /// Delete a manifest (by tag or digest) from upstream
+    pub async fn delete_manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<(), ProxyError> {
+        let full_repo = self.full_repository(repository);
+        let url = format!(
+            "{}/v2/{}/manifests/{}",
+            self.config.url, full_repo, reference
+        );
+        self.delete(&url, &format!("{}:{}", repository, reference))
+            .await
+    }
+
+    /// Delete a blob from upstream
+    pub async fn delete_blob(&self, repository: &str, digest: &str) -> Result<(), ProxyError> {
+        let full_repo = self.full_repository(repository);
+        let url = format!("{}/v2/{}/blobs/{}", self.config.url, full_repo, digest);
+        self.delete(&url, &format!("{}@{}", repository, digest))
+            .await
+    }
+
+    /// Send a registry DELETE request; `what` names the target in errors
+    async fn delete(&self, url: &str, what: &str) -> Result<(), ProxyError> {
+        debug!("Deleting upstream: {}", url);
+
+        let response = self
+            .authenticated_request("DELETE", url, vec![], None)
+            .await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProxyError::NotFound(what.to_string()));
+        }
+
+        if !status.is_success() {
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_content_range() {
+        let range = ContentRange::parse("bytes 0-99/1000").unwrap();
+        assert_eq!(range.length(), 100);
+        assert_eq!(range.total, Some(1000));
+        assert_eq!(range.header_value(), "bytes 0-99/1000");
+
+        let range = ContentRange::parse("bytes 10-19/*").unwrap();
+        assert_eq!(range.total, None);
+
+        assert!(ContentRange::parse("bytes 20-10/100").is_none());
+        assert!(ContentRange::parse("bytes */100").is_none());
+    }
+
+    #[test]
+    fn test_request_scope() {
+        let url = "https://harbor.example.com/v2/library/nginx/manifests/latest";
+        assert_eq!(request_scope("GET", url), "repository:library/nginx:pull");
+        assert_eq!(request_scope("HEAD", url), "repository:library/nginx:pull");
+        assert_eq!(
+            request_scope("PUT", url),
+            "repository:library/nginx:pull,push"
+        );
+        assert_eq!(
+            request_scope("DELETE", url),
+            "repository:library/nginx:delete"
+        );
+        assert_eq!(
+            request_scope(
+                "PUT",
+                "https://harbor.example.com/v2/team/app/blobs/uploads/abc?_state=x&digest=sha256:1"
+            ),
+            "repository:team/app:pull,push"
+        );
+        assert_eq!(
+            request_scope("GET", "https://harbor.example.com/v2/_catalog?n=10"),
+            "registry:catalog:*"
+        );
+        assert_eq!(request_scope("GET", "https://harbor.example.com/v2/"), "");
+    }
+
+    #[test]
+    fn test_token_cache_honours_expiry() {
+        let client = HarborClient::new(HarborClientConfig {
+            url: "http://localhost:8880".to_string(),
+            registry: "library".to_string(),
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            retry: RetryPolicy::default(),
+        })
+        .unwrap();
+        let scope = "repository:library/nginx:pull";
+
+        client.store_token(scope.to_string(), "Bearer a".to_string(), Some(300));
+        assert_eq!(client.cached_token(scope), Some("Bearer a".to_string()));
+        assert_eq!(client.cached_token("repository:library/redis:pull"), None);
+
+        // Tokens about to expire are not cached
+        client.store_token(scope.to_string(), "Bearer b".to_string(), Some(5));
+        assert_eq!(client.cached_token(scope), Some("Bearer a".to_string()));
+
+        client.forget_token(scope);
+        assert_eq!(client.cached_token(scope), None);
+
+        // Expired tokens are dropped
+        client.tokens.lock().unwrap().insert(
+            scope.to_string(),
+            CachedToken {
+                header: "Bearer c".to_string(),
+                expires_at: Instant::now(),
+            },
+        );
+        assert_eq!(client.cached_token(scope), None);
+        assert!(client.tokens.lock().unwrap().is_empty());
+    }
+
+    /// Serve canned HTTP responses on a local port, one connection each,
+    /// and return the base URL and the request heads received
+    async fn serve(responses: Vec<&'static [u8]>) -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&head).to_lowercase());
+                socket.write_all(response).await.unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn retrying_client(url: String) -> HarborClient {
+        HarborClient::new(HarborClientConfig {
+            url,
+            registry: "library".to_string(),
+            username: None,
+            password: None,
+            skip_tls_verify: false,
+            retry: RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(10),
+            },
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_temporary_upstream_errors() {
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        assert!(retrying_client(url).ping().await.unwrap());
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        // Retry-After beyond the maximum backoff is not waited for
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 120\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        assert!(!retrying_client(url).ping().await.unwrap());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_blob_stream_resumes_interrupted_download() {
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 4-9/10\r\nContent-Length: 6\r\nConnection: close\r\n\r\n456789",
+        ])
+        .await;
+        let client = retrying_client(url);
+
+        let (stream, size) = client.get_blob_stream("nginx", "sha256:abc").await.unwrap();
+        assert_eq!(size, 10);
+        let chunks: Vec<Bytes> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks.concat(), b"0123456789");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("get /v2/library/nginx/blobs/sha256:abc"));
+        assert!(requests[1].contains("range: bytes=4-"));
+    }
+
+    #[test]
+    fn test_next_last_from_link() {
+        let link = "</v2/proj/alpine/tags/list?n=2&last=3.19>; rel=\"next\"";
+        assert_eq!(next_last_from_link(link), Some("3.19".to_string()));
+
+        assert_eq!(
+            next_last_from_link("</v2/x/tags/list?n=2>; rel=\"next\""),
+            None
+        );
+        assert_eq!(
+            next_last_from_link("</v2/x/tags/list?last=a>; rel=\"prev\""),
+            None
+        );
+    }
 }
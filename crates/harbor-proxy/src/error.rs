@@ -21,4 +21,20 @@ pub enum ProxyError {
 
     #[error("Token refresh failed")]
     TokenRefreshFailed,
+
+    /// The upstream rejected a byte range; carries the blob size if reported
+    #[error("Requested range not satisfiable")]
+    RangeNotSatisfiable { size: Option<u64> },
+}
+
+impl ProxyError {
+    /// Whether the error indicates the upstream is unavailable (connection
+    /// failures, timeouts, 5xx and 429 responses) rather than a definitive answer
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ProxyError::Http(_) => true,
+            ProxyError::UpstreamError { status, .. } => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
 }
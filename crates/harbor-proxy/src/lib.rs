@@ -5,6 +5,8 @@
 
 pub mod client;
 pub mod error;
+pub mod retry;
 
-pub use client::{HarborClient, HarborClientConfig};
+pub use client::{ContentRange, HarborClient, HarborClientConfig, RepositoryList, TagList};
 pub use error::ProxyError;
+pub use retry::RetryPolicy;
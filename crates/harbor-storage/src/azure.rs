@@ -0,0 +1,91 @@
+//! Azure Blob Storage backend
+//!
+//! Builds an [`ObjectStorage`] on an Azure Blob Storage container.
+//! Supports the Azurite emulator.
+
+use object_store::azure::MicrosoftAzureBuilder;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::error::StorageError;
+use crate::object::ObjectStorage;
+
+/// Azure Blob Storage configuration
+#[derive(Debug, Clone)]
+pub struct AzureConfig {
+    /// Storage account name
+    pub account: Option<String>,
+    /// Blob container name
+    pub container: String,
+    /// Storage account access key
+    pub access_key: Option<String>,
+    /// Blob service endpoint URL (for emulators or sovereign clouds)
+    pub endpoint: Option<String>,
+    /// Use the Azurite emulator with its well-known development account
+    pub use_emulator: bool,
+    /// Prefix for all objects (optional)
+    pub prefix: Option<String>,
+    /// Allow HTTP (not HTTPS) connections
+    pub allow_http: bool,
+}
+
+impl Default for AzureConfig {
+    fn default() -> Self {
+        Self {
+            account: None,
+            container: "harbor-cache".to_string(),
+            access_key: None,
+            endpoint: None,
+            use_emulator: false,
+            prefix: None,
+            allow_http: false,
+        }
+    }
+}
+
+impl ObjectStorage {
+    /// Create an Azure Blob Storage backend
+    ///
+    /// Without an access key, credentials are taken from the environment
+    /// (service principal, managed identity or Azure CLI). Presigned URLs
+    /// are service SAS URLs and require the access key.
+    pub async fn azure(config: AzureConfig) -> Result<Self, StorageError> {
+        let mut builder = MicrosoftAzureBuilder::from_env()
+            .with_container_name(&config.container)
+            .with_use_emulator(config.use_emulator);
+
+        if let Some(account) = &config.account {
+            builder = builder.with_account(account);
+        }
+        if let Some(access_key) = &config.access_key {
+            builder = builder.with_access_key(access_key);
+        }
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder.with_endpoint(endpoint.clone());
+        }
+
+        // Allow HTTP for local development (Azurite)
+        if config.allow_http {
+            builder = builder.with_allow_http(true);
+        }
+
+        let store = Arc::new(builder.build().map_err(|e| {
+            StorageError::Configuration(format!("Failed to create Azure client: {}", e))
+        })?);
+
+        info!(
+            "Initialized Azure storage: account={:?}, container={}, endpoint={:?}, prefix={}",
+            config.account,
+            config.container,
+            config.endpoint,
+            config.prefix.as_deref().unwrap_or_default()
+        );
+
+        Ok(Self::from_store(
+            store.clone(),
+            Some(store),
+            config.prefix,
+            "az",
+        ))
+    }
+}
@@ -4,12 +4,59 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use futures::Stream;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::error::StorageError;
 
 /// Type alias for a boxed stream of bytes
 pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, StorageError>> + Send>>;
 
+/// A part of a multipart chunked upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadedPart {
+    /// Part identifier returned by the store (the S3 ETag)
+    pub etag: String,
+    pub size: u64,
+}
+
+/// Backend state of a chunked upload that must survive between requests.
+///
+/// Backends that cannot append in place (S3) upload chunks as parts of a
+/// multipart upload; the caller persists this state with the upload session
+/// and passes it back on every call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkedUploadState {
+    /// Multipart upload id, set once the first part is uploaded
+    pub multipart_upload_id: Option<String>,
+    /// Uploaded parts, in order
+    pub parts: Vec<UploadedPart>,
+}
+
+impl ChunkedUploadState {
+    /// Bytes stored in uploaded parts
+    pub fn parts_size(&self) -> u64 {
+        self.parts.iter().map(|p| p.size).sum()
+    }
+}
+
+/// Temporary data of a chunked upload found in storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadFile {
+    pub session_id: String,
+    /// Bytes held in temporary objects (parts of an unfinished multipart
+    /// upload are not listed by the store and not counted)
+    pub size: u64,
+    pub last_modified: SystemTime,
+}
+
+/// A blob found in storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredBlob {
+    pub digest: String,
+    pub size: u64,
+}
+
 /// Storage backend trait
 ///
 /// Implementations of this trait provide content-addressable storage
@@ -31,6 +78,28 @@ pub trait StorageBackend: Send + Sync {
     /// Stream a blob
     async fn stream(&self, digest: &str) -> Result<ByteStream, StorageError>;
 
+    /// Stream an inclusive byte range of a blob.
+    ///
+    /// The default implementation buffers the range with `read_range`;
+    /// backends override it to avoid holding large ranges in memory.
+    async fn stream_range(
+        &self,
+        digest: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<ByteStream, StorageError> {
+        let data = self.read_range(digest, start, end).await?;
+        Ok(Box::pin(futures::stream::once(async move { Ok(data) })))
+    }
+
+    /// Stream a blob to verify its content.
+    ///
+    /// Unlike `stream`, this is not an access: tiered backends read the
+    /// copy they hold without promoting it.
+    async fn verify_stream(&self, digest: &str) -> Result<ByteStream, StorageError> {
+        self.stream(digest).await
+    }
+
     /// Write a blob (verifies digest after writing)
     async fn write(&self, digest: &str, data: Bytes) -> Result<String, StorageError>;
 
@@ -51,18 +120,35 @@ pub trait StorageBackend: Send + Sync {
     /// Initialize a chunked upload session, returns temp file path
     async fn init_chunked_upload(&self, session_id: &str) -> Result<String, StorageError>;
 
-    /// Append data to a chunked upload
-    async fn append_chunk(&self, session_id: &str, data: Bytes) -> Result<u64, StorageError>;
+    /// Append data to a chunked upload, updating its state; returns the
+    /// total number of bytes received so far
+    async fn append_chunk(
+        &self,
+        session_id: &str,
+        state: &mut ChunkedUploadState,
+        data: Bytes,
+    ) -> Result<u64, StorageError>;
 
     /// Complete a chunked upload, verify digest, move to final location
     async fn complete_chunked_upload(
         &self,
         session_id: &str,
+        state: &ChunkedUploadState,
         digest: &str,
     ) -> Result<String, StorageError>;
 
     /// Cancel a chunked upload
-    async fn cancel_chunked_upload(&self, session_id: &str) -> Result<(), StorageError>;
+    async fn cancel_chunked_upload(
+        &self,
+        session_id: &str,
+        state: &ChunkedUploadState,
+    ) -> Result<(), StorageError>;
+
+    /// List the temporary data of chunked uploads, one entry per session
+    async fn list_uploads(&self) -> Result<Vec<UploadFile>, StorageError>;
+
+    /// List all blobs held in storage
+    async fn list_blobs(&self) -> Result<Vec<StoredBlob>, StorageError>;
 
     /// Get a presigned URL for downloading a blob directly from storage
     ///
@@ -84,6 +170,12 @@ pub trait StorageBackend: Send + Sync {
         digest: &str,
         ttl_secs: u64,
     ) -> Result<Option<String>, StorageError>;
+
+    /// Open a namespace of this storage: a backend of the same kind whose
+    /// blobs and uploads are kept apart under `namespaces/<name>`.
+    ///
+    /// Blobs of a namespace are not listed by the parent backend.
+    async fn namespace(&self, name: &str) -> Result<Arc<dyn StorageBackend>, StorageError>;
 }
 
 /// Parse a digest string (e.g., "sha256:abc123...")
@@ -151,6 +243,23 @@ pub fn validate_digest(digest: &str) -> Result<(), StorageError> {
     Ok(())
 }
 
+/// Validate a storage namespace name.
+///
+/// Names become a path component, so only ASCII alphanumerics, dots,
+/// underscores and dashes are allowed, and `.` and `..` are rejected.
+pub fn validate_namespace(name: &str) -> Result<(), StorageError> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if name.is_empty() || name == "." || name == ".." || !valid_chars {
+        return Err(StorageError::Configuration(format!(
+            "Invalid storage namespace: {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
 /// Compute SHA256 digest of data
 pub fn compute_sha256(data: &[u8]) -> String {
     use sha2::{Digest, Sha256};
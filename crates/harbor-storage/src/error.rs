@@ -19,8 +19,8 @@ pub enum StorageError {
     #[error("Storage backend error: {0}")]
     Backend(String),
 
-    #[error("S3 error: {0}")]
-    S3(String),
+    #[error("Object store error: {0}")]
+    ObjectStore(String),
 
     #[error("Configuration error: {0}")]
     Configuration(String),
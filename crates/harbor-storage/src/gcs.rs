@@ -0,0 +1,80 @@
+//! Google Cloud Storage backend
+//!
+//! Builds an [`ObjectStorage`] on a GCS bucket. Emulators such as
+//! fake-gcs-server are reached through a service account file that sets
+//! `gcs_base_url` and `disable_oauth`.
+
+use object_store::ClientOptions;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::error::StorageError;
+use crate::object::ObjectStorage;
+
+/// GCS storage configuration
+#[derive(Debug, Clone)]
+pub struct GcsConfig {
+    /// GCS bucket name
+    pub bucket: String,
+    /// Path to a service account JSON file
+    pub service_account_path: Option<String>,
+    /// Service account JSON (alternative to `service_account_path`)
+    pub service_account_key: Option<String>,
+    /// Prefix for all objects (optional)
+    pub prefix: Option<String>,
+    /// Allow HTTP (not HTTPS) connections
+    pub allow_http: bool,
+}
+
+impl Default for GcsConfig {
+    fn default() -> Self {
+        Self {
+            bucket: "harbor-cache".to_string(),
+            service_account_path: None,
+            service_account_key: None,
+            prefix: None,
+            allow_http: false,
+        }
+    }
+}
+
+impl ObjectStorage {
+    /// Create a GCS storage backend
+    ///
+    /// Without a service account, credentials are taken from the environment
+    /// or application default credentials.
+    /// Presigned URLs require credentials holding a private key.
+    pub async fn gcs(config: GcsConfig) -> Result<Self, StorageError> {
+        let mut builder = GoogleCloudStorageBuilder::from_env().with_bucket_name(&config.bucket);
+
+        if let Some(path) = &config.service_account_path {
+            builder = builder.with_service_account_path(path);
+        }
+        if let Some(key) = &config.service_account_key {
+            builder = builder.with_service_account_key(key);
+        }
+
+        // Allow HTTP for local development (fake-gcs-server)
+        if config.allow_http {
+            builder = builder.with_client_options(ClientOptions::new().with_allow_http(true));
+        }
+
+        let store = Arc::new(builder.build().map_err(|e| {
+            StorageError::Configuration(format!("Failed to create GCS client: {}", e))
+        })?);
+
+        info!(
+            "Initialized GCS storage: bucket={}, prefix={}",
+            config.bucket,
+            config.prefix.as_deref().unwrap_or_default()
+        );
+
+        Ok(Self::from_store(
+            store.clone(),
+            Some(store),
+            config.prefix,
+            "gs",
+        ))
+    }
+}
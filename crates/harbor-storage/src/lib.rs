@@ -1,14 +1,23 @@
 //! Harbor Cache Storage Layer
 //!
 //! This crate provides storage abstraction for Harbor Cache,
-//! supporting local disk and S3-compatible backends.
+//! supporting local disk, S3-compatible, Google Cloud Storage and Azure
+//! Blob Storage backends, and a tiered combination of local disk and S3.
 
+pub mod azure;
 pub mod backend;
 pub mod error;
+pub mod gcs;
 pub mod local;
+pub mod object;
 pub mod s3;
+pub mod tiered;
 
-pub use backend::StorageBackend;
+pub use azure::AzureConfig;
+pub use backend::{ChunkedUploadState, StorageBackend, StoredBlob, UploadFile, UploadedPart};
 pub use error::StorageError;
+pub use gcs::GcsConfig;
 pub use local::LocalStorage;
-pub use s3::{S3Config, S3Storage};
+pub use object::ObjectStorage;
+pub use s3::S3Config;
+pub use tiered::{DemotionPolicy, TieredConfig, TieredStorage};
@@ -5,17 +5,23 @@ use bytes::Bytes;
 use futures::StreamExt;
 use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs::{self, File};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
 use tracing::{debug, info};
 
-use crate::backend::{ByteStream, StorageBackend, compute_sha256, parse_digest};
+use crate::backend::{
+    ByteStream, ChunkedUploadState, StorageBackend, StoredBlob, UploadFile, compute_sha256,
+    parse_digest, validate_namespace,
+};
 use crate::error::StorageError;
 
 /// Local disk storage backend
 ///
 /// Stores blobs in a content-addressable directory structure:
 /// `<base_path>/blobs/<algorithm>/<first 2 chars>/<digest>`
+///
+/// Namespaces live in `<base_path>/namespaces/<name>` with the same layout.
 pub struct LocalStorage {
     base_path: PathBuf,
     uploads_path: PathBuf,
@@ -41,7 +47,7 @@ impl LocalStorage {
     }
 
     /// Get the file path for a digest
-    fn blob_path(&self, digest: &str) -> Result<PathBuf, StorageError> {
+    pub(crate) fn blob_path(&self, digest: &str) -> Result<PathBuf, StorageError> {
         let (algorithm, hash) = parse_digest(digest)?;
 
         if hash.len() < 2 {
@@ -143,6 +149,34 @@ impl StorageBackend for LocalStorage {
         ))
     }
 
+    async fn stream_range(
+        &self,
+        digest: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<ByteStream, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!("Streaming blob range {}-{} from {:?}", start, end, path);
+
+        let mut file = File::open(&path).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                StorageError::NotFound(digest.to_string())
+            } else {
+                StorageError::Io(e)
+            }
+        })?;
+
+        use tokio::io::AsyncSeekExt;
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+
+        let reader = BufReader::new(file.take(end - start + 1));
+        let stream = tokio_util::io::ReaderStream::new(reader);
+
+        Ok(Box::pin(
+            stream.map(|result| result.map_err(StorageError::Io)),
+        ))
+    }
+
     async fn write(&self, digest: &str, data: Bytes) -> Result<String, StorageError> {
         // Verify digest
         let computed = compute_sha256(&data);
@@ -240,7 +274,12 @@ impl StorageBackend for LocalStorage {
         Ok(path.to_string_lossy().to_string())
     }
 
-    async fn append_chunk(&self, session_id: &str, data: Bytes) -> Result<u64, StorageError> {
+    async fn append_chunk(
+        &self,
+        session_id: &str,
+        _state: &mut ChunkedUploadState,
+        data: Bytes,
+    ) -> Result<u64, StorageError> {
         let path = self.upload_path(session_id);
         debug!("Appending {} bytes to upload {:?}", data.len(), path);
 
@@ -267,6 +306,7 @@ impl StorageBackend for LocalStorage {
     async fn complete_chunked_upload(
         &self,
         session_id: &str,
+        _state: &ChunkedUploadState,
         digest: &str,
     ) -> Result<String, StorageError> {
         let upload_path = self.upload_path(session_id);
@@ -319,7 +359,11 @@ impl StorageBackend for LocalStorage {
         Ok(blob_path.to_string_lossy().to_string())
     }
 
-    async fn cancel_chunked_upload(&self, session_id: &str) -> Result<(), StorageError> {
+    async fn cancel_chunked_upload(
+        &self,
+        session_id: &str,
+        _state: &ChunkedUploadState,
+    ) -> Result<(), StorageError> {
         let path = self.upload_path(session_id);
         debug!("Canceling chunked upload at {:?}", path);
 
@@ -330,6 +374,58 @@ impl StorageBackend for LocalStorage {
         }
     }
 
+    async fn list_uploads(&self) -> Result<Vec<UploadFile>, StorageError> {
+        let mut uploads = Vec::new();
+        let mut entries = fs::read_dir(&self.uploads_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            uploads.push(UploadFile {
+                session_id: entry.file_name().to_string_lossy().to_string(),
+                size: metadata.len(),
+                last_modified: metadata.modified()?,
+            });
+        }
+        Ok(uploads)
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<StoredBlob>, StorageError> {
+        let mut blobs = Vec::new();
+        let mut algorithms = fs::read_dir(self.base_path.join("blobs")).await?;
+        while let Some(algorithm) = algorithms.next_entry().await? {
+            if !algorithm.file_type().await?.is_dir() {
+                continue;
+            }
+            let algorithm_name = algorithm.file_name().to_string_lossy().to_string();
+            let mut shards = fs::read_dir(algorithm.path()).await?;
+            while let Some(shard) = shards.next_entry().await? {
+                if !shard.file_type().await?.is_dir() {
+                    continue;
+                }
+                let mut files = fs::read_dir(shard.path()).await?;
+                while let Some(file) = files.next_entry().await? {
+                    let metadata = file.metadata().await?;
+                    let path = file.path();
+                    // Skip temp files of in-progress writes
+                    if !metadata.is_file() || path.extension().is_some() {
+                        continue;
+                    }
+                    blobs.push(StoredBlob {
+                        digest: format!(
+                            "{}:{}",
+                            algorithm_name,
+                            file.file_name().to_string_lossy()
+                        ),
+                        size: metadata.len(),
+                    });
+                }
+            }
+        }
+        Ok(blobs)
+    }
+
     async fn get_presigned_url(
         &self,
         _digest: &str,
@@ -339,4 +435,10 @@ impl StorageBackend for LocalStorage {
         // Return None to indicate graceful fallback to streaming
         Ok(None)
     }
+
+    async fn namespace(&self, name: &str) -> Result<Arc<dyn StorageBackend>, StorageError> {
+        validate_namespace(name)?;
+        let storage = LocalStorage::new(self.base_path.join("namespaces").join(name)).await?;
+        Ok(Arc::new(storage))
+    }
 }
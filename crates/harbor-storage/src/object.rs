@@ -0,0 +1,859 @@
+//! Object store storage backend
+//!
+//! Stores blobs in a cloud object store through the `object_store` crate.
+//! The S3, GCS and Azure backends share this implementation and only
+//! differ in how the store is built (see the `s3`, `gcs` and `azure`
+//! modules).
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use object_store::multipart::{MultipartStore, PartId};
+use object_store::path::Path as ObjectPath;
+use object_store::signer::Signer;
+use object_store::{GetOptions, GetRange, ObjectStore, PutPayload};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+use crate::backend::{
+    ByteStream, ChunkedUploadState, StorageBackend, StoredBlob, UploadFile, UploadedPart,
+    compute_sha256, parse_digest, validate_namespace,
+};
+use crate::error::StorageError;
+
+/// Minimum size of every multipart part except the last (S3 and GCS limit)
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Maximum size of a single uploaded part (S3 allows up to 5 GiB)
+const MAX_PART_SIZE: usize = 512 * 1024 * 1024;
+
+/// An object store supporting multipart uploads
+pub(crate) trait CloudStore: ObjectStore + MultipartStore {}
+
+impl<T: ObjectStore + MultipartStore> CloudStore for T {}
+
+/// Object store storage backend
+///
+/// Stores blobs in a bucket with content-addressable paths:
+/// `<prefix>/blobs/<algorithm>/<first 2 chars>/<digest>`
+///
+/// Namespaces use `<prefix>/namespaces/<name>` as their prefix.
+pub struct ObjectStorage {
+    store: Arc<dyn CloudStore>,
+    /// Generates presigned URLs (None if the store cannot sign)
+    signer: Option<Arc<dyn Signer>>,
+    prefix: String,
+    /// URL scheme of reported storage paths (e.g. "s3")
+    scheme: &'static str,
+}
+
+impl ObjectStorage {
+    /// Wrap a configured object store
+    pub(crate) fn from_store(
+        store: Arc<dyn CloudStore>,
+        signer: Option<Arc<dyn Signer>>,
+        prefix: Option<String>,
+        scheme: &'static str,
+    ) -> Self {
+        Self {
+            store,
+            signer,
+            prefix: prefix.unwrap_or_default(),
+            scheme,
+        }
+    }
+
+    /// Get the object path for a blob digest
+    fn blob_path(&self, digest: &str) -> Result<ObjectPath, StorageError> {
+        let (algorithm, hash) = parse_digest(digest)?;
+
+        if hash.len() < 2 {
+            return Err(StorageError::InvalidDigest(format!(
+                "Hash too short: {}",
+                digest
+            )));
+        }
+
+        // Use first 2 characters for sharding
+        let shard = &hash[..2];
+        let path = if self.prefix.is_empty() {
+            format!("blobs/{}/{}/{}", algorithm, shard, hash)
+        } else {
+            format!("{}/blobs/{}/{}/{}", self.prefix, algorithm, shard, hash)
+        };
+
+        ObjectPath::parse(&path)
+            .map_err(|e| StorageError::InvalidDigest(format!("Invalid path: {}", e)))
+    }
+
+    /// Get the object path prefix under which upload sessions are stored
+    fn uploads_prefix(&self) -> ObjectPath {
+        if self.prefix.is_empty() {
+            ObjectPath::from("uploads")
+        } else {
+            ObjectPath::from(format!("{}/uploads", self.prefix))
+        }
+    }
+
+    /// Get the object path prefix under which blobs are stored
+    fn blobs_prefix(&self) -> ObjectPath {
+        if self.prefix.is_empty() {
+            ObjectPath::from("blobs")
+        } else {
+            ObjectPath::from(format!("{}/blobs", self.prefix))
+        }
+    }
+
+    /// Get the object path for an upload session.
+    ///
+    /// Holds chunk data not yet uploaded as a multipart part (less than
+    /// the minimum part size), or the whole blob for small uploads.
+    fn upload_path(&self, session_id: &str) -> ObjectPath {
+        let path = if self.prefix.is_empty() {
+            format!("uploads/{}", session_id)
+        } else {
+            format!("{}/uploads/{}", self.prefix, session_id)
+        };
+
+        ObjectPath::from(path)
+    }
+
+    /// Get the object path targeted by the multipart upload of a session
+    fn multipart_path(&self, session_id: &str) -> ObjectPath {
+        let path = if self.prefix.is_empty() {
+            format!("uploads/{}.multipart", session_id)
+        } else {
+            format!("{}/uploads/{}.multipart", self.prefix, session_id)
+        };
+
+        ObjectPath::from(path)
+    }
+
+    /// Read the buffered (not yet uploaded) data of an upload session
+    async fn read_pending(&self, session_id: &str) -> Result<Bytes, StorageError> {
+        match self.store.get(&self.upload_path(session_id)).await {
+            Ok(result) => result.bytes().await.map_err(|e| {
+                StorageError::ObjectStore(format!("Failed to read pending data: {}", e))
+            }),
+            Err(object_store::Error::NotFound { .. }) => Ok(Bytes::new()),
+            Err(e) => Err(StorageError::ObjectStore(e.to_string())),
+        }
+    }
+
+    /// Upload one part of a session's multipart upload, starting the
+    /// multipart upload on the first part
+    async fn upload_part(
+        &self,
+        session_id: &str,
+        state: &mut ChunkedUploadState,
+        data: Bytes,
+    ) -> Result<(), StorageError> {
+        let path = self.multipart_path(session_id);
+        let upload_id = match &state.multipart_upload_id {
+            Some(id) => id.clone(),
+            None => {
+                let id = self.store.create_multipart(&path).await.map_err(|e| {
+                    StorageError::ObjectStore(format!("Failed to start multipart upload: {}", e))
+                })?;
+                state.multipart_upload_id = Some(id.clone());
+                id
+            }
+        };
+
+        let size = data.len() as u64;
+        let part = self
+            .store
+            .put_part(&path, &upload_id, state.parts.len(), PutPayload::from(data))
+            .await
+            .map_err(|e| StorageError::ObjectStore(format!("Failed to upload part: {}", e)))?;
+
+        debug!(
+            "Uploaded part {} ({} bytes) of upload {:?}",
+            state.parts.len() + 1,
+            size,
+            path
+        );
+        state.parts.push(UploadedPart {
+            etag: part.content_id,
+            size,
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ObjectStorage {
+    async fn exists(&self, digest: &str) -> Result<bool, StorageError> {
+        let path = self.blob_path(digest)?;
+
+        match self.store.head(&path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(StorageError::ObjectStore(e.to_string())),
+        }
+    }
+
+    async fn size(&self, digest: &str) -> Result<u64, StorageError> {
+        let path = self.blob_path(digest)?;
+
+        let meta = self.store.head(&path).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => StorageError::NotFound(digest.to_string()),
+            _ => StorageError::ObjectStore(e.to_string()),
+        })?;
+
+        Ok(meta.size as u64)
+    }
+
+    async fn read(&self, digest: &str) -> Result<Bytes, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!("Reading blob from object store: {:?}", path);
+
+        let result = self.store.get(&path).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => StorageError::NotFound(digest.to_string()),
+            _ => StorageError::ObjectStore(e.to_string()),
+        })?;
+
+        let bytes = result
+            .bytes()
+            .await
+            .map_err(|e| StorageError::ObjectStore(format!("Failed to read bytes: {}", e)))?;
+
+        Ok(bytes)
+    }
+
+    async fn read_range(&self, digest: &str, start: u64, end: u64) -> Result<Bytes, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!(
+            "Reading blob range {}-{} from object store: {:?}",
+            start, end, path
+        );
+
+        let range = std::ops::Range {
+            start: start as usize,
+            end: (end + 1) as usize,
+        };
+
+        let result = self
+            .store
+            .get_range(&path, range)
+            .await
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => StorageError::NotFound(digest.to_string()),
+                _ => StorageError::ObjectStore(e.to_string()),
+            })?;
+
+        Ok(result)
+    }
+
+    async fn stream(&self, digest: &str) -> Result<ByteStream, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!("Streaming blob from object store: {:?}", path);
+
+        let result = self.store.get(&path).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => StorageError::NotFound(digest.to_string()),
+            _ => StorageError::ObjectStore(e.to_string()),
+        })?;
+
+        let stream = result
+            .into_stream()
+            .map_err(|e| StorageError::ObjectStore(format!("Stream error: {}", e)));
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn stream_range(
+        &self,
+        digest: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<ByteStream, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!(
+            "Streaming blob range {}-{} from object store: {:?}",
+            start, end, path
+        );
+
+        let options = GetOptions {
+            range: Some(GetRange::Bounded(start as usize..(end + 1) as usize)),
+            ..Default::default()
+        };
+        let result = self
+            .store
+            .get_opts(&path, options)
+            .await
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => StorageError::NotFound(digest.to_string()),
+                _ => StorageError::ObjectStore(e.to_string()),
+            })?;
+
+        let stream = result
+            .into_stream()
+            .map_err(|e| StorageError::ObjectStore(format!("Stream error: {}", e)));
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn write(&self, digest: &str, data: Bytes) -> Result<String, StorageError> {
+        // Verify digest
+        let computed = compute_sha256(&data);
+        if computed != digest {
+            return Err(StorageError::DigestMismatch {
+                expected: digest.to_string(),
+                actual: computed,
+            });
+        }
+
+        let path = self.blob_path(digest)?;
+        debug!("Writing blob to object store: {:?}", path);
+
+        self.store
+            .put(&path, PutPayload::from(data))
+            .await
+            .map_err(|e| StorageError::ObjectStore(e.to_string()))?;
+
+        Ok(path.to_string())
+    }
+
+    async fn write_stream(
+        &self,
+        digest: &str,
+        mut stream: ByteStream,
+        _expected_size: Option<u64>,
+    ) -> Result<String, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!("Writing blob stream to object store: {:?}", path);
+
+        // Use a multipart upload to avoid buffering entire blob in memory
+        let mut upload = self.store.put_multipart(&path).await.map_err(|e| {
+            StorageError::ObjectStore(format!("Failed to start multipart upload: {}", e))
+        })?;
+
+        let mut hasher = Sha256::new();
+        let mut buffer = Vec::with_capacity(5 * 1024 * 1024); // 5MB minimum part size
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            buffer.extend_from_slice(&chunk);
+
+            // Upload part when buffer reaches minimum size (5MB)
+            // Last part can be smaller
+            if buffer.len() >= 5 * 1024 * 1024 {
+                upload
+                    .put_part(PutPayload::from(Bytes::from(std::mem::take(&mut buffer))))
+                    .await
+                    .map_err(|e| {
+                        StorageError::ObjectStore(format!("Failed to upload part: {}", e))
+                    })?;
+                buffer = Vec::with_capacity(5 * 1024 * 1024);
+            }
+        }
+
+        // Upload remaining data as final part
+        if !buffer.is_empty() {
+            upload
+                .put_part(PutPayload::from(Bytes::from(buffer)))
+                .await
+                .map_err(|e| {
+                    StorageError::ObjectStore(format!("Failed to upload final part: {}", e))
+                })?;
+        }
+
+        // Complete multipart upload
+        upload.complete().await.map_err(|e| {
+            StorageError::ObjectStore(format!("Failed to complete multipart upload: {}", e))
+        })?;
+
+        // Verify digest
+        let computed = format!("sha256:{}", hex::encode(hasher.finalize()));
+        if computed != digest {
+            // Clean up the uploaded object, log failure if cleanup fails
+            if let Err(e) = self.store.delete(&path).await {
+                warn!(
+                    "Failed to clean up object after digest mismatch (path: {:?}): {}",
+                    path, e
+                );
+            }
+            return Err(StorageError::DigestMismatch {
+                expected: digest.to_string(),
+                actual: computed,
+            });
+        }
+
+        Ok(path.to_string())
+    }
+
+    async fn delete(&self, digest: &str) -> Result<bool, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!("Deleting blob from object store: {:?}", path);
+
+        // Check if exists first
+        let exists = self.exists(digest).await?;
+        if !exists {
+            return Ok(false);
+        }
+
+        self.store
+            .delete(&path)
+            .await
+            .map_err(|e| StorageError::ObjectStore(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    fn storage_path(&self, digest: &str) -> String {
+        self.blob_path(digest)
+            .map(|p| format!("{}://{}", self.scheme, p))
+            .unwrap_or_default()
+    }
+
+    async fn init_chunked_upload(&self, session_id: &str) -> Result<String, StorageError> {
+        let path = self.upload_path(session_id);
+        debug!("Initializing chunked upload at object store: {:?}", path);
+
+        // Create empty object to mark upload session
+        self.store
+            .put(&path, PutPayload::from(Bytes::new()))
+            .await
+            .map_err(|e| StorageError::ObjectStore(e.to_string()))?;
+
+        Ok(path.to_string())
+    }
+
+    async fn append_chunk(
+        &self,
+        session_id: &str,
+        state: &mut ChunkedUploadState,
+        data: Bytes,
+    ) -> Result<u64, StorageError> {
+        let path = self.upload_path(session_id);
+        debug!(
+            "Appending {} bytes to object store upload: {:?}",
+            data.len(),
+            path
+        );
+
+        // Objects cannot be appended to: chunks are buffered in the
+        // upload object until they add up to a multipart part
+        let pending = self.read_pending(session_id).await?;
+        let mut buffer = if pending.is_empty() {
+            data
+        } else {
+            let mut combined = Vec::with_capacity(pending.len() + data.len());
+            combined.extend_from_slice(&pending);
+            combined.extend_from_slice(&data);
+            Bytes::from(combined)
+        };
+
+        let mut uploaded = false;
+        while buffer.len() >= MIN_PART_SIZE {
+            let part = buffer.split_to(buffer.len().min(MAX_PART_SIZE));
+            self.upload_part(session_id, state, part).await?;
+            uploaded = true;
+        }
+
+        // Keep the remainder buffered (emptied once it went into a part)
+        let pending_size = buffer.len() as u64;
+        if uploaded || pending_size > 0 {
+            self.store
+                .put(&path, PutPayload::from(buffer))
+                .await
+                .map_err(|e| StorageError::ObjectStore(e.to_string()))?;
+        }
+
+        Ok(state.parts_size() + pending_size)
+    }
+
+    async fn complete_chunked_upload(
+        &self,
+        session_id: &str,
+        state: &ChunkedUploadState,
+        digest: &str,
+    ) -> Result<String, StorageError> {
+        let upload_path = self.upload_path(session_id);
+        let blob_path = self.blob_path(digest)?;
+
+        debug!(
+            "Completing chunked upload {:?} -> {:?} ({} parts)",
+            upload_path,
+            blob_path,
+            state.parts.len()
+        );
+
+        // Finish the multipart upload with the buffered data as last part;
+        // uploads smaller than one part live entirely in the upload object
+        let source_path = match &state.multipart_upload_id {
+            Some(upload_id) => {
+                let mut state = state.clone();
+                let pending = self.read_pending(session_id).await?;
+                if !pending.is_empty() {
+                    self.upload_part(session_id, &mut state, pending).await?;
+                }
+
+                let path = self.multipart_path(session_id);
+                let parts = state
+                    .parts
+                    .iter()
+                    .map(|p| PartId {
+                        content_id: p.etag.clone(),
+                    })
+                    .collect();
+                self.store
+                    .complete_multipart(&path, upload_id, parts)
+                    .await
+                    .map_err(|e| {
+                        StorageError::ObjectStore(format!(
+                            "Failed to complete multipart upload: {}",
+                            e
+                        ))
+                    })?;
+                path
+            }
+            None => upload_path.clone(),
+        };
+
+        // Stream uploaded data to compute digest without buffering entire blob
+        let result = self.store.get(&source_path).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => {
+                StorageError::NotFound(format!("Upload session: {}", session_id))
+            }
+            _ => StorageError::ObjectStore(e.to_string()),
+        })?;
+
+        let mut stream = result.into_stream();
+        let mut hasher = Sha256::new();
+
+        // Stream through data to compute digest without buffering
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = chunk_result
+                .map_err(|e| StorageError::ObjectStore(format!("Failed to read chunk: {}", e)))?;
+            hasher.update(&chunk);
+        }
+
+        // Verify digest
+        let computed = format!("sha256:{}", hex::encode(hasher.finalize()));
+        if computed != digest {
+            // Clean up, log failure if cleanup fails
+            for path in [&upload_path, &source_path] {
+                if let Err(e) = self.store.delete(path).await {
+                    warn!(
+                        "Failed to clean up upload after digest mismatch (path: {:?}): {}",
+                        path, e
+                    );
+                }
+            }
+            return Err(StorageError::DigestMismatch {
+                expected: digest.to_string(),
+                actual: computed,
+            });
+        }
+
+        // Use a server-side copy to move to final location without re-downloading
+        self.store
+            .copy(&source_path, &blob_path)
+            .await
+            .map_err(|e| {
+                StorageError::ObjectStore(format!("Failed to copy to final location: {}", e))
+            })?;
+
+        // Delete upload objects
+        for path in [&upload_path, &source_path] {
+            if let Err(e) = self.store.delete(path).await {
+                warn!(
+                    "Failed to delete upload temp file after completion (path: {:?}): {}",
+                    path, e
+                );
+            }
+        }
+
+        Ok(blob_path.to_string())
+    }
+
+    async fn cancel_chunked_upload(
+        &self,
+        session_id: &str,
+        state: &ChunkedUploadState,
+    ) -> Result<(), StorageError> {
+        let path = self.upload_path(session_id);
+        debug!("Canceling chunked upload: {:?}", path);
+
+        if let Some(upload_id) = &state.multipart_upload_id
+            && let Err(e) = self
+                .store
+                .abort_multipart(&self.multipart_path(session_id), upload_id)
+                .await
+        {
+            warn!(
+                "Failed to abort multipart upload for session {}: {}",
+                session_id, e
+            );
+        }
+
+        // The multipart target only outlives its session if a completion
+        // was interrupted before cleanup
+        for path in [path, self.multipart_path(session_id)] {
+            match self.store.delete(&path).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(StorageError::ObjectStore(e.to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<UploadFile>, StorageError> {
+        let objects: Vec<_> = self
+            .store
+            .list(Some(&self.uploads_prefix()))
+            .try_collect()
+            .await
+            .map_err(|e| StorageError::ObjectStore(e.to_string()))?;
+
+        // Group the pending-chunk object and the multipart target by session
+        let mut uploads: BTreeMap<String, UploadFile> = BTreeMap::new();
+        for object in objects {
+            let Some(name) = object.location.filename() else {
+                continue;
+            };
+            let session_id = name.strip_suffix(".multipart").unwrap_or(name);
+            let last_modified = SystemTime::from(object.last_modified);
+            let upload = uploads
+                .entry(session_id.to_string())
+                .or_insert_with(|| UploadFile {
+                    session_id: session_id.to_string(),
+                    size: 0,
+                    last_modified,
+                });
+            upload.size += object.size as u64;
+            upload.last_modified = upload.last_modified.max(last_modified);
+        }
+        Ok(uploads.into_values().collect())
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<StoredBlob>, StorageError> {
+        let objects: Vec<_> = self
+            .store
+            .list(Some(&self.blobs_prefix()))
+            .try_collect()
+            .await
+            .map_err(|e| StorageError::ObjectStore(e.to_string()))?;
+
+        // Paths end in <algorithm>/<shard>/<hash>
+        Ok(objects
+            .into_iter()
+            .filter_map(|object| {
+                let parts: Vec<_> = object.location.parts().collect();
+                let [.., algorithm, _shard, hash] = parts.as_slice() else {
+                    return None;
+                };
+                Some(StoredBlob {
+                    digest: format!("{}:{}", algorithm.as_ref(), hash.as_ref()),
+                    size: object.size as u64,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_presigned_url(
+        &self,
+        digest: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<String>, StorageError> {
+        let path = self.blob_path(digest)?;
+        debug!(
+            "Generating presigned URL for blob: {:?}, TTL: {}s",
+            path, ttl_secs
+        );
+
+        let Some(signer) = &self.signer else {
+            return Ok(None);
+        };
+        let url = signer
+            .signed_url(http::Method::GET, &path, Duration::from_secs(ttl_secs))
+            .await
+            .map_err(|e| {
+                StorageError::ObjectStore(format!("Failed to generate presigned URL: {}", e))
+            })?;
+
+        // Note: We intentionally do NOT log the full presigned URL as it contains
+        // sensitive signing information. Only log that a URL was generated.
+        debug!("Generated presigned URL for blob: {}", digest);
+        Ok(Some(url.to_string()))
+    }
+
+    async fn namespace(&self, name: &str) -> Result<Arc<dyn StorageBackend>, StorageError> {
+        validate_namespace(name)?;
+        let prefix = if self.prefix.is_empty() {
+            format!("namespaces/{}", name)
+        } else {
+            format!("{}/namespaces/{}", self.prefix, name)
+        };
+        Ok(Arc::new(Self {
+            store: self.store.clone(),
+            signer: self.signer.clone(),
+            prefix,
+            scheme: self.scheme,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_path() {
+        // Path generation is also covered against an in-memory store below
+        let digest = "sha256:abc123def456";
+        let (algo, hash) = parse_digest(digest).unwrap();
+        assert_eq!(algo, "sha256");
+        assert_eq!(hash, "abc123def456");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_upload_through_multipart() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        let storage =
+            ObjectStorage::from_store(store.clone(), None, Some("cache".to_string()), "mem");
+
+        // Large enough to be sent as a multipart part plus a buffered tail
+        let data = Bytes::from(vec![7u8; MIN_PART_SIZE + 1024]);
+        let digest = compute_sha256(&data);
+
+        let mut state = ChunkedUploadState::default();
+        storage.init_chunked_upload("session").await.unwrap();
+        let received = storage
+            .append_chunk("session", &mut state, data.clone())
+            .await
+            .unwrap();
+        assert_eq!(received, data.len() as u64);
+        assert_eq!(state.parts.len(), 1);
+        assert!(state.multipart_upload_id.is_some());
+
+        storage
+            .complete_chunked_upload("session", &state, &digest)
+            .await
+            .unwrap();
+        assert_eq!(storage.read(&digest).await.unwrap(), data);
+        assert!(storage.list_uploads().await.unwrap().is_empty());
+
+        let blobs = storage.list_blobs().await.unwrap();
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(blobs[0].digest, digest);
+        assert_eq!(blobs[0].size, data.len() as u64);
+
+        let hash = digest.strip_prefix("sha256:").unwrap();
+        assert_eq!(
+            storage.storage_path(&digest),
+            format!("mem://cache/blobs/sha256/{}/{}", &hash[..2], hash)
+        );
+
+        // Stores without a signer cannot redirect
+        assert!(
+            storage
+                .get_presigned_url(&digest, 60)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_small_chunks_are_buffered_into_parts() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        let storage = ObjectStorage::from_store(store, None, None, "mem");
+        let chunk = |byte: u8, size: usize| Bytes::from(vec![byte; size]);
+
+        // Chunks below the part minimum stay buffered until they add up
+        let mut state = ChunkedUploadState::default();
+        let mut data = Vec::new();
+        storage.init_chunked_upload("session").await.unwrap();
+        for (idx, byte) in [1u8, 2, 3].into_iter().enumerate() {
+            let part = chunk(byte, 2 * 1024 * 1024);
+            data.extend_from_slice(&part);
+            let received = storage
+                .append_chunk("session", &mut state, part)
+                .await
+                .unwrap();
+            assert_eq!(received, data.len() as u64);
+            assert_eq!(state.parts.len(), idx / 2);
+        }
+        assert_eq!(state.parts_size(), 6 * 1024 * 1024);
+
+        // The trailing chunk is buffered and sent as the last part
+        let tail = chunk(4, 1024);
+        data.extend_from_slice(&tail);
+        storage
+            .append_chunk("session", &mut state, tail)
+            .await
+            .unwrap();
+        assert_eq!(state.parts.len(), 1);
+        let digest = compute_sha256(&data);
+        storage
+            .complete_chunked_upload("session", &state, &digest)
+            .await
+            .unwrap();
+        assert_eq!(storage.read(&digest).await.unwrap(), data);
+
+        // Uploads smaller than one part never start a multipart upload
+        let mut state = ChunkedUploadState::default();
+        let small = chunk(5, 1024);
+        storage.init_chunked_upload("small").await.unwrap();
+        storage
+            .append_chunk("small", &mut state, small.clone())
+            .await
+            .unwrap();
+        assert!(state.multipart_upload_id.is_none());
+        let digest = compute_sha256(&small);
+        storage
+            .complete_chunked_upload("small", &state, &digest)
+            .await
+            .unwrap();
+        assert_eq!(storage.read(&digest).await.unwrap(), small);
+
+        // Cancelling aborts the multipart upload and drops the buffer
+        let mut state = ChunkedUploadState::default();
+        storage.init_chunked_upload("cancelled").await.unwrap();
+        storage
+            .append_chunk("cancelled", &mut state, chunk(6, MIN_PART_SIZE + 1))
+            .await
+            .unwrap();
+        assert!(state.multipart_upload_id.is_some());
+        storage
+            .cancel_chunked_upload("cancelled", &state)
+            .await
+            .unwrap();
+        assert!(storage.list_uploads().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_are_separate() {
+        let store = Arc::new(object_store::memory::InMemory::new());
+        let storage =
+            ObjectStorage::from_store(store.clone(), None, Some("cache".to_string()), "mem");
+        let namespace = storage.namespace("team-a").await.unwrap();
+
+        let data = Bytes::from_static(b"isolated layer");
+        let digest = compute_sha256(&data);
+        namespace.write(&digest, data.clone()).await.unwrap();
+
+        assert_eq!(namespace.read(&digest).await.unwrap(), data);
+        assert!(!storage.exists(&digest).await.unwrap());
+        assert!(storage.list_blobs().await.unwrap().is_empty());
+        assert_eq!(namespace.list_blobs().await.unwrap().len(), 1);
+
+        let hash = digest.strip_prefix("sha256:").unwrap();
+        assert_eq!(
+            namespace.storage_path(&digest),
+            format!(
+                "mem://cache/namespaces/team-a/blobs/sha256/{}/{}",
+                &hash[..2],
+                hash
+            )
+        );
+
+        assert!(storage.namespace("../escape").await.is_err());
+    }
+}
@@ -0,0 +1,670 @@
+//! Tiered storage backend
+//!
+//! Keeps every blob in a durable cold tier (S3) and serves frequently used
+//! blobs from a bounded local hot tier. Blobs are written through to both
+//! tiers, promoted to the hot tier when read from the cold tier, and demoted
+//! (deleted locally only) when the hot tier runs out of space. A blob only
+//! enters the hot tier once it is in the cold tier, so a crash never leaves
+//! a hot blob that the cold tier lacks.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+use crate::backend::{
+    ByteStream, ChunkedUploadState, StorageBackend, StoredBlob, UploadFile, validate_namespace,
+};
+use crate::error::StorageError;
+use crate::local::LocalStorage;
+
+/// Policy choosing which hot blobs to demote first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DemotionPolicy {
+    /// Least recently accessed first
+    #[default]
+    Lru,
+    /// Least frequently accessed first (ties broken by recency)
+    Lfu,
+    /// Oldest in the hot tier first
+    Fifo,
+}
+
+/// Tiered storage configuration
+#[derive(Debug, Clone)]
+pub struct TieredConfig {
+    /// Maximum size of the hot tier in bytes
+    pub hot_max_size: u64,
+    pub policy: DemotionPolicy,
+}
+
+/// Access bookkeeping of a blob in the hot tier
+#[derive(Debug, Clone, Copy)]
+struct HotBlob {
+    size: u64,
+    inserted: u64,
+    accessed: u64,
+    hits: u64,
+}
+
+/// In-memory index of the hot tier. Access order uses a logical clock.
+#[derive(Debug, Default)]
+struct HotIndex {
+    blobs: HashMap<String, HotBlob>,
+    total_size: u64,
+    clock: u64,
+}
+
+impl HotIndex {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn insert(&mut self, digest: &str, size: u64) {
+        let now = self.tick();
+        let blob = HotBlob {
+            size,
+            inserted: now,
+            accessed: now,
+            hits: 1,
+        };
+        if let Some(previous) = self.blobs.insert(digest.to_string(), blob) {
+            self.total_size -= previous.size;
+        }
+        self.total_size += size;
+    }
+
+    /// Record an access; returns whether the blob is hot
+    fn touch(&mut self, digest: &str) -> bool {
+        let now = self.tick();
+        match self.blobs.get_mut(digest) {
+            Some(blob) => {
+                blob.accessed = now;
+                blob.hits += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn contains(&self, digest: &str) -> bool {
+        self.blobs.contains_key(digest)
+    }
+
+    fn remove(&mut self, digest: &str) -> bool {
+        match self.blobs.remove(digest) {
+            Some(blob) => {
+                self.total_size -= blob.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove blobs in policy order until `required` more bytes fit within
+    /// `max_size`, returning the removed digests
+    fn take_victims(
+        &mut self,
+        required: u64,
+        max_size: u64,
+        policy: DemotionPolicy,
+    ) -> Vec<String> {
+        let mut victims = Vec::new();
+        while self.total_size + required > max_size {
+            let victim = self
+                .blobs
+                .iter()
+                .min_by_key(|(_, blob)| match policy {
+                    DemotionPolicy::Lru => (blob.accessed, 0),
+                    DemotionPolicy::Lfu => (blob.hits, blob.accessed),
+                    DemotionPolicy::Fifo => (blob.inserted, 0),
+                })
+                .map(|(digest, _)| digest.clone());
+            let Some(victim) = victim else {
+                break;
+            };
+            self.remove(&victim);
+            victims.push(victim);
+        }
+        victims
+    }
+}
+
+/// State shared with background promotions
+struct Tiers {
+    hot: LocalStorage,
+    /// Streamed writes land here until the cold tier has them
+    staging: LocalStorage,
+    cold: Arc<dyn StorageBackend>,
+    config: TieredConfig,
+    index: Mutex<HotIndex>,
+    /// Digests being promoted in the background
+    promoting: Mutex<HashSet<String>>,
+}
+
+impl Tiers {
+    /// Record an access; returns whether the blob is hot
+    fn touch(&self, digest: &str) -> bool {
+        self.index.lock().unwrap().touch(digest)
+    }
+
+    /// Drop a blob from the index whose hot copy disappeared (demoted
+    /// concurrently), so it is served from the cold tier
+    fn forget(&self, digest: &str) {
+        self.index.lock().unwrap().remove(digest);
+    }
+
+    /// Demote hot blobs until `size` more bytes fit in the hot tier
+    async fn make_room(&self, size: u64) {
+        let victims = self.index.lock().unwrap().take_victims(
+            size,
+            self.config.hot_max_size,
+            self.config.policy,
+        );
+        for digest in victims {
+            debug!("Demoting {} from hot tier", digest);
+            if let Err(e) = self.hot.delete(&digest).await {
+                warn!("Failed to demote {} from hot tier: {}", digest, e);
+            }
+        }
+    }
+
+    /// Store a blob in the hot tier if it fits
+    async fn store_hot(&self, digest: &str, data: Bytes) -> Result<(), StorageError> {
+        let size = data.len() as u64;
+        if size > self.config.hot_max_size {
+            return Ok(());
+        }
+        self.make_room(size).await;
+        self.hot.write(digest, data).await?;
+        self.index.lock().unwrap().insert(digest, size);
+        Ok(())
+    }
+
+    /// Move a staged blob, already in the cold tier, into the hot tier
+    async fn adopt_staged(&self, digest: &str) -> Result<(), StorageError> {
+        let staged = self.staging.blob_path(digest)?;
+        let size = fs::metadata(&staged).await?.len();
+        self.make_room(size).await;
+
+        let path = self.hot.blob_path(digest)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(&staged, &path).await?;
+        self.index.lock().unwrap().insert(digest, size);
+        Ok(())
+    }
+
+    /// Copy a blob from the cold tier into the hot tier
+    async fn promote(&self, digest: &str) -> Result<(), StorageError> {
+        let size = self.cold.size(digest).await?;
+        if size > self.config.hot_max_size || self.index.lock().unwrap().contains(digest) {
+            return Ok(());
+        }
+        self.make_room(size).await;
+        let stream = self.cold.stream(digest).await?;
+        self.hot.write_stream(digest, stream, Some(size)).await?;
+        self.index.lock().unwrap().insert(digest, size);
+        debug!("Promoted {} to hot tier ({} bytes)", digest, size);
+        Ok(())
+    }
+}
+
+/// Spawn a background promotion unless one is already running
+fn spawn_promotion(tiers: &Arc<Tiers>, digest: &str) {
+    if !tiers.promoting.lock().unwrap().insert(digest.to_string()) {
+        return;
+    }
+    let tiers = tiers.clone();
+    let digest = digest.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = tiers.promote(&digest).await {
+            warn!("Failed to promote {} to hot tier: {}", digest, e);
+        }
+        tiers.promoting.lock().unwrap().remove(&digest);
+    });
+}
+
+/// Tiered storage backend: a bounded local hot tier in front of a durable
+/// cold tier holding every blob
+///
+/// Chunked uploads, listings and presigned URLs are served by the cold
+/// tier. The hot tier directory must not be shared with other storage.
+///
+/// A namespace combines namespaces of both tiers and has a hot tier of its
+/// own, bounded by the same `hot_max_size`.
+pub struct TieredStorage {
+    tiers: Arc<Tiers>,
+    hot_path: PathBuf,
+}
+
+impl TieredStorage {
+    /// Create a tiered backend, indexing the blobs already in the hot tier
+    pub async fn new(
+        hot_path: impl AsRef<Path>,
+        cold: Arc<dyn StorageBackend>,
+        config: TieredConfig,
+    ) -> Result<Self, StorageError> {
+        let hot_path = hot_path.as_ref().to_path_buf();
+        let hot = LocalStorage::new(&hot_path).await?;
+
+        // Staged writes interrupted by a restart may be missing from the
+        // cold tier, so they are dropped
+        let staging_path = hot_path.join("staging");
+        if let Err(e) = fs::remove_dir_all(&staging_path).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            return Err(e.into());
+        }
+        let staging = LocalStorage::new(&staging_path).await?;
+
+        let mut index = HotIndex::default();
+        for blob in hot.list_blobs().await? {
+            index.insert(&blob.digest, blob.size);
+        }
+        info!(
+            "Initialized tiered storage: {} hot blobs ({} of {} bytes), policy {:?}",
+            index.blobs.len(),
+            index.total_size,
+            config.hot_max_size,
+            config.policy
+        );
+
+        let tiers = Arc::new(Tiers {
+            hot,
+            staging,
+            cold,
+            config,
+            index: Mutex::new(index),
+            promoting: Mutex::new(HashSet::new()),
+        });
+        // Demote right away if the hot tier was shrunk
+        tiers.make_room(0).await;
+
+        Ok(Self { tiers, hot_path })
+    }
+
+    /// Whether a blob is in the hot tier
+    pub fn is_hot(&self, digest: &str) -> bool {
+        self.tiers.index.lock().unwrap().contains(digest)
+    }
+
+    /// Bytes held in the hot tier
+    pub fn hot_size(&self) -> u64 {
+        self.tiers.index.lock().unwrap().total_size
+    }
+}
+
+#[async_trait]
+impl StorageBackend for TieredStorage {
+    async fn exists(&self, digest: &str) -> Result<bool, StorageError> {
+        if self.is_hot(digest) {
+            return Ok(true);
+        }
+        self.tiers.cold.exists(digest).await
+    }
+
+    async fn size(&self, digest: &str) -> Result<u64, StorageError> {
+        let hot_size = self
+            .tiers
+            .index
+            .lock()
+            .unwrap()
+            .blobs
+            .get(digest)
+            .map(|blob| blob.size);
+        match hot_size {
+            Some(size) => Ok(size),
+            None => self.tiers.cold.size(digest).await,
+        }
+    }
+
+    async fn read(&self, digest: &str) -> Result<Bytes, StorageError> {
+        if self.tiers.touch(digest) {
+            match self.tiers.hot.read(digest).await {
+                Err(StorageError::NotFound(_)) => self.tiers.forget(digest),
+                result => return result,
+            }
+        }
+
+        let data = self.tiers.cold.read(digest).await?;
+        if let Err(e) = self.tiers.store_hot(digest, data.clone()).await {
+            warn!("Failed to promote {} to hot tier: {}", digest, e);
+        }
+        Ok(data)
+    }
+
+    async fn read_range(&self, digest: &str, start: u64, end: u64) -> Result<Bytes, StorageError> {
+        if self.tiers.touch(digest) {
+            match self.tiers.hot.read_range(digest, start, end).await {
+                Err(StorageError::NotFound(_)) => self.tiers.forget(digest),
+                result => return result,
+            }
+        }
+
+        let data = self.tiers.cold.read_range(digest, start, end).await?;
+        spawn_promotion(&self.tiers, digest);
+        Ok(data)
+    }
+
+    async fn stream(&self, digest: &str) -> Result<ByteStream, StorageError> {
+        if self.tiers.touch(digest) {
+            match self.tiers.hot.stream(digest).await {
+                Err(StorageError::NotFound(_)) => self.tiers.forget(digest),
+                result => return result,
+            }
+        }
+
+        let stream = self.tiers.cold.stream(digest).await?;
+        spawn_promotion(&self.tiers, digest);
+        Ok(stream)
+    }
+
+    async fn stream_range(
+        &self,
+        digest: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<ByteStream, StorageError> {
+        if self.tiers.touch(digest) {
+            match self.tiers.hot.stream_range(digest, start, end).await {
+                Err(StorageError::NotFound(_)) => self.tiers.forget(digest),
+                result => return result,
+            }
+        }
+
+        let stream = self.tiers.cold.stream_range(digest, start, end).await?;
+        spawn_promotion(&self.tiers, digest);
+        Ok(stream)
+    }
+
+    async fn verify_stream(&self, digest: &str) -> Result<ByteStream, StorageError> {
+        if self.is_hot(digest) {
+            match self.tiers.hot.stream(digest).await {
+                Err(StorageError::NotFound(_)) => {}
+                result => return result,
+            }
+        }
+        self.tiers.cold.stream(digest).await
+    }
+
+    async fn write(&self, digest: &str, data: Bytes) -> Result<String, StorageError> {
+        // The cold tier is written first so every hot blob is durable
+        let path = self.tiers.cold.write(digest, data.clone()).await?;
+        if let Err(e) = self.tiers.store_hot(digest, data).await {
+            warn!("Failed to write {} to hot tier: {}", digest, e);
+        }
+        Ok(path)
+    }
+
+    async fn write_stream(
+        &self,
+        digest: &str,
+        stream: ByteStream,
+        expected_size: Option<u64>,
+    ) -> Result<String, StorageError> {
+        let hot_fits = expected_size.is_some_and(|size| size <= self.tiers.config.hot_max_size);
+        if !hot_fits {
+            return self
+                .tiers
+                .cold
+                .write_stream(digest, stream, expected_size)
+                .await;
+        }
+
+        // Land the stream on local disk first, upload it from there and
+        // only then move it into the hot tier
+        let staging = &self.tiers.staging;
+        staging.write_stream(digest, stream, expected_size).await?;
+        let uploaded = async {
+            let local = staging.stream(digest).await?;
+            self.tiers
+                .cold
+                .write_stream(digest, local, expected_size)
+                .await
+        }
+        .await;
+        let path = match uploaded {
+            Ok(path) => path,
+            Err(e) => {
+                let _ = staging.delete(digest).await;
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = self.tiers.adopt_staged(digest).await {
+            warn!("Failed to move {} into hot tier: {}", digest, e);
+            let _ = staging.delete(digest).await;
+        }
+        Ok(path)
+    }
+
+    async fn delete(&self, digest: &str) -> Result<bool, StorageError> {
+        self.tiers.forget(digest);
+        self.tiers.hot.delete(digest).await?;
+        self.tiers.cold.delete(digest).await
+    }
+
+    fn storage_path(&self, digest: &str) -> String {
+        self.tiers.cold.storage_path(digest)
+    }
+
+    async fn init_chunked_upload(&self, session_id: &str) -> Result<String, StorageError> {
+        self.tiers.cold.init_chunked_upload(session_id).await
+    }
+
+    async fn append_chunk(
+        &self,
+        session_id: &str,
+        state: &mut ChunkedUploadState,
+        data: Bytes,
+    ) -> Result<u64, StorageError> {
+        self.tiers.cold.append_chunk(session_id, state, data).await
+    }
+
+    async fn complete_chunked_upload(
+        &self,
+        session_id: &str,
+        state: &ChunkedUploadState,
+        digest: &str,
+    ) -> Result<String, StorageError> {
+        self.tiers
+            .cold
+            .complete_chunked_upload(session_id, state, digest)
+            .await
+    }
+
+    async fn cancel_chunked_upload(
+        &self,
+        session_id: &str,
+        state: &ChunkedUploadState,
+    ) -> Result<(), StorageError> {
+        self.tiers
+            .cold
+            .cancel_chunked_upload(session_id, state)
+            .await
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<UploadFile>, StorageError> {
+        self.tiers.cold.list_uploads().await
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<StoredBlob>, StorageError> {
+        self.tiers.cold.list_blobs().await
+    }
+
+    async fn get_presigned_url(
+        &self,
+        digest: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<String>, StorageError> {
+        // Hot blobs are served locally; cold ones are redirected and warmed
+        if self.tiers.touch(digest) {
+            return Ok(None);
+        }
+        let url = self.tiers.cold.get_presigned_url(digest, ttl_secs).await?;
+        if url.is_some() {
+            spawn_promotion(&self.tiers, digest);
+        }
+        Ok(url)
+    }
+
+    async fn namespace(&self, name: &str) -> Result<Arc<dyn StorageBackend>, StorageError> {
+        validate_namespace(name)?;
+        let storage = TieredStorage::new(
+            self.hot_path.join("namespaces").join(name),
+            self.tiers.cold.namespace(name).await?,
+            self.tiers.config.clone(),
+        )
+        .await?;
+        Ok(Arc::new(storage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::compute_sha256;
+
+    fn hot_index() -> HotIndex {
+        let mut index = HotIndex::default();
+        index.insert("a", 10);
+        index.insert("b", 10);
+        index.insert("c", 10);
+        // c is used twice, then a once
+        index.touch("c");
+        index.touch("c");
+        index.touch("a");
+        index
+    }
+
+    #[test]
+    fn test_victims_follow_policy() {
+        let mut index = hot_index();
+        assert_eq!(index.take_victims(10, 30, DemotionPolicy::Lru), vec!["b"]);
+
+        let mut index = hot_index();
+        assert_eq!(index.take_victims(10, 30, DemotionPolicy::Lfu), vec!["b"]);
+        assert_eq!(index.take_victims(10, 20, DemotionPolicy::Lfu), vec!["a"]);
+
+        let mut index = hot_index();
+        assert_eq!(
+            index.take_victims(15, 30, DemotionPolicy::Fifo),
+            vec!["a", "b"]
+        );
+        assert_eq!(index.total_size, 10);
+    }
+
+    #[tokio::test]
+    async fn test_promotes_on_access_and_demotes_under_pressure() {
+        let hot_dir = tempfile::tempdir().unwrap();
+        let cold_dir = tempfile::tempdir().unwrap();
+        let cold: Arc<dyn StorageBackend> =
+            Arc::new(LocalStorage::new(cold_dir.path()).await.unwrap());
+        let storage = TieredStorage::new(
+            hot_dir.path(),
+            cold.clone(),
+            TieredConfig {
+                hot_max_size: 8,
+                policy: DemotionPolicy::Lru,
+            },
+        )
+        .await
+        .unwrap();
+
+        let blobs: Vec<(String, Bytes)> = [&b"blob-one"[..], b"blob-two"]
+            .into_iter()
+            .map(|data| (compute_sha256(data), Bytes::copy_from_slice(data)))
+            .collect();
+        let (one, two) = (&blobs[0].0, &blobs[1].0);
+
+        // Written through to both tiers; the second write demotes the first
+        storage.write(one, blobs[0].1.clone()).await.unwrap();
+        storage.write(two, blobs[1].1.clone()).await.unwrap();
+        assert!(cold.exists(one).await.unwrap());
+        assert!(cold.exists(two).await.unwrap());
+        assert!(!storage.is_hot(one));
+        assert!(storage.is_hot(two));
+        assert_eq!(storage.hot_size(), 8);
+
+        // Reading a cold blob promotes it
+        assert_eq!(storage.read(one).await.unwrap(), blobs[0].1);
+        assert!(storage.is_hot(one));
+        assert!(!storage.is_hot(two));
+        assert!(storage.exists(two).await.unwrap());
+
+        // Blobs larger than the hot tier stay cold
+        let large = Bytes::from_static(b"larger than the hot tier");
+        let large_digest = compute_sha256(&large);
+        storage.write(&large_digest, large.clone()).await.unwrap();
+        assert!(!storage.is_hot(&large_digest));
+        assert_eq!(storage.read(&large_digest).await.unwrap(), large);
+
+        // The hot tier is re-indexed on restart
+        drop(storage);
+        let storage = TieredStorage::new(
+            hot_dir.path(),
+            cold.clone(),
+            TieredConfig {
+                hot_max_size: 8,
+                policy: DemotionPolicy::Lru,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(storage.is_hot(one));
+
+        storage.delete(one).await.unwrap();
+        assert!(!storage.is_hot(one));
+        assert!(!cold.exists(one).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_writes_reach_cold_tier_before_hot_tier() {
+        let hot_dir = tempfile::tempdir().unwrap();
+        let cold_dir = tempfile::tempdir().unwrap();
+        let cold: Arc<dyn StorageBackend> =
+            Arc::new(LocalStorage::new(cold_dir.path()).await.unwrap());
+        let config = TieredConfig {
+            hot_max_size: 64,
+            policy: DemotionPolicy::Lru,
+        };
+        let storage = TieredStorage::new(hot_dir.path(), cold.clone(), config.clone())
+            .await
+            .unwrap();
+
+        let data = Bytes::from_static(b"streamed blob");
+        let digest = compute_sha256(&data);
+        let stream: ByteStream = Box::pin(futures::stream::iter([Ok(data.clone())]));
+        storage
+            .write_stream(&digest, stream, Some(data.len() as u64))
+            .await
+            .unwrap();
+        assert!(cold.exists(&digest).await.unwrap());
+        assert!(storage.is_hot(&digest));
+        assert!(!storage.tiers.staging.exists(&digest).await.unwrap());
+
+        // A write interrupted before the upload finished is not adopted
+        let staged = Bytes::from_static(b"staged blob");
+        let staged_digest = compute_sha256(&staged);
+        storage
+            .tiers
+            .staging
+            .write(&staged_digest, staged)
+            .await
+            .unwrap();
+        drop(storage);
+        let storage = TieredStorage::new(hot_dir.path(), cold.clone(), config)
+            .await
+            .unwrap();
+        assert!(storage.is_hot(&digest));
+        assert!(!storage.is_hot(&staged_digest));
+        assert!(!storage.tiers.staging.exists(&staged_digest).await.unwrap());
+        assert!(!storage.exists(&staged_digest).await.unwrap());
+    }
+}
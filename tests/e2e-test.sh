@@ -74,6 +74,14 @@ get_cache_token() {
         | jq -r '.token'
 }
 
+# Get a registry pull token for a repository from Harbor Cache
+get_registry_token() {
+    local repo=$1
+    curl -s -u "${CACHE_USER}:${CACHE_PASS}" \
+        "${HARBOR_CACHE_URL}/service/token?service=harbor-cache&scope=repository:${repo}:pull" \
+        | jq -r '.token'
+}
+
 # Get cache stats
 get_cache_stats() {
     local token=$1
@@ -188,6 +196,7 @@ test_basic_pull() {
     # First, check manifest via OCI API
     local manifest_response=$(curl -s -w "\n%{http_code}" \
         "${HARBOR_CACHE_URL}/v2/library/alpine/manifests/latest" \
+        -H "Authorization: Bearer $(get_registry_token library/alpine)" \
         -H "Accept: application/vnd.oci.image.manifest.v1+json,application/vnd.docker.distribution.manifest.v2+json,application/vnd.oci.image.index.v1+json,application/vnd.docker.distribution.manifest.list.v2+json")
 
     local http_code=$(echo "$manifest_response" | tail -1)
@@ -210,6 +219,7 @@ test_basic_pull() {
         local initial_hits=$(echo "$after_stats" | jq -r '.hit_count')
 
         curl -s "${HARBOR_CACHE_URL}/v2/library/alpine/manifests/latest" \
+            -H "Authorization: Bearer $(get_registry_token library/alpine)" \
             -H "Accept: application/vnd.oci.image.manifest.v1+json,application/vnd.docker.distribution.manifest.v2+json" \
             > /dev/null
 
@@ -275,6 +285,7 @@ test_oci_multiarch_image() {
     # Request with OCI image index accept header (for multi-arch)
     local manifest_response=$(curl -s -w "\n%{http_code}" \
         "${HARBOR_CACHE_URL}/v2/${test_repo}/manifests/${test_tag}" \
+        -H "Authorization: Bearer $(get_registry_token "${test_repo}")" \
         -H "Accept: application/vnd.oci.image.index.v1+json,application/vnd.docker.distribution.manifest.list.v2+json,application/vnd.oci.image.manifest.v1+json,application/vnd.docker.distribution.manifest.v2+json")
 
     local http_code=$(echo "$manifest_response" | tail -1)
@@ -309,6 +320,7 @@ test_oci_multiarch_image() {
 
             local arch_response=$(curl -s -w "\n%{http_code}" \
                 "${HARBOR_CACHE_URL}/v2/${test_repo}/manifests/${amd64_digest}" \
+                -H "Authorization: Bearer $(get_registry_token "${test_repo}")" \
                 -H "Accept: application/vnd.oci.image.manifest.v1+json,application/vnd.docker.distribution.manifest.v2+json")
 
             local arch_http_code=$(echo "$arch_response" | tail -1)
@@ -330,6 +342,7 @@ test_oci_multiarch_image() {
                     log_info "Fetching first layer blob: $first_layer"
 
                     local blob_response=$(curl -s -w "%{http_code}" -o /dev/null \
+                        -H "Authorization: Bearer $(get_registry_token "${test_repo}")" \
                         "${HARBOR_CACHE_URL}/v2/${test_repo}/blobs/${first_layer}")
 
                     if [ "$blob_response" == "200" ]; then
@@ -387,6 +400,7 @@ test_oci_manifest_types() {
     log_info "Requesting with OCI Image Index Accept header..."
     local oci_response=$(curl -s -D - -o /dev/null \
         "${HARBOR_CACHE_URL}/v2/${test_repo}/manifests/${test_tag}" \
+        -H "Authorization: Bearer $(get_registry_token "${test_repo}")" \
         -H "Accept: application/vnd.oci.image.index.v1+json" 2>&1)
 
     local content_type=$(echo "$oci_response" | grep -i "content-type:" | head -1 | tr -d '\r')
@@ -396,6 +410,7 @@ test_oci_manifest_types() {
     log_info "Requesting with Docker Manifest List Accept header..."
     local docker_response=$(curl -s -D - -o /dev/null \
         "${HARBOR_CACHE_URL}/v2/${test_repo}/manifests/${test_tag}" \
+        -H "Authorization: Bearer $(get_registry_token "${test_repo}")" \
         -H "Accept: application/vnd.docker.distribution.manifest.list.v2+json" 2>&1)
 
     content_type=$(echo "$docker_response" | grep -i "content-type:" | head -1 | tr -d '\r')
@@ -422,6 +437,7 @@ test_docker_pull_through_cache() {
 
     # Try to pull through the cache (this may fail if Docker isn't configured for insecure registries)
     log_info "Attempting Docker pull from localhost:5001/library/alpine:latest..."
+    echo "${CACHE_PASS}" | docker login localhost:5001 -u "${CACHE_USER}" --password-stdin >/dev/null 2>&1 || true
 
     if docker pull localhost:5001/library/alpine:latest 2>/dev/null; then
         log_success "Docker pull through cache succeeded"